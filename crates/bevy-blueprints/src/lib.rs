@@ -1,11 +1,16 @@
-mod prelude {
+#![doc = include_str!("../README.md")]
+
+pub mod prelude {
+	pub use crate::app_ext::BlueprintAppExt;
+	pub use crate::components::*;
 	pub use crate::plugin::*;
 	pub use crate::sets::*;
-	pub use crate::components::*;
+	pub use crate::traits::*;
 
 	// bevy
-	pub use bevy::prelude::*;
-	pub use bevy::ecs::schedule::{ScheduleLabel, InternedScheduleLabel};
+	pub(crate) use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
+	pub(crate) use bevy::ecs::system::{StaticSystemParam, SystemParam, SystemParamItem};
+	pub(crate) use bevy::prelude::*;
 }
 
 mod plugin {
@@ -16,21 +21,44 @@ mod plugin {
 		schedule: InternedScheduleLabel,
 	}
 
+	/// Which [Schedule] the [BlueprintsPlugin] was configured for,
+	/// so that [crate::prelude::BlueprintAppExt::register_blueprint] knows where to add systems
+	#[derive(Resource, Debug, Clone, Copy)]
+	pub(crate) struct BlueprintsSchedule(pub(crate) InternedScheduleLabel);
+
 	impl Plugin for BlueprintsPlugin {
 		fn build(&self, app: &mut App) {
 			type BS = BlueprintsSet;
 			app
 				.register_type::<BlueprintNeedsUpdating>()
+				.register_type::<FreshlyExpanded>()
+				.insert_resource(BlueprintsSchedule(self.schedule))
 				.configure_sets(
 					self.schedule,
 					(
+						BS::ClearFreshlyExpanded,
 						BS::ApplyDeferred1,
 						BS::MarkChanged,
 						BS::ApplyDeferred2,
 						BS::ExpandBlueprints,
 						BS::ApplyDeferred3,
+						BS::MarkChildrenChanged,
+						BS::ApplyDeferred4,
+						BS::ExpandChildBlueprints,
+						BS::ApplyDeferred5,
 					)
 						.chain(),
+				)
+				.add_systems(
+					self.schedule,
+					(
+						Self::clear_freshly_expanded.in_set(BS::ClearFreshlyExpanded),
+						apply_deferred.in_set(BS::ApplyDeferred1),
+						apply_deferred.in_set(BS::ApplyDeferred2),
+						apply_deferred.in_set(BS::ApplyDeferred3),
+						apply_deferred.in_set(BS::ApplyDeferred4),
+						apply_deferred.in_set(BS::ApplyDeferred5),
+					),
 				);
 		}
 	}
@@ -41,6 +69,15 @@ mod plugin {
 				schedule: schedule.intern(),
 			}
 		}
+
+		fn clear_freshly_expanded(
+			markers: Query<Entity, With<FreshlyExpanded>>,
+			mut commands: Commands,
+		) {
+			for entity in markers.iter() {
+				commands.entity(entity).remove::<FreshlyExpanded>();
+			}
+		}
 	}
 }
 
@@ -50,6 +87,9 @@ mod sets {
 	/// Set that is initialized by the [BlueprintsPlugin].
 	#[derive(SystemSet, Hash, Clone, Eq, PartialEq, Debug)]
 	pub enum BlueprintsSet {
+		/// Removes the [FreshlyExpanded] markers from the last run
+		ClearFreshlyExpanded,
+
 		ApplyDeferred1,
 
 		/// Adds the [BlueprintNeedsUpdating] marker component to
@@ -62,6 +102,17 @@ mod sets {
 		ExpandBlueprints,
 
 		ApplyDeferred3,
+
+		/// Same as [BlueprintsSet::MarkChanged], for blueprints that were just
+		/// spawned by other blueprints, see [crate::prelude::BlueprintAppExt::register_child_blueprint]
+		MarkChildrenChanged,
+
+		ApplyDeferred4,
+
+		/// Same as [BlueprintsSet::ExpandBlueprints], for child blueprints
+		ExpandChildBlueprints,
+
+		ApplyDeferred5,
 	}
 }
 
@@ -71,10 +122,141 @@ mod components {
 	/// Marker [Component] that communicates an [Entity] is still in the
 	/// process of being expanded.
 	/// Added in the [BlueprintsSet] [BlueprintsSet::MarkChanged],
-	/// and maybe removed in the [BlueprintsSet::ExpandBlueprints] depending
-	/// on implementation details per blueprint type.
+	/// and removed in the [BlueprintsSet::ExpandBlueprints] once expanded.
 	#[derive(Component, Reflect, Debug, Default)]
+	#[component(storage = "SparseSet")]
 	pub struct BlueprintNeedsUpdating;
+
+	/// If an entity has this component, it means that its blueprint was just expanded.
+	/// Removed at the start of the next run of the [BlueprintsPlugin]'s schedule,
+	/// so systems can use `Added<FreshlyExpanded>`.
+	#[derive(Component, Reflect, Debug, Default)]
+	#[component(storage = "SparseSet")]
+	pub struct FreshlyExpanded;
+}
+
+mod traits {
+	use serde::{de::DeserializeOwned, Serialize};
+
+	use crate::prelude::*;
+
+	/// Represents a type [Blueprint] that can be [Blueprint::stamp]ed into
+	/// a bundle that can be spawned, i.e., a [Bundle] that is specifically
+	/// [Blueprint::Bundle]
+	pub trait Blueprint: std::fmt::Debug {
+		/// The bundle type that this blueprint can be stamped into.
+		type Bundle: Bundle;
+		/// A way to access the world when stamping, for things like
+		/// [AssetServer] or [ResMut<Assets<Mesh>>].
+		/// Use `'static` lifetimes, e.g. `Res<'static, AssetServer>`.
+		type StampSystemParam: SystemParam + 'static;
+
+		/// Stamps this blueprint into a bundle that can be spawned.
+		fn stamp(
+			&self,
+			system_param: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) -> Self::Bundle;
+
+		/// Expands this blueprint onto `entity`, whenever the blueprint component changes.
+		/// By default, stamps the [Blueprint::Bundle] on top of the entity.
+		///
+		/// Override to also spawn children.
+		fn expand(
+			&self,
+			entity: Entity,
+			commands: &mut Commands,
+			system_param: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) {
+			commands.entity(entity).insert(self.stamp(system_param));
+		}
+	}
+
+	/// A blueprint that is synced over the network.
+	/// Hence, it must be serializable and deserializable,
+	/// and contain at least a serializable component.
+	///
+	/// Only the [NetworkedBlueprintBundle::NetworkedBlueprintComponent] needs to be synced,
+	/// everything else is derived from it by registering it with
+	/// [crate::prelude::BlueprintAppExt::register_blueprint].
+	pub trait NetworkedBlueprintBundle:
+		Bundle + std::ops::Deref<Target = Self::NetworkedBlueprintComponent>
+	{
+		type NetworkedBlueprintComponent: Component + Serialize + DeserializeOwned + Blueprint;
+	}
+}
+
+mod app_ext {
+	use crate::{plugin::BlueprintsSchedule, prelude::*};
+
+	pub trait BlueprintAppExt {
+		/// Expands entities whenever their `B` component is added or changed,
+		/// using [Blueprint::expand].
+		///
+		/// Requires the [BlueprintsPlugin] to be added first.
+		fn register_blueprint<B: Blueprint + Component>(&mut self) -> &mut Self;
+
+		/// Same as [BlueprintAppExt::register_blueprint], but expanded after
+		/// all of those in the same run.
+		/// Use for blueprints that are spawned by other blueprints,
+		/// so that they are fully expanded in the same run.
+		fn register_child_blueprint<B: Blueprint + Component>(&mut self) -> &mut Self;
+	}
+
+	impl BlueprintAppExt for App {
+		fn register_blueprint<B: Blueprint + Component>(&mut self) -> &mut Self {
+			register::<B>(self, BlueprintsSet::MarkChanged, BlueprintsSet::ExpandBlueprints)
+		}
+
+		fn register_child_blueprint<B: Blueprint + Component>(&mut self) -> &mut Self {
+			register::<B>(
+				self,
+				BlueprintsSet::MarkChildrenChanged,
+				BlueprintsSet::ExpandChildBlueprints,
+			)
+		}
+	}
+
+	fn register<B: Blueprint + Component>(
+		app: &mut App,
+		mark: BlueprintsSet,
+		expand: BlueprintsSet,
+	) -> &mut App {
+		let BlueprintsSchedule(schedule) = *app
+			.world
+			.get_resource::<BlueprintsSchedule>()
+			.expect("Add the BlueprintsPlugin before registering blueprints");
+		app.add_systems(
+			schedule,
+			(
+				mark_changed::<B>.in_set(mark),
+				// expanding different blueprints only touches different entities
+				expand_blueprints::<B>
+					.in_set(expand.clone())
+					.ambiguous_with(expand),
+			),
+		)
+	}
+
+	fn mark_changed<B: Component>(changed: Query<Entity, Changed<B>>, mut commands: Commands) {
+		for entity in changed.iter() {
+			commands.entity(entity).insert(BlueprintNeedsUpdating);
+		}
+	}
+
+	fn expand_blueprints<B: Blueprint + Component>(
+		instances: Query<(Entity, &B), With<BlueprintNeedsUpdating>>,
+		mut commands: Commands,
+		mut system_param: StaticSystemParam<'_, '_, B::StampSystemParam>,
+	) {
+		for (entity, blueprint) in instances.iter() {
+			trace!("Expanding blueprint: {:?}", blueprint);
+			blueprint.expand(entity, &mut commands, &mut system_param);
+			commands
+				.entity(entity)
+				.remove::<BlueprintNeedsUpdating>()
+				.insert(FreshlyExpanded);
+		}
+	}
 }
 
 #[cfg(test)]
@@ -82,7 +264,58 @@ mod tests {
 	use crate::prelude::*;
 
 	fn test_app() -> App {
-		App::new()
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, BlueprintsPlugin::new(Update)));
+		app
+	}
+
+	#[derive(Component, Debug)]
+	struct ShipBlueprint(u32);
+
+	#[derive(Component, Debug)]
+	struct Ship(u32);
+
+	impl Blueprint for ShipBlueprint {
+		type Bundle = Ship;
+		type StampSystemParam = ();
+
+		fn stamp(&self, _: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
+			Ship(self.0)
+		}
+
+		fn expand(
+			&self,
+			entity: Entity,
+			commands: &mut Commands,
+			system_param: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) {
+			commands
+				.entity(entity)
+				.despawn_descendants()
+				.insert(self.stamp(system_param))
+				.with_children(|parent| {
+					parent.spawn(PartBlueprint);
+				});
+		}
+	}
+
+	#[derive(Component, Debug)]
+	struct PartBlueprint;
+
+	#[derive(Component, Debug)]
+	struct Part;
+
+	impl Blueprint for PartBlueprint {
+		type Bundle = Part;
+		type StampSystemParam = ();
+
+		fn stamp(&self, _: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
+			Part
+		}
+	}
+
+	fn count<F: bevy::ecs::query::ReadOnlyWorldQuery>(app: &mut App) -> usize {
+		app.world.query_filtered::<(), F>().iter(&app.world).count()
 	}
 
 	#[derive(ScheduleLabel, Hash, Clone, Copy, PartialEq, Eq, Debug)]
@@ -90,8 +323,58 @@ mod tests {
 
 	#[test]
 	fn plugin_initializes() {
-		let mut app = test_app();
+		let mut app = App::new();
 
 		app.add_plugins((MinimalPlugins, BlueprintsPlugin::new(BlueprintSchedule)));
 	}
+
+	#[test]
+	#[should_panic]
+	fn registering_needs_plugin() {
+		App::new().register_blueprint::<ShipBlueprint>();
+	}
+
+	#[test]
+	fn expands_added_blueprints() {
+		let mut app = test_app();
+		app.register_blueprint::<ShipBlueprint>();
+
+		let entity = app.world.spawn(ShipBlueprint(1)).id();
+		app.update();
+
+		assert_eq!(app.world.get::<Ship>(entity).unwrap().0, 1);
+		assert!(app.world.get::<FreshlyExpanded>(entity).is_some());
+		assert!(app.world.get::<BlueprintNeedsUpdating>(entity).is_none());
+
+		app.update();
+		assert!(app.world.get::<FreshlyExpanded>(entity).is_none());
+	}
+
+	#[test]
+	fn re_expands_changed_blueprints() {
+		let mut app = test_app();
+		app.register_blueprint::<ShipBlueprint>();
+
+		let entity = app.world.spawn(ShipBlueprint(1)).id();
+		app.update();
+		app.world.get_mut::<ShipBlueprint>(entity).unwrap().0 = 2;
+		app.update();
+
+		assert_eq!(app.world.get::<Ship>(entity).unwrap().0, 2);
+		assert!(app.world.get::<FreshlyExpanded>(entity).is_some());
+	}
+
+	#[test]
+	fn expands_child_blueprints_in_the_same_run() {
+		let mut app = test_app();
+		app
+			.register_blueprint::<ShipBlueprint>()
+			.register_child_blueprint::<PartBlueprint>();
+
+		app.world.spawn(ShipBlueprint(1));
+		app.update();
+
+		assert_eq!(count::<With<Part>>(&mut app), 1);
+		assert_eq!(count::<(With<PartBlueprint>, With<FreshlyExpanded>)>(&mut app), 1);
+	}
 }
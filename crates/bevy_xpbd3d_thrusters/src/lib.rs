@@ -7,10 +7,11 @@ pub mod prelude {
 		components::{CurrentVelocity, IntendedVelocity, Thruster},
 		ForceAxis, Relative6DVector, Vec6,
 	};
+	pub use crate::strategies::{ExactAxisStrategy, OptimalAllocationStrategy, PureStrategy};
 
 	pub use bevy_xpbd_3d_parenting::prelude::*;
 
-	pub(crate) use crate::impl_relative_6d_vector;
+	pub(crate) use crate::{impl_from_vec6, impl_relative_6d_vector};
 	pub(crate) use bevy::{prelude::*, utils::HashMap};
 	pub(crate) use bevy_inspector_egui::prelude::*;
 }
@@ -176,8 +177,28 @@ mod shared_types {
 		};
 	}
 
-	/// Forces are between [-1, 1],
-	/// Torques are 'normalized' to [-1, 1] // TODO
+	#[macro_export]
+	macro_rules! impl_from_vec6 {
+		// impl From<Vec6> for $type with forward,right,upwards etc fields
+		($type:ty) => {
+			impl From<$crate::shared_types::Vec6> for $type {
+				fn from(vec: $crate::shared_types::Vec6) -> Self {
+					Self {
+						forward: vec.forward,
+						right: vec.right,
+						upwards: vec.upwards,
+						turn_right: vec.turn_right,
+						pitch_up: vec.pitch_up,
+						roll_right: vec.roll_right,
+					}
+				}
+			}
+		};
+	}
+
+	/// The linear (m/s^2) and angular (rad/s^2) acceleration a thruster firing
+	/// at full strength gives its parent, in the parent's local frame.
+	/// See [ForceAxis::new].
 	///
 	/// The Greek philosopher, Archimedes, said,
 	/// “Give me a lever long enough and a fulcrum on which to place it, and I shall move the world.”
@@ -192,8 +213,40 @@ mod shared_types {
 		roll_right: f32,
 	}
 	impl_relative_6d_vector!(ForceAxis);
+	impl_from_vec6!(ForceAxis);
+
+	impl ForceAxis {
+		/// Takes the thruster's [Transform] relative to its parent,
+		/// which pushes along its local +Z with `strength_factor` Newtons,
+		/// and the parent's mass properties in the parent's local frame.
+		///
+		/// Torque is the lever arm around `center_of_mass` crossed with the force,
+		/// so thrusters further from the center of mass turn the parent faster.
+		pub fn new(
+			thruster: &Transform,
+			strength_factor: f32,
+			center_of_mass: Vec3,
+			inverse_mass: f32,
+			inverse_inertia: Mat3,
+		) -> Self {
+			let force = thruster.rotation.mul_vec3(Vec3::Z) * strength_factor;
+			let torque = (thruster.translation - center_of_mass).cross(force);
+
+			let linear = force * inverse_mass;
+			let angular = inverse_inertia * torque;
 
-	#[derive(Debug, Reflect, derive_more::Add, derive_more::Sub)]
+			Self {
+				forward: linear.dot(Vec3::NEG_Z),
+				right: linear.dot(Vec3::X),
+				upwards: linear.dot(Vec3::Y),
+				turn_right: angular.dot(Vec3::NEG_Y),
+				pitch_up: angular.dot(Vec3::X),
+				roll_right: angular.dot(Vec3::NEG_Z),
+			}
+		}
+	}
+
+	#[derive(Debug, Reflect, Default, Clone, Copy, PartialEq, derive_more::Add, derive_more::Sub)]
 	pub struct Vec6 {
 		pub forward: f32,
 		pub right: f32,
@@ -329,6 +382,7 @@ mod shared_types {
 		}
 
 		impl_relative_6d_vector!(CurrentVelocity);
+		impl_from_vec6!(CurrentVelocity);
 
 		#[derive(Component, Debug, Default, Reflect)]
 		#[reflect(Component)]
@@ -342,6 +396,7 @@ mod shared_types {
 		}
 
 		impl_relative_6d_vector!(IntendedVelocity);
+		impl_from_vec6!(IntendedVelocity);
 	}
 
 	// mod bundles {
@@ -358,7 +413,7 @@ mod shared_types {
 	// }
 }
 
-mod strategies {
+pub mod strategies {
 	use bevy::ecs::query::WorldQuery;
 
 	use crate::prelude::*;
@@ -420,12 +475,263 @@ mod strategies {
 		}
 	}
 
+	/// Solves for the [Thruster] statuses in `[0, 1]` whose combined [ForceAxis]s
+	/// best match the desired 6D wrench, see [allocate].
+	///
+	/// Unlike [ExactAxisStrategy] this never fires opposing thrusters against each other,
+	/// and can hit combined translation and rotation targets.
+	#[derive(Debug, Clone, Reflect)]
+	pub struct OptimalAllocationStrategy {
+		/// Penalty on total thrust, which picks the most fuel efficient solution
+		/// when many solutions match the target equally well
+		pub regularization: f32,
+		pub max_iterations: usize,
+		/// Stops iterating once no status changes by more than this
+		pub tolerance: f32,
+	}
+
+	impl Default for OptimalAllocationStrategy {
+		fn default() -> Self {
+			Self {
+				regularization: 1e-4,
+				max_iterations: 200,
+				tolerance: 1e-5,
+			}
+		}
+	}
+
+	impl OptimalAllocationStrategy {
+		/// Returns the status of each thruster, in the same order as `axes`
+		pub fn allocate(&self, axes: &[Vec6], target: &Vec6) -> Vec<f32> {
+			allocate(axes, target, self)
+		}
+	}
+
+	impl<ID: std::hash::Hash + Eq> PureStrategy<ID> for OptimalAllocationStrategy {
+		fn calculate<'w>(
+			&self,
+			blocks: HashMap<&'w ID, ThrusterInfo<'w>>,
+			parent: ParentInfo<'w>,
+		) -> HashMap<&'w ID, f32> {
+			let (ids, axes): (Vec<_>, Vec<_>) = blocks
+				.into_iter()
+				.map(|(id, info)| (id, info.force_axis.get_generic()))
+				.unzip();
+			let statuses = self.allocate(&axes, &parent.difference().get_generic());
+
+			ids.into_iter().zip(statuses).collect()
+		}
+	}
+
+	/// Bounded least squares, minimizing
+	/// `|A x - target|^2 + regularization * |x|^2` where `0 <= x <= 1`
+	/// and the columns of `A` are the thrusters' [ForceAxis]s.
+	///
+	/// Solved with projected coordinate descent, which always converges since
+	/// the problem is convex, and is plenty fast for the handful of thrusters a ship has.
+	pub fn allocate(axes: &[Vec6], target: &Vec6, options: &OptimalAllocationStrategy) -> Vec<f32> {
+		let n = axes.len();
+
+		// normal equations, H = A^T A + regularization * I and g = A^T target
+		let hessian: Vec<Vec<f32>> = axes
+			.iter()
+			.enumerate()
+			.map(|(i, a)| {
+				axes
+					.iter()
+					.enumerate()
+					.map(|(j, b)| a.dot(b) + if i == j { options.regularization } else { 0. })
+					.collect()
+			})
+			.collect();
+		let gradient_offset: Vec<f32> = axes.iter().map(|a| a.dot(target)).collect();
+
+		let mut statuses = vec![0.; n];
+		for _ in 0..options.max_iterations {
+			let mut max_change: f32 = 0.;
+			for i in 0..n {
+				if hessian[i][i] <= 0. {
+					continue;
+				}
+				let gradient: f32 = (0..n)
+					.map(|j| hessian[i][j] * statuses[j])
+					.sum::<f32>()
+					- gradient_offset[i];
+				let updated = (statuses[i] - gradient / hessian[i][i]).clamp(0., 1.);
+
+				max_change = max_change.max((updated - statuses[i]).abs());
+				statuses[i] = updated;
+			}
+			if max_change < options.tolerance {
+				break;
+			}
+		}
+		statuses
+	}
+
 	#[cfg(test)]
 	mod tests {
+		use super::*;
+
+		const EPSILON: f32 = 0.01;
+
+		fn vec6(forward: f32, right: f32, turn_right: f32) -> Vec6 {
+			Vec6 {
+				forward,
+				right,
+				turn_right,
+				..default()
+			}
+		}
+
+		/// Sums up what the thrusters actually do at the given statuses
+		fn achieved(axes: &[Vec6], statuses: &[f32]) -> Vec6 {
+			axes
+				.iter()
+				.zip(statuses)
+				.fold(Vec6::default(), |sum, (axis, status)| {
+					sum
+						+ Vec6 {
+							forward: axis.forward * status,
+							right: axis.right * status,
+							upwards: axis.upwards * status,
+							turn_right: axis.turn_right * status,
+							pitch_up: axis.pitch_up * status,
+							roll_right: axis.roll_right * status,
+						}
+				})
+		}
+
+		fn assert_near(a: f32, b: f32) {
+			assert!((a - b).abs() < EPSILON, "{} is not near {}", a, b);
+		}
+
 		#[test]
 		fn exact_axis_strategy_works() {
-			// TODO
-			todo!()
+			let forward = Thruster::new_with_strength_factor(1.);
+			let backward = Thruster::new_with_strength_factor(1.);
+			let forward_axis = ForceAxis::from(vec6(1., 0., 0.));
+			let backward_axis = ForceAxis::from(vec6(-1., 0., 0.));
+
+			let current_velocity = CurrentVelocity::default();
+			let intended_velocity = IntendedVelocity::from(vec6(1., 0., 0.));
+
+			let blocks = HashMap::from_iter([
+				(
+					&0,
+					ThrusterInfo {
+						thruster: &forward,
+						force_axis: &forward_axis,
+					},
+				),
+				(
+					&1,
+					ThrusterInfo {
+						thruster: &backward,
+						force_axis: &backward_axis,
+					},
+				),
+			]);
+			let result = ExactAxisStrategy.calculate(
+				blocks,
+				ParentInfo {
+					current_velocity: &current_velocity,
+					intended_velocity: &intended_velocity,
+				},
+			);
+
+			assert_eq!(result[&0], 1.);
+			assert_eq!(result[&1], -1.);
+		}
+
+		#[test]
+		fn force_axis_scales_with_lever_arm() {
+			let thruster_at = |z: f32| Transform {
+				translation: Vec3::new(0., 0., z),
+				// pushing left
+				rotation: Quat::from_rotation_y(-std::f32::consts::FRAC_PI_2),
+				..default()
+			};
+			let near = ForceAxis::new(&thruster_at(1.), 2., Vec3::ZERO, 0.5, Mat3::IDENTITY);
+			let far = ForceAxis::new(&thruster_at(3.), 2., Vec3::ZERO, 0.5, Mat3::IDENTITY);
+
+			assert_near(near.get_right(), -1.);
+			assert_near(far.get_right(), -1.);
+			assert_near(near.get_turn_right(), 2.);
+			assert_near(far.get_turn_right(), 6.);
+
+			let heavy = ForceAxis::new(&thruster_at(3.), 2., Vec3::ZERO, 0.5, Mat3::IDENTITY * (1. / 3.));
+			assert_near(heavy.get_turn_right(), near.get_turn_right());
+		}
+
+		#[test]
+		fn never_fires_opposing_thrusters() {
+			let axes = [vec6(1., 0., 0.), vec6(-1., 0., 0.), vec6(-0.5, 0., 0.)];
+			let statuses = OptimalAllocationStrategy::default().allocate(&axes, &vec6(0.5, 0., 0.));
+
+			assert_near(statuses[0], 0.5);
+			assert_near(statuses[1], 0.);
+			assert_near(statuses[2], 0.);
+		}
+
+		#[test]
+		fn balances_asymmetric_lever_arms() {
+			// two main engines, the right one twice as far from the center of mass,
+			// so going straight needs the left one to fire twice as hard
+			let axes = [vec6(1., 0., 1.), vec6(1., 0., -2.)];
+			let target = vec6(1.5, 0., 0.);
+			let statuses = OptimalAllocationStrategy::default().allocate(&axes, &target);
+
+			assert_near(statuses[0], 1.);
+			assert_near(statuses[1], 0.5);
+			let achieved = achieved(&axes, &statuses);
+			assert_near(achieved.forward, target.forward);
+			assert_near(achieved.turn_right, 0.);
+		}
+
+		#[test]
+		fn combines_translation_and_rotation() {
+			// asymmetric ship: a single main engine, and lopsided side thrusters
+			let axes = [
+				vec6(1., 0., 0.),
+				// side thrusters at the back
+				vec6(0., 1., 1.),
+				vec6(0., -1., -1.),
+				// one side thruster at the front, further from the center of mass
+				vec6(0., 1., -2.),
+			];
+			let target = vec6(1., 0.5, 0.25);
+			let statuses = OptimalAllocationStrategy::default().allocate(&axes, &target);
+
+			let achieved = achieved(&axes, &statuses);
+			assert_near(achieved.forward, target.forward);
+			assert_near(achieved.right, target.right);
+			assert_near(achieved.turn_right, target.turn_right);
+		}
+
+		#[test]
+		fn statuses_stay_within_bounds() {
+			let axes = [vec6(1., 0., 0.3), vec6(0.2, 1., -0.7), vec6(-1., -1., 0.)];
+			let statuses = OptimalAllocationStrategy::default().allocate(&axes, &vec6(10., -10., 5.));
+
+			for status in statuses {
+				assert!((0. ..=1.).contains(&status), "{} out of bounds", status);
+			}
+		}
+
+		#[test]
+		fn unreachable_target_gets_closest() {
+			// can only push forward, so going backwards is best done by doing nothing
+			let axes = [vec6(1., 0., 0.)];
+			let statuses = OptimalAllocationStrategy::default().allocate(&axes, &vec6(-1., 0., 0.));
+
+			assert_eq!(statuses, vec![0.]);
+		}
+
+		#[test]
+		fn no_thrusters() {
+			let statuses = OptimalAllocationStrategy::default().allocate(&[], &vec6(1., 0., 0.));
+			assert!(statuses.is_empty());
 		}
 	}
 }
@@ -0,0 +1,121 @@
+//! Dedicated headless server, run with `cargo r --bin space_craft_server -- --config server.toml`
+
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+use bevy_replicon::renet::ClientId;
+use clap::{Parser, Subcommand};
+use space_craft::{
+	load_ship, save_connect_token, DedicatedServerConfig, DedicatedServerPlugin, PrivateKey,
+	ShutdownRequested,
+};
+
+#[derive(Parser, Debug)]
+struct Cli {
+	/// TOML file to read the server configuration from,
+	/// uses the default configuration if not provided.
+	#[arg(short, long)]
+	config: Option<PathBuf>,
+
+	/// Runs the server if not provided
+	#[command(subcommand)]
+	command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+	/// Generates a new private key, to be shared between the server and the token issuer
+	GenerateKey {
+		#[arg(long, default_value = "server.key")]
+		out: PathBuf,
+	},
+	/// Signs a connect token that lets a client join a server using the same private key
+	IssueToken {
+		#[arg(long, default_value = "server.key")]
+		key: PathBuf,
+
+		/// Unique id of the client the token is issued to
+		#[arg(long)]
+		client_id: u64,
+
+		/// Public address(es) of the server(s) the client may connect to
+		#[arg(long = "server", required = true)]
+		server_addresses: Vec<SocketAddr>,
+
+		/// How long the client has to connect before the token expires
+		#[arg(long, default_value_t = 300)]
+		expire_seconds: u64,
+
+		#[arg(long, default_value = "client.token")]
+		out: PathBuf,
+	},
+	/// Checks that a RON or JSON ship file can be loaded, exiting with an error if it can't
+	ValidateShip { path: PathBuf },
+}
+
+fn main() {
+	let cli = Cli::parse();
+	match cli.command {
+		None => run_server(cli.config),
+		Some(Commands::GenerateKey { out }) => {
+			PrivateKey::generate()
+				.save(&out)
+				.expect("Couldn't save private key");
+			println!("Saved new private key to {:?}", out);
+		}
+		Some(Commands::IssueToken {
+			key,
+			client_id,
+			server_addresses,
+			expire_seconds,
+			out,
+		}) => {
+			let key = PrivateKey::load(&key).expect("Couldn't load private key");
+			let token = key
+				.issue_connect_token(
+					ClientId::from_raw(client_id),
+					server_addresses,
+					Duration::from_secs(expire_seconds),
+				)
+				.expect("Couldn't generate connect token");
+			save_connect_token(&token, &out).expect("Couldn't save connect token");
+			println!("Saved connect token for client {} to {:?}", client_id, out);
+		}
+		Some(Commands::ValidateShip { path }) => match load_ship(&path) {
+			Ok(ship) => println!(
+				"{:?} is a valid ship with {} blocks",
+				path,
+				ship.occupied().count()
+			),
+			Err(err) => {
+				eprintln!("{:?} is not a valid ship: {}", path, err);
+				std::process::exit(1);
+			}
+		},
+	}
+}
+
+fn run_server(config: Option<PathBuf>) {
+	let config = match config {
+		Some(path) => DedicatedServerConfig::load(&path)
+			.unwrap_or_else(|err| panic!("Couldn't load server config from {:?}: {}", path, err)),
+		None => DedicatedServerConfig::default(),
+	};
+
+	let mut app = App::new();
+
+	app
+		.add_plugins((
+			MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(config.tick_duration())),
+			LogPlugin {
+				level: bevy::log::Level::INFO,
+				filter: "space_craft=debug,bevy_replicon=info,renet=info".into(),
+			},
+		))
+		.add_plugins(DedicatedServerPlugin::new(config));
+
+	let shutdown = app.world.resource::<ShutdownRequested>().clone();
+	ctrlc::set_handler(move || shutdown.request()).expect("Couldn't set SIGINT handler");
+
+	app.run();
+}
@@ -1,12 +1,26 @@
 use crate::prelude::*;
 
+mod chunks;
+mod connectivity;
+mod integrity;
 mod worldgen;
 
+pub use chunks::{ChunkConfig, ChunkCoord, InChunk};
+pub use connectivity::ConnectivityGraph;
+pub use integrity::Integrity;
+pub use worldgen::{
+	RadiusBand, ResourceKind, SizeDistribution, TerrainItem, TerrainStructureBlueprint, TerrainType,
+	WorldGenConfig, WorldSeed,
+};
+
 pub struct BlockPlugins;
 
 impl PluginGroup for BlockPlugins {
 	fn build(self) -> PluginGroupBuilder {
-		PluginGroupBuilder::start::<Self>().add(worldgen::WorldGenPlugin)
+		PluginGroupBuilder::start::<Self>()
+			.add(worldgen::WorldGenPlugin)
+			.add(chunks::ChunksPlugin)
+			.add(integrity::IntegrityPlugin)
 	}
 }
 
@@ -33,7 +47,7 @@ impl BlockId {
 ///
 /// Assumes all blocks have a position, material and mesh. These restrictions may be lifted
 /// if ever there was a need.
-#[derive(Debug, Reflect, Serialize, Deserialize, Clone, Deref)]
+#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq, Deref)]
 pub struct BlockBlueprint<T> {
 	pub transform: Transform,
 	pub mesh: OptimizableMesh,
@@ -42,9 +56,16 @@ pub struct BlockBlueprint<T> {
 	pub specific_marker: T,
 }
 
+impl<T: GetBlockId> GetBlockId for BlockBlueprint<T> {
+	fn get_block_id(&self) -> BlockId {
+		self.specific_marker.get_block_id()
+	}
+}
+
 pub mod manual_builder {
 	use crate::prelude::*;
 
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize, EnumIter)]
 	pub enum Facing {
 		Up,
 		Down,
@@ -91,6 +112,25 @@ pub mod manual_builder {
 		pub fn into_world_offset(self) -> Vec3 {
 			self.0.as_vec3().mul(PIXEL_SIZE)
 		}
+
+		/// Snaps a position relative to the parent to the nearest pixel
+		pub fn from_world_offset(offset: Vec3) -> Self {
+			Self(offset.div(PIXEL_SIZE).round().as_ivec3())
+		}
+
+		/// The 6 pixels sharing a face with this one
+		pub fn neighbours(self) -> impl Iterator<Item = Self> {
+			[
+				IVec3::X,
+				IVec3::NEG_X,
+				IVec3::Y,
+				IVec3::NEG_Y,
+				IVec3::Z,
+				IVec3::NEG_Z,
+			]
+			.into_iter()
+			.map(move |offset| Self(self.0 + offset))
+		}
 	}
 
 	#[cfg(test)]
@@ -117,6 +157,13 @@ pub mod manual_builder {
 			assert_eq!(forwards, Vec3::X);
 		}
 
+		#[test]
+		fn world_offset_round_trips() {
+			let pixel = RelativePixel::new(-2, 0, 3);
+			assert_eq!(RelativePixel::from_world_offset(pixel.into_world_offset()), pixel);
+			assert_eq!(pixel.neighbours().count(), 6);
+		}
+
 		#[test]
 		fn facing_up() {
 			let quat = Facing::Up.into_quat();
@@ -129,11 +176,11 @@ pub mod manual_builder {
 	}
 }
 
-pub use structure_block::{StructureBlockBlueprint, StructureBlockBundle};
+pub use structure_block::{StructureBlockBlueprint, StructureBlockBundle, StructureMaterial};
 mod structure_block;
 
 /// Since raw [Mesh] cannot be serialized
-#[derive(Debug, Reflect, Serialize, Deserialize, Clone)]
+#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
 pub enum OptimizableMesh {
 	StandardBlock,
 	CustomRectangularPrism {
@@ -165,7 +212,7 @@ impl OptimizableMesh {
 	}
 }
 
-#[derive(Debug, Reflect, Serialize, Deserialize, Clone)]
+#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
 pub enum OptimizableMaterial {
 	OpaqueColour(Color),
 	None,
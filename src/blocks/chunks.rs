@@ -0,0 +1,473 @@
+//! Space is split into [ChunkCoord]s, which are only loaded (restored or generated)
+//! around players, and unloaded again once every player has moved away.
+//!
+//! Loaded terrain belongs to the chunk it was loaded in, see [InChunk],
+//! and is saved back to that chunk when it is unloaded, wherever it drifted to.
+//! Clients are only sent the replicated entities in the chunks around their own ship.
+
+use crate::prelude::*;
+
+use super::worldgen::TerrainStructureBlueprint;
+
+pub use api::*;
+
+pub(super) struct ChunksPlugin;
+
+impl Plugin for ChunksPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.init_resource::<ChunkConfig>()
+			.init_resource::<LoadedChunks>()
+			.init_resource::<UnloadedChunks>()
+			.add_systems(
+				FixedUpdate,
+				(
+					(Self::unload_chunks, Self::load_chunks)
+						.chain()
+						.run_if(resource_exists::<WorldSeed>().and_then(NetcodeConfig::has_authority())),
+					// e.g. going online after playing offline, the server's chunks are replicated instead
+					Self::drop_chunks
+						.run_if(resource_exists::<WorldSeed>().and_then(not(NetcodeConfig::has_authority()))),
+				)
+					.in_set(GlobalSystemSet::WorldCreation),
+			)
+			.add_systems(
+				FixedUpdate,
+				Self::update_visibility
+					.after(GlobalSystemSet::ExecuteGameLogic)
+					.before(ServerSet::Send)
+					.run_if(resource_exists::<RenetServer>()),
+			)
+			.add_systems(SaveWorld, Self::save_loaded_chunks);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	use super::TerrainStructureBlueprint;
+
+	/// A cube of space [ChunkCoord::SIZE] wide, the unit the world is generated, saved and replicated in
+	#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+	pub struct ChunkCoord(pub IVec3);
+
+	impl ChunkCoord {
+		pub const SIZE: f32 = 32.;
+
+		/// The chunk containing `position`
+		pub fn of(position: Vec3) -> Self {
+			Self((position / Self::SIZE).floor().as_ivec3())
+		}
+
+		pub fn min_corner(self) -> Vec3 {
+			self.0.as_vec3() * Self::SIZE
+		}
+
+		/// Whether `other` is at most `radius` chunks away along every axis
+		pub fn is_within(self, other: ChunkCoord, radius: u32) -> bool {
+			(self.0 - other.0).abs().max_element() <= radius as i32
+		}
+
+		/// Every chunk within `radius` of this one, including itself
+		pub fn around(self, radius: u32) -> impl Iterator<Item = ChunkCoord> {
+			let radius = radius as i32;
+			(-radius..=radius).flat_map(move |x| {
+				(-radius..=radius)
+					.flat_map(move |y| (-radius..=radius).map(move |z| Self(self.0 + IVec3::new(x, y, z))))
+			})
+		}
+
+		/// Mixes the world's seed with this chunk's coordinates,
+		/// so that every chunk is generated independently of the others
+		pub(crate) fn seed(self, world_seed: u64) -> u64 {
+			world_seed
+				^ (self.0.x as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+				^ (self.0.y as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+				^ (self.0.z as i64 as u64).wrapping_mul(0x1656_67B1_9E37_79F9)
+		}
+
+		/// Id of the chunk's record in the [WorldStore]
+		pub(super) fn record_id(self) -> String {
+			format!("{}_{}_{}", self.0.x, self.0.y, self.0.z)
+		}
+	}
+
+	/// How far around players chunks are streamed, in chunks
+	#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+	#[serde(default, deny_unknown_fields)]
+	pub struct ChunkConfig {
+		/// Chunks this close to a player are loaded, and replicated to that player
+		pub load_radius: u32,
+		/// Chunks further than this from every player are unloaded,
+		/// kept bigger than [ChunkConfig::load_radius] so that chunks on the border don't flicker
+		pub unload_radius: u32,
+	}
+
+	impl Default for ChunkConfig {
+		fn default() -> Self {
+			Self {
+				load_radius: 2,
+				unload_radius: 3,
+			}
+		}
+	}
+
+	/// The chunk a loaded entity will be saved into when it is unloaded, server side only
+	#[derive(Component, Debug, Clone, Copy)]
+	pub struct InChunk(pub ChunkCoord);
+
+	/// Every currently loaded chunk
+	#[derive(Resource, Debug, Default)]
+	pub(super) struct LoadedChunks(pub(super) HashSet<ChunkCoord>);
+
+	/// Where unloaded chunks are kept when there is no [WorldStore] to save them to
+	#[derive(Resource, Debug, Default)]
+	pub(super) struct UnloadedChunks(pub(super) HashMap<ChunkCoord, StoredChunk>);
+
+	#[derive(Debug, Default, Serialize, Deserialize)]
+	pub(super) struct StoredChunk {
+		pub(super) terrain: Vec<TerrainStructureBlueprint>,
+	}
+
+	impl StoredChunk {
+		pub(super) const TABLE: &'static str = "chunk";
+	}
+}
+
+mod systems {
+	use crate::prelude::*;
+
+	use super::{ChunksPlugin, LoadedChunks, StoredChunk, TerrainStructureBlueprint, UnloadedChunks};
+
+	impl ChunksPlugin {
+		/// Restores or generates the chunks around every player,
+		/// and the chunks that new terrain was spawned into, so that it is saved with them
+		pub(super) fn load_chunks(
+			mut commands: Commands,
+			mut loaded: ResMut<LoadedChunks>,
+			mut unloaded: ResMut<UnloadedChunks>,
+			players: Query<&Transform, With<NetworkId>>,
+			spawned: Query<&InChunk, Added<InChunk>>,
+			config: Res<ChunkConfig>,
+			world_gen: Res<WorldGenConfig>,
+			seed: Res<WorldSeed>,
+			store: Option<Res<WorldStore>>,
+		) {
+			let wanted: HashSet<ChunkCoord> = players
+				.iter()
+				.flat_map(|transform| ChunkCoord::of(transform.translation).around(config.load_radius))
+				// e.g. wreckage breaking off far away from every player,
+				// which is unloaded again with the rest of its chunk
+				.chain(spawned.iter().map(|home| home.0))
+				.collect();
+			for chunk in wanted {
+				if !loaded.0.insert(chunk) {
+					continue;
+				}
+				let restored = match store.as_deref() {
+					Some(store) => store
+						.load_record::<StoredChunk>(StoredChunk::TABLE, &chunk.record_id())
+						.unwrap_or_else(|err| {
+							error!("Couldn't restore chunk {:?}: {}", chunk, err);
+							// an empty chunk beats generating asteroids a second time
+							Some(StoredChunk::default())
+						}),
+					None => unloaded.0.remove(&chunk),
+				};
+				let terrain = match restored {
+					Some(stored) => stored.terrain,
+					None => world_gen.generate_chunk(seed.0, chunk),
+				};
+				trace!("Loading chunk {:?} with {} asteroids", chunk, terrain.len());
+				commands.spawn_batch(
+					terrain
+						.into_iter()
+						.map(move |blueprint| (blueprint, InChunk(chunk), Replication)),
+				);
+			}
+		}
+
+		/// Saves and despawns the chunks no player is near any more
+		pub(super) fn unload_chunks(
+			mut commands: Commands,
+			mut loaded: ResMut<LoadedChunks>,
+			mut unloaded: ResMut<UnloadedChunks>,
+			players: Query<&Transform, With<NetworkId>>,
+			mut terrain: Query<(
+				Entity,
+				&mut InChunk,
+				&TerrainStructureBlueprint,
+				&Transform,
+				&LinearVelocity,
+				&AngularVelocity,
+			)>,
+			config: Res<ChunkConfig>,
+			store: Option<Res<WorldStore>>,
+		) {
+			let centers: Vec<ChunkCoord> = players
+				.iter()
+				.map(|transform| ChunkCoord::of(transform.translation))
+				.collect();
+			let mut unloading: HashMap<ChunkCoord, StoredChunk> = loaded
+				.0
+				.iter()
+				.filter(|chunk| {
+					centers
+						.iter()
+						.all(|center| !center.is_within(**chunk, config.unload_radius))
+				})
+				.map(|chunk| (*chunk, StoredChunk::default()))
+				.collect();
+			if unloading.is_empty() {
+				return;
+			}
+
+			for (entity, mut home, blueprint, transform, linvel, angvel) in terrain.iter_mut() {
+				if !unloading.contains_key(&home.0) {
+					continue;
+				}
+				// terrain that drifted into a chunk that stays loaded moves there instead
+				let current = ChunkCoord::of(transform.translation);
+				if loaded.0.contains(&current) && !unloading.contains_key(&current) {
+					home.0 = current;
+					continue;
+				}
+				if let Some(stored) = unloading.get_mut(&home.0) {
+					stored
+						.terrain
+						.push(blueprint.drifted(*transform, *linvel, *angvel));
+				}
+				commands.entity(entity).despawn_recursive();
+			}
+
+			for (chunk, stored) in unloading {
+				trace!(
+					"Unloading chunk {:?} with {} asteroids",
+					chunk,
+					stored.terrain.len()
+				);
+				loaded.0.remove(&chunk);
+				match store.as_deref() {
+					Some(store) => {
+						if let Err(err) = store.save_record(StoredChunk::TABLE, &chunk.record_id(), &stored) {
+							error!("Couldn't save chunk {:?}: {}", chunk, err);
+						}
+					}
+					None => {
+						unloaded.0.insert(chunk, stored);
+					}
+				}
+			}
+		}
+
+		/// Forgets the local chunks once this instance stops being authoritative
+		pub(super) fn drop_chunks(
+			mut commands: Commands,
+			terrain: Query<Entity, With<InChunk>>,
+			mut loaded: ResMut<LoadedChunks>,
+			mut unloaded: ResMut<UnloadedChunks>,
+		) {
+			debug!("Dropping {} local chunks", loaded.0.len());
+			for entity in terrain.iter() {
+				commands.entity(entity).despawn_recursive();
+			}
+			loaded.0.clear();
+			unloaded.0.clear();
+			commands.remove_resource::<WorldSeed>();
+		}
+
+		/// Saves every loaded chunk without unloading it, see [SaveWorld]
+		pub(super) fn save_loaded_chunks(
+			loaded: Res<LoadedChunks>,
+			terrain: Query<(
+				&InChunk,
+				&TerrainStructureBlueprint,
+				&Transform,
+				&LinearVelocity,
+				&AngularVelocity,
+			)>,
+			store: Res<WorldStore>,
+		) {
+			let mut chunks: HashMap<ChunkCoord, StoredChunk> = loaded
+				.0
+				.iter()
+				.map(|chunk| (*chunk, StoredChunk::default()))
+				.collect();
+			for (home, blueprint, transform, linvel, angvel) in terrain.iter() {
+				if let Some(stored) = chunks.get_mut(&home.0) {
+					stored
+						.terrain
+						.push(blueprint.drifted(*transform, *linvel, *angvel));
+				}
+			}
+			for (chunk, stored) in chunks {
+				if let Err(err) = store.save_record(StoredChunk::TABLE, &chunk.record_id(), &stored) {
+					error!("Couldn't save chunk {:?}: {}", chunk, err);
+				}
+			}
+		}
+
+		/// Only replicates entities in the chunks around each client's own player
+		pub(super) fn update_visibility(
+			mut clients: ResMut<ClientsInfo>,
+			players: Query<(&NetworkId, &Transform)>,
+			replicated: Query<(Entity, Option<&Transform>), With<Replication>>,
+			config: Res<ChunkConfig>,
+		) {
+			for client in clients.iter_mut() {
+				let center = players
+					.iter()
+					.find(|(id, _)| id.get_network_id() == client.id())
+					.map(|(_, transform)| ChunkCoord::of(transform.translation));
+				let visibility = client.visibility_mut();
+				for (entity, transform) in replicated.iter() {
+					// anything without a position isn't in any chunk, so everyone sees it
+					let visible = match (center, transform) {
+						(_, None) => true,
+						(Some(center), Some(transform)) => {
+							center.is_within(ChunkCoord::of(transform.translation), config.load_radius)
+						}
+						(None, Some(_)) => false,
+					};
+					visibility.set_visibility(entity, visible);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{prelude::*, StorageEngine};
+
+	use super::{ChunksPlugin, LoadedChunks, StoredChunk, TerrainStructureBlueprint, UnloadedChunks};
+
+	/// Everything loading and unloading chunks needs, with only the chunk a player is in loaded
+	fn chunk_world() -> World {
+		let mut world = World::new();
+		world.init_resource::<LoadedChunks>();
+		world.init_resource::<UnloadedChunks>();
+		world.insert_resource(ChunkConfig {
+			load_radius: 0,
+			unload_radius: 0,
+		});
+		world.insert_resource(WorldGenConfig {
+			density: 1.,
+			radius_bands: vec![RadiusBand {
+				inner: 0.,
+				outer: 1000.,
+			}],
+			..default()
+		});
+		world.insert_resource(WorldSeed(69));
+		world.insert_resource(WorldStore::open(&StorageEngine::Memory).unwrap());
+		world
+	}
+
+	/// Gives loaded terrain what expanding its blueprint would
+	fn expand_terrain(world: &mut World) {
+		let terrain: Vec<(Entity, Transform)> = world
+			.query_filtered::<(Entity, &TerrainStructureBlueprint), Without<Transform>>()
+			.iter(world)
+			.map(|(entity, blueprint)| (entity, blueprint.transform))
+			.collect();
+		for (entity, transform) in terrain {
+			world.entity_mut(entity).insert((
+				transform,
+				LinearVelocity::default(),
+				AngularVelocity::default(),
+			));
+		}
+	}
+
+	fn terrain_positions(world: &mut World) -> Vec<Vec3> {
+		let mut positions: Vec<Vec3> = world
+			.query::<&TerrainStructureBlueprint>()
+			.iter(world)
+			.map(|blueprint| blueprint.transform.translation)
+			.collect();
+		positions.sort_by(|a, b| a.to_array().partial_cmp(&b.to_array()).unwrap());
+		positions
+	}
+
+	#[test]
+	fn restores_drifted_terrain() {
+		let mut world = chunk_world();
+		let player = world
+			.spawn((
+				Transform::from_translation(Vec3::splat(ChunkCoord::SIZE / 2.)),
+				NetworkId::from_raw(1),
+			))
+			.id();
+		world.run_system_once(ChunksPlugin::load_chunks);
+		expand_terrain(&mut world);
+		let generated = terrain_positions(&mut world);
+		assert!(!generated.is_empty());
+
+		// one asteroid drifts, before the player leaves and comes back
+		world
+			.query_filtered::<&mut Transform, With<TerrainStructureBlueprint>>()
+			.iter_mut(&mut world)
+			.next()
+			.unwrap()
+			.translation += Vec3::X;
+		world.get_mut::<Transform>(player).unwrap().translation = Vec3::splat(1000.);
+		world.run_system_once(ChunksPlugin::unload_chunks);
+		assert!(terrain_positions(&mut world).is_empty());
+		world.get_mut::<Transform>(player).unwrap().translation = Vec3::splat(ChunkCoord::SIZE / 2.);
+		world.run_system_once(ChunksPlugin::load_chunks);
+
+		let restored = terrain_positions(&mut world);
+		assert_eq!(restored.len(), generated.len());
+		assert_ne!(restored, generated);
+		assert_eq!(
+			restored
+				.iter()
+				.filter(|position| !generated.contains(position))
+				.count(),
+			1
+		);
+	}
+
+	#[test]
+	fn terrain_spawned_far_away_is_saved_with_its_chunk() {
+		let mut world = chunk_world();
+		let far = ChunkCoord(IVec3::new(10, 0, 0));
+		let wreckage = TerrainStructureBlueprint {
+			transform: Transform::from_translation(far.min_corner() + Vec3::ONE),
+			..default()
+		};
+		world.spawn((wreckage, InChunk(far)));
+
+		world.run_system_once(ChunksPlugin::load_chunks);
+		assert!(world.resource::<LoadedChunks>().0.contains(&far));
+
+		// no player is near, so it is unloaded straight away
+		expand_terrain(&mut world);
+		world.run_system_once(ChunksPlugin::unload_chunks);
+		assert!(!world.resource::<LoadedChunks>().0.contains(&far));
+		let stored: StoredChunk = world
+			.resource::<WorldStore>()
+			.load_record(StoredChunk::TABLE, &far.record_id())
+			.unwrap()
+			.unwrap();
+		assert!(stored
+			.terrain
+			.iter()
+			.any(|terrain| terrain.transform.translation == far.min_corner() + Vec3::ONE));
+	}
+
+	#[test]
+	fn chunk_coords() {
+		assert_eq!(ChunkCoord::of(Vec3::splat(0.5)), ChunkCoord(IVec3::ZERO));
+		assert_eq!(
+			ChunkCoord::of(Vec3::new(-0.5, ChunkCoord::SIZE, 70.)),
+			ChunkCoord(IVec3::new(-1, 1, 2))
+		);
+
+		let origin = ChunkCoord::default();
+		assert_eq!(origin.around(2).count(), 125);
+		assert!(origin.around(2).all(|chunk| origin.is_within(chunk, 2)));
+		assert!(!origin.is_within(ChunkCoord(IVec3::new(0, -3, 1)), 2));
+	}
+}
@@ -0,0 +1,152 @@
+//! Which pixels of a structure hold on to each other, see [ConnectivityGraph].
+//!
+//! Used to check that ships are in one piece, and to split ships and asteroids
+//! into separate bodies once blocks are destroyed or mined out of them.
+
+use crate::{blocks::manual_builder::RelativePixel, prelude::*};
+
+/// The occupied pixels of one structure, where pixels sharing a face are connected.
+///
+/// Built from the locations of [BlockBlueprint]s, see [ConnectivityGraph::with_blocks],
+/// or from any set of locations, e.g. the shape of a [TerrainStructureBlueprint]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectivityGraph {
+	pixels: HashSet<RelativePixel>,
+}
+
+impl From<HashSet<RelativePixel>> for ConnectivityGraph {
+	fn from(pixels: HashSet<RelativePixel>) -> Self {
+		Self { pixels }
+	}
+}
+
+impl FromIterator<RelativePixel> for ConnectivityGraph {
+	fn from_iter<I: IntoIterator<Item = RelativePixel>>(pixels: I) -> Self {
+		Self {
+			pixels: pixels.into_iter().collect(),
+		}
+	}
+}
+
+impl ConnectivityGraph {
+	/// Adds the pixel every block is in
+	pub fn with_blocks<'b, T: 'b>(
+		mut self,
+		blocks: impl IntoIterator<Item = &'b BlockBlueprint<T>>,
+	) -> Self {
+		self.pixels.extend(
+			blocks
+				.into_iter()
+				.map(|block| RelativePixel::from_world_offset(block.transform.translation)),
+		);
+		self
+	}
+
+	pub fn len(&self) -> usize {
+		self.pixels.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.pixels.is_empty()
+	}
+
+	pub fn contains(&self, pixel: RelativePixel) -> bool {
+		self.pixels.contains(&pixel)
+	}
+
+	/// In no particular order
+	pub fn pixels(&self) -> impl Iterator<Item = RelativePixel> + '_ {
+		self.pixels.iter().copied()
+	}
+
+	/// The occupied pixels sharing a face with `pixel`
+	pub fn neighbours(&self, pixel: RelativePixel) -> impl Iterator<Item = RelativePixel> + '_ {
+		pixel
+			.neighbours()
+			.filter(|neighbour| self.pixels.contains(neighbour))
+	}
+
+	/// Flood fills from `start`, which is included even if it isn't occupied itself
+	pub fn reachable_from(&self, start: RelativePixel) -> Self {
+		let mut reached: HashSet<RelativePixel> = [start].into_iter().collect();
+		let mut frontier = vec![start];
+		while let Some(pixel) = frontier.pop() {
+			for neighbour in self.neighbours(pixel) {
+				if reached.insert(neighbour) {
+					frontier.push(neighbour);
+				}
+			}
+		}
+		Self { pixels: reached }
+	}
+
+	/// Splits into the groups of pixels that are connected to each other, biggest first.
+	/// The same graph is always split in the same order
+	pub fn components(&self) -> Vec<Self> {
+		let mut left = self.pixels.clone();
+		let mut components = Vec::new();
+		// starting from the smallest pixel left keeps the order independent of the hash set's
+		while let Some(start) = left.iter().copied().min_by_key(|pixel| pixel.0.to_array()) {
+			let component = self.reachable_from(start);
+			left.retain(|pixel| !component.contains(*pixel));
+			components.push(component);
+		}
+		// stable, so equally big components stay in the same order
+		components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+		components
+	}
+
+	/// Relative to the structure's origin, assuming every pixel weighs the same
+	pub fn centre_of_mass(&self) -> Vec3 {
+		if self.pixels.is_empty() {
+			return Vec3::ZERO;
+		}
+		self
+			.pixels
+			.iter()
+			.map(|pixel| pixel.into_world_offset())
+			.sum::<Vec3>()
+			/ self.pixels.len() as f32
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::blocks::manual_builder::RelativePixel;
+
+	use super::ConnectivityGraph;
+
+	#[test]
+	fn finds_components() {
+		let graph: ConnectivityGraph = [
+			RelativePixel::new(0, 0, 0),
+			RelativePixel::new(1, 0, 0),
+			RelativePixel::new(2, 0, 0),
+			// only touching diagonally
+			RelativePixel::new(3, 1, 0),
+			RelativePixel::new(5, 0, 0),
+			RelativePixel::new(5, 0, 1),
+		]
+		.into_iter()
+		.collect();
+
+		let components = graph.components();
+		assert_eq!(
+			components
+				.iter()
+				.map(ConnectivityGraph::len)
+				.collect::<Vec<_>>(),
+			vec![3, 2, 1]
+		);
+		assert!(components[1].contains(RelativePixel::new(5, 0, 1)));
+		assert_eq!(components, graph.components());
+		assert_eq!(
+			graph.reachable_from(RelativePixel::default()),
+			components[0]
+		);
+		assert_eq!(
+			components[0].centre_of_mass(),
+			RelativePixel::new(1, 0, 0).into_world_offset()
+		);
+	}
+}
@@ -0,0 +1,118 @@
+//! How much damage blocks can take before they are destroyed, see [Integrity].
+//!
+//! Damage is only dealt by the server, e.g. from hard collisions.
+//! Destroyed blocks are removed from their structure's blueprint by whatever owns it,
+//! i.e. [crate::players] for ships and [crate::blocks::worldgen] for terrain,
+//! which then re-expands like any other blueprint change.
+//! Whatever is no longer connected breaks off as a separate body, see [TerrainStructureBlueprint::split].
+//! Ship cameras have no integrity, so what is left of a ship can always be flown.
+
+use crate::prelude::*;
+
+pub use api::*;
+
+pub(super) struct IntegrityPlugin;
+
+impl Plugin for IntegrityPlugin {
+	fn build(&self, app: &mut App) {
+		app.register_type::<Integrity>().add_systems(
+			FixedUpdate,
+			Self::apply_collision_damage
+				.in_set(GlobalSystemSet::ExecuteGameLogic)
+				.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
+		);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	/// The health of a single block, derived from its type when it is stamped.
+	///
+	/// Only changed on the server, and never replicated since blocks aren't themselves replicated.
+	#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+	pub struct Integrity {
+		remaining: f32,
+	}
+
+	impl Integrity {
+		/// Collisions gentler than this don't do any damage, in Newton seconds
+		pub const IMPULSE_THRESHOLD: f32 = 2.;
+		/// Damage per Newton second of impulse above the [Self::IMPULSE_THRESHOLD]
+		pub const DAMAGE_PER_IMPULSE: f32 = 5.;
+
+		/// At full health
+		pub fn new(max: f32) -> Self {
+			Self { remaining: max }
+		}
+
+		pub fn is_destroyed(&self) -> bool {
+			self.remaining <= 0.
+		}
+
+		/// Returns whether this `damage` destroyed the block, i.e. it wasn't already destroyed
+		pub fn damage(&mut self, damage: f32) -> bool {
+			let was_destroyed = self.is_destroyed();
+			self.remaining = (self.remaining - damage.max(0.)).max(0.);
+			!was_destroyed && self.is_destroyed()
+		}
+
+		/// How much damage a collision with this total `impulse` does to both blocks
+		pub fn collision_damage(impulse: f32) -> f32 {
+			(impulse.abs() - Self::IMPULSE_THRESHOLD).max(0.) * Self::DAMAGE_PER_IMPULSE
+		}
+	}
+}
+
+mod systems {
+	use crate::prelude::*;
+
+	use super::{Integrity, IntegrityPlugin};
+
+	impl IntegrityPlugin {
+		/// Damages both blocks of every hard collision, from the contact impulses of the last physics step
+		pub(super) fn apply_collision_damage(
+			collisions: Res<Collisions>,
+			mut blocks: Query<&mut Integrity>,
+		) {
+			for contacts in collisions.iter() {
+				if !contacts.during_current_frame {
+					continue;
+				}
+				let damage = Integrity::collision_damage(contacts.total_normal_impulse);
+				if damage <= 0. {
+					continue;
+				}
+				for entity in [contacts.entity1, contacts.entity2] {
+					if let Ok(mut integrity) = blocks.get_mut(entity) {
+						if integrity.damage(damage) {
+							trace!("Block {:?} was destroyed by a collision", entity);
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Integrity;
+
+	#[test]
+	fn is_only_destroyed_once() {
+		let mut integrity = Integrity::new(10.);
+		assert_eq!(
+			Integrity::collision_damage(Integrity::IMPULSE_THRESHOLD),
+			0.
+		);
+
+		assert!(!integrity.damage(4.));
+		assert!(!integrity.is_destroyed());
+		assert!(integrity.damage(Integrity::collision_damage(
+			Integrity::IMPULSE_THRESHOLD + 2.
+		)));
+		assert!(integrity.is_destroyed());
+		assert!(!integrity.damage(1.));
+	}
+}
@@ -4,29 +4,75 @@ use super::manual_builder;
 use super::BlockBlueprint;
 
 /// Used for building structures
-#[derive(Debug, Reflect, Serialize, Deserialize, Clone, IntoStaticStr)]
-pub enum StructureBlockBlueprint {
+#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StructureBlockBlueprint {
+	pub(crate) id: BlockId,
+	pub material: StructureMaterial,
+}
+
+/// What a [StructureBlockBlueprint] is made of, each tier is fabricated from different resources
+#[derive(Debug, Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, IntoStaticStr)]
+pub enum StructureMaterial {
 	Aluminum,
+	/// Heavier than aluminium
+	Steel,
+	/// As light as aluminium, but expensive
+	Composite,
 }
 
-impl StructureBlockBlueprint {
+impl StructureMaterial {
 	pub fn name(&self) -> &'static str {
 		self.into()
 	}
+
+	/// Relative to the default [ColliderDensity] of 1
+	pub fn density(&self) -> f32 {
+		match self {
+			Self::Aluminum => 1.,
+			Self::Steel => 2.8,
+			Self::Composite => 0.9,
+		}
+	}
+
+	/// How much damage a block of this material takes before it is destroyed, see [Integrity]
+	pub fn integrity(&self) -> f32 {
+		match self {
+			Self::Aluminum => 100.,
+			Self::Steel => 250.,
+			Self::Composite => 200.,
+		}
+	}
+
+	pub fn colour(&self) -> Color {
+		match self {
+			Self::Aluminum => Color::SILVER,
+			Self::Steel => Color::GRAY,
+			Self::Composite => Color::rgb_u8(40, 44, 52),
+		}
+	}
+}
+
+impl GetBlockId for StructureBlockBlueprint {
+	fn get_block_id(&self) -> BlockId {
+		self.id
+	}
 }
 
 #[derive(Bundle)]
 pub struct StructureBlockBundle {
 	pbr: PbrBundle,
 	collider: AsyncCollider,
+	density: ColliderDensity,
+	integrity: Integrity,
 	name: Name,
+	id: BlockId,
 }
 
 impl Blueprint for BlockBlueprint<StructureBlockBlueprint> {
 	type Bundle = StructureBlockBundle;
-	type StampSystemParam<'w, 's> = MMA<'w>;
+	type StampSystemParam = MMA<'static>;
 
-	fn stamp(&self, mma: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+	fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
 		let BlockBlueprint {
 			transform,
 			mesh,
@@ -41,21 +87,34 @@ impl Blueprint for BlockBlueprint<StructureBlockBlueprint> {
 				..default()
 			},
 			collider: AsyncCollider(ComputedCollider::ConvexHull),
-			name: Name::new(format!("StructureBlock {}", specific_marker.name())),
+			density: ColliderDensity(specific_marker.material.density()),
+			integrity: Integrity::new(specific_marker.material.integrity()),
+			name: Name::new(format!(
+				"StructureBlock {}",
+				specific_marker.material.name()
+			)),
+			id: specific_marker.id,
 		}
 	}
 }
 
 impl BlockBlueprint<StructureBlockBlueprint> {
 	pub fn new_structure(
-		block: StructureBlockBlueprint,
+		material: StructureMaterial,
 		location: impl Into<manual_builder::RelativePixel>,
 	) -> Self {
 		BlockBlueprint {
 			transform: Transform::from_translation(location.into().into_world_offset()),
 			mesh: super::OptimizableMesh::StandardBlock,
-			material: super::OptimizableMaterial::OpaqueColour(Color::SILVER),
-			specific_marker: block,
+			material: super::OptimizableMaterial::OpaqueColour(material.colour()),
+			specific_marker: StructureBlockBlueprint {
+				id: BlockId::random(),
+				material,
+			},
 		}
 	}
+
+	pub fn location(&self) -> manual_builder::RelativePixel {
+		manual_builder::RelativePixel::from_world_offset(self.transform.translation)
+	}
 }
@@ -1,95 +1,217 @@
 use crate::prelude::*;
 
+pub use api::*;
+
 pub struct WorldGenPlugin;
 
 impl Plugin for WorldGenPlugin {
 	fn build(&self, app: &mut App) {
 		app
+			.init_resource::<WorldGenConfig>()
+			.register_blueprint::<terrain_blueprint::TerrainStructureBlueprint>()
 			.add_systems(
-				Blueprints,
-				Self::expand_terrain_structure.in_set(BlueprintExpansion::Terrain),
+				WorldCreation,
+				Self::creation_choose_seed.in_set(WorldCreationSet::Asteroids),
 			)
 			.add_systems(
-				WorldCreation,
-				Self::creation_spawn_random_world.in_set(WorldCreationSet::Asteroids),
+				SaveWorld,
+				Self::save_seed.run_if(resource_exists::<WorldSeed>()),
+			)
+			.add_systems(RestoreWorld, Self::restore_seed)
+			.add_systems(
+				FixedUpdate,
+				Self::destroy_terrain_items
+					.in_set(GlobalSystemSet::ExecuteGameLogic)
+					.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
 			);
+
+		replicate_marked!(app, terrain_blueprint::TerrainStructureBlueprint);
 	}
 }
 
-mod systems {
+pub use terrain_blueprint::TerrainStructureBlueprint;
+pub use terrain_bundle::TerrainItem;
+pub use terrain_type::{ResourceKind, TerrainType};
 
+mod api {
 	use crate::prelude::*;
 
-	use super::{
-		discrete_shapes::{DiscreteSphere, OptimizableDiscreteShape},
-		terrain_blueprint::TerrainStructureBlueprint,
-		terrain_type::TerrainType,
-		WorldGenPlugin,
-	};
+	/// Configures the asteroid fields of new worlds.
+	/// The same config with the same [WorldGenConfig::seed] always generates the same world.
+	#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+	#[serde(default, deny_unknown_fields)]
+	pub struct WorldGenConfig {
+		/// Seed for generating the world, or [None] to use a random (logged) seed
+		pub seed: Option<u64>,
+		/// Asteroids per 1000 cubic units of every [RadiusBand], generated a chunk at a time.
+		/// Negative densities generate no asteroids
+		pub density: f32,
+		/// Shells around the world's origin that asteroids are spawned in
+		pub radius_bands: Vec<RadiusBand>,
+		/// How big the asteroids are
+		pub sizes: SizeDistribution,
+	}
 
-	impl WorldGenPlugin {
-		pub(super) fn expand_terrain_structure(
-			mut blueprints: Query<(Entity, &TerrainStructureBlueprint), Added<TerrainStructureBlueprint>>,
-			mut commands: Commands,
-			mut mma: MMA,
-		) {
-			for (entity, blueprint) in blueprints.iter_mut() {
-				commands
-					.entity(entity)
-					.insert(blueprint.stamp(&mut mma))
-					.with_children(|parent| {
-						for child in blueprint.clone().into_children().iter() {
-							parent.spawn(child.stamp(&mut mma));
-						}
-					});
+	impl Default for WorldGenConfig {
+		fn default() -> Self {
+			Self {
+				seed: None,
+				density: 0.02,
+				// leaves room for the spawn points around the origin
+				radius_bands: vec![RadiusBand {
+					inner: 25.,
+					outer: 60.,
+				}],
+				sizes: SizeDistribution::PowerLaw {
+					min: 1,
+					max: 6,
+					exponent: 2.,
+				},
 			}
 		}
+	}
+
+	/// A spherical shell around the world's origin
+	#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+	pub struct RadiusBand {
+		pub inner: f32,
+		pub outer: f32,
+	}
+
+	impl RadiusBand {
+		pub fn contains(&self, distance: f32) -> bool {
+			(self.inner..=self.outer).contains(&distance)
+		}
+	}
+
+	/// The seed the current world was generated with, saved with the world
+	/// so that chunks generated after a restore still match the rest of it
+	#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub struct WorldSeed(pub u64);
+
+	impl WorldSeed {
+		pub(crate) const TABLE: &'static str = "world";
+		pub(crate) const RECORD: &'static str = "seed";
+	}
+
+	/// Radii (in pixels) of generated asteroids
+	#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+	#[serde(rename_all = "snake_case")]
+	pub enum SizeDistribution {
+		/// Every radius between `min` and `max` is as likely
+		Uniform { min: u8, max: u8 },
+		/// Small asteroids are common and big ones are rare,
+		/// the chance of a radius `r` is proportional to `r^-exponent`
+		PowerLaw { min: u8, max: u8, exponent: f32 },
+	}
+
+	impl SizeDistribution {
+		pub fn sample(&self, rng: &mut impl Rng) -> NonZeroU8 {
+			let radius = match *self {
+				Self::Uniform { min, max } => rng.gen_range(min..=max.max(min)),
+				Self::PowerLaw { min, max, exponent } => {
+					// inverse transform sampling of the continuous distribution over [min, max + 1)
+					let (min, max) = (min.max(1) as f32, max.max(min) as f32 + 1.);
+					let u: f32 = rng.gen();
+					let radius = if (exponent - 1.).abs() < f32::EPSILON {
+						min * (max / min).powf(u)
+					} else {
+						let k = 1. - exponent;
+						((max.powf(k) - min.powf(k)) * u + min.powf(k)).powf(1. / k)
+					};
+					radius.floor().clamp(min, max - 1.) as u8
+				}
+			};
+			NonZeroU8::new(radius).unwrap_or(NonZeroU8::MIN)
+		}
+	}
+}
+
+mod systems {
 
-		pub(super) fn creation_spawn_random_world(mut commands: Commands) {
-			debug!("Spawning initial asteroids");
+	use crate::{blocks::manual_builder::RelativePixel, prelude::*};
+
+	use super::WorldGenPlugin;
 
-			let mut rng = rand::thread_rng();
-			const NUM: usize = 0;
-			let structures: Vec<TerrainStructureBlueprint> = (0..NUM)
-				.map(|_| {
-					let pos = vec3_polar_random(&mut rng);
-					let distance = 20.0..30.0;
-					let pos = pos * rng.gen_range(distance);
+	impl WorldGenPlugin {
+		/// Asteroids are only generated once a player comes near them, see [crate::blocks::chunks]
+		pub(super) fn creation_choose_seed(mut commands: Commands, config: Res<WorldGenConfig>) {
+			let seed = config.seed.unwrap_or_else(random);
+			info!("Generating the world with seed {}", seed);
+			commands.insert_resource(WorldSeed(seed));
+		}
+
+		pub(super) fn save_seed(seed: Res<WorldSeed>, store: Res<WorldStore>) {
+			if let Err(err) = store.save_record(WorldSeed::TABLE, WorldSeed::RECORD, seed.as_ref()) {
+				error!("Couldn't save the world seed: {}", err);
+			}
+		}
 
-					let rot = Quat::from_euler(
-						EulerRot::XYZ,
-						rng.gen_range(0. ..=TAU),
-						rng.gen_range(0. ..=TAU),
-						rng.gen_range(0. ..=TAU),
+		pub(super) fn restore_seed(
+			mut commands: Commands,
+			store: Res<WorldStore>,
+			config: Res<WorldGenConfig>,
+		) {
+			match store.load_record::<WorldSeed>(WorldSeed::TABLE, WorldSeed::RECORD) {
+				Ok(Some(seed)) => {
+					info!("Restored the world seed {}", seed.0);
+					commands.insert_resource(seed);
+				}
+				Ok(None) | Err(_) => {
+					let seed = config.seed.unwrap_or_else(random);
+					warn!(
+						"Couldn't restore the world seed, new chunks will be generated with seed {}",
+						seed
 					);
+					commands.insert_resource(WorldSeed(seed));
+				}
+			}
+		}
 
-					let mut r = |bound: f32| rng.gen_range(-bound..bound);
-					let max_linvel = 1.0;
-					let linvel = LinearVelocity(Vec3::new(r(max_linvel), r(max_linvel), r(max_linvel)));
-					let max_angvel = 0.5;
-					let angvel = AngularVelocity(Vec3::new(r(max_angvel), r(max_angvel), r(max_angvel)));
-
-					TerrainStructureBlueprint {
-						transform: Transform::from_translation(pos).with_rotation(rot),
-						initial_velocity: Some((linvel, angvel)),
-						shape: OptimizableDiscreteShape::Sphere(DiscreteSphere {
-							radius: NonZeroU8::new(rng.gen_range(1..=4)).unwrap(),
-						}),
-						terrain_type: TerrainType::SilicateRock,
-					}
-				})
-				.collect();
+		/// Removes destroyed items from their structure's blueprint, see [Integrity]
+		pub(super) fn destroy_terrain_items(
+			mut commands: Commands,
+			items: Query<(&Integrity, &Parent, &Transform), (With<TerrainItem>, Changed<Integrity>)>,
+			mut structures: Query<(
+				&mut TerrainStructureBlueprint,
+				&Transform,
+				&LinearVelocity,
+				&AngularVelocity,
+				&CenterOfMass,
+			)>,
+		) {
+			let mut destroyed: HashMap<Entity, Vec<RelativePixel>> = HashMap::new();
+			for (integrity, structure, item) in items.iter() {
+				if integrity.is_destroyed() {
+					destroyed
+						.entry(structure.get())
+						.or_default()
+						.push(RelativePixel::from_world_offset(item.translation));
+				}
+			}
 
-			commands.spawn_batch(structures);
+			for (structure, locations) in destroyed {
+				let Ok((mut blueprint, transform, linvel, angvel, centre_of_mass)) =
+					structures.get_mut(structure)
+				else {
+					continue;
+				};
+				// re-expanding moves the structure back to its blueprint's transform
+				let mut left = blueprint.drifted(*transform, *linvel, *angvel);
+				for location in locations {
+					left.mine(location);
+				}
+				blueprint.break_apart(left, centre_of_mass, structure, &mut commands);
+			}
 		}
 	}
 
 	#[test]
 	fn test_world_gen_expands() {
 		let mut app = test_app();
-		app.add_plugins(super::WorldGenPlugin);
+		app.add_plugins((ReplicationPlugins, BlueprintsPlugin, super::WorldGenPlugin));
 
-		app.world.spawn(TerrainStructureBlueprint::default());
+		app.world.spawn(super::TerrainStructureBlueprint::default());
 		fn assert_0_item(items: Query<(), (With<Name>, With<Transform>, With<Handle<Mesh>>)>) {
 			assert_eq!(items.iter().count(), 0);
 		}
@@ -102,6 +224,178 @@ mod systems {
 		}
 		app.world.run_system_once(assert_1_item);
 	}
+
+	#[test]
+	fn mining_keeps_the_integrity_of_the_other_items() {
+		let mut app = test_app();
+		app.add_plugins((ReplicationPlugins, BlueprintsPlugin, super::WorldGenPlugin));
+
+		let (mined, kept) = (RelativePixel::new(0, 0, 0), RelativePixel::new(1, 0, 0));
+		let structure = app
+			.world
+			.spawn(super::TerrainStructureBlueprint {
+				shape: super::discrete_shapes::OptimizableDiscreteShape::Explicit(vec![mined, kept]),
+				..default()
+			})
+			.id();
+		app.world.run_schedule(FixedUpdate);
+
+		let mut items = app
+			.world
+			.query_filtered::<(Entity, &Transform, &mut Integrity), With<TerrainItem>>();
+		let (kept_item, _, mut integrity) = items
+			.iter_mut(&mut app.world)
+			.find(|(_, transform, _)| transform.translation == kept.into_world_offset())
+			.unwrap();
+		integrity.damage(1.);
+		let damaged = *integrity;
+
+		app
+			.world
+			.get_mut::<super::TerrainStructureBlueprint>(structure)
+			.unwrap()
+			.mine(mined);
+		app.world.run_schedule(FixedUpdate);
+
+		let left: Vec<(Entity, Integrity)> = items
+			.iter(&app.world)
+			.map(|(entity, _, integrity)| (entity, *integrity))
+			.collect();
+		assert_eq!(left, vec![(kept_item, damaged)]);
+	}
+}
+
+mod generation {
+	use crate::prelude::*;
+
+	use super::{
+		discrete_shapes::{DiscreteSphere, NoisyAsteroid, OptimizableDiscreteShape},
+		terrain_blueprint::{TerrainComposition, TerrainStructureBlueprint},
+	};
+
+	impl WorldGenConfig {
+		/// Every asteroid first spawned in `chunk`,
+		/// only ever drawing from a [StdRng] seeded with `seed` and the chunk's coordinates
+		pub(crate) fn generate_chunk(
+			&self,
+			seed: u64,
+			chunk: ChunkCoord,
+		) -> Vec<TerrainStructureBlueprint> {
+			let mut rng = StdRng::seed_from_u64(chunk.seed(seed));
+			let expected = ChunkCoord::SIZE.powi(3) / 1000. * self.density.max(0.);
+			let count = expected as usize + rng.gen_bool(expected.fract().into()) as usize;
+
+			let corner = chunk.min_corner();
+			let mut structures = Vec::new();
+			for _ in 0..count {
+				let pos = corner + Vec3::new(rng.gen(), rng.gen(), rng.gen()) * ChunkCoord::SIZE;
+				// keeps the density per band, since chunks only partly overlap them
+				if self
+					.radius_bands
+					.iter()
+					.any(|band| band.contains(pos.length()))
+				{
+					structures.push(self.generate_asteroid(pos, &mut rng));
+				}
+			}
+			structures
+		}
+
+		fn generate_asteroid(&self, pos: Vec3, rng: &mut StdRng) -> TerrainStructureBlueprint {
+			let rot = Quat::from_euler(
+				EulerRot::XYZ,
+				rng.gen_range(0. ..=TAU),
+				rng.gen_range(0. ..=TAU),
+				rng.gen_range(0. ..=TAU),
+			);
+
+			let mut r = |bound: f32| rng.gen_range(-bound..bound);
+			let max_linvel = 1.0;
+			let linvel = LinearVelocity(Vec3::new(r(max_linvel), r(max_linvel), r(max_linvel)));
+			let max_angvel = 0.5;
+			let angvel = AngularVelocity(Vec3::new(r(max_angvel), r(max_angvel), r(max_angvel)));
+
+			let radius = self.sizes.sample(rng);
+			let shape = if radius.get() == 1 {
+				// too small for noise to make a difference
+				OptimizableDiscreteShape::Sphere(DiscreteSphere { radius })
+			} else {
+				OptimizableDiscreteShape::Noisy(NoisyAsteroid {
+					radius,
+					seed: rng.gen(),
+					roughness: rng.gen_range(0.15..0.45),
+				})
+			};
+
+			TerrainStructureBlueprint {
+				transform: Transform::from_translation(pos).with_rotation(rot),
+				initial_velocity: Some((linvel, angvel)),
+				shape,
+				composition: TerrainComposition::Layered,
+			}
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn same_seed_same_chunk() {
+			let config = WorldGenConfig {
+				density: 1.,
+				radius_bands: vec![RadiusBand {
+					inner: 0.,
+					outer: 100.,
+				}],
+				..default()
+			};
+			let chunk = ChunkCoord(IVec3::new(1, -1, 0));
+			let asteroids = config.generate_chunk(69, chunk);
+			assert!(!asteroids.is_empty());
+			assert_eq!(asteroids, config.generate_chunk(69, chunk));
+			assert_ne!(asteroids, config.generate_chunk(420, chunk));
+			assert_ne!(asteroids, config.generate_chunk(69, ChunkCoord::default()));
+			assert!(asteroids
+				.iter()
+				.all(|asteroid| ChunkCoord::of(asteroid.transform.translation) == chunk));
+		}
+
+		#[test]
+		fn negative_density_generates_nothing() {
+			let config = WorldGenConfig {
+				density: -1.5,
+				radius_bands: vec![RadiusBand {
+					inner: 0.,
+					outer: 100.,
+				}],
+				..default()
+			};
+			assert!(config.generate_chunk(69, ChunkCoord::default()).is_empty());
+		}
+
+		#[test]
+		fn sizes_stay_in_bounds() {
+			let mut rng = StdRng::seed_from_u64(0);
+			for sizes in [
+				SizeDistribution::Uniform { min: 2, max: 5 },
+				SizeDistribution::PowerLaw {
+					min: 2,
+					max: 5,
+					exponent: 2.,
+				},
+				SizeDistribution::PowerLaw {
+					min: 2,
+					max: 5,
+					exponent: 1.,
+				},
+			] {
+				for _ in 0..1000 {
+					assert!((2..=5).contains(&sizes.sample(&mut rng).get()));
+				}
+			}
+		}
+	}
 }
 
 mod terrain_blueprint {
@@ -113,55 +407,319 @@ mod terrain_blueprint {
 	};
 
 	/// Blueprint for [TerrainItemBundle]
-	#[derive(Serialize, Deserialize, Debug, Reflect, Clone)]
+	#[derive(Serialize, Deserialize, Debug, Reflect, Clone, PartialEq)]
 	pub struct TerrainItemBlueprint {
 		pub terrain_type: TerrainType,
 		pub location: RelativePixel,
 	}
 
 	/// Blueprint for [TerrainStructureBundle]
-	#[derive(Component, Serialize, Deserialize, Debug, Default, Reflect, Clone)]
+	#[derive(Component, Serialize, Deserialize, Debug, Default, Reflect, Clone, PartialEq)]
 	#[reflect(Component)]
 	pub struct TerrainStructureBlueprint {
 		pub transform: Transform,
 		pub initial_velocity: Option<(LinearVelocity, AngularVelocity)>,
 		pub shape: OptimizableDiscreteShape,
-		pub terrain_type: TerrainType,
+		pub composition: TerrainComposition,
+	}
+
+	/// Which [TerrainType] each location of a [TerrainStructureBlueprint] is
+	#[derive(Serialize, Deserialize, Debug, Reflect, Clone, PartialEq)]
+	pub enum TerrainComposition {
+		/// The whole structure is the same type
+		Uniform(TerrainType),
+		/// Changes with the depth below the surface, see [TerrainType::at_depth]
+		Layered,
+		/// The type of every location of an [OptimizableDiscreteShape::Explicit] shape, in the same order
+		Explicit(Vec<TerrainType>),
+	}
+
+	impl Default for TerrainComposition {
+		fn default() -> Self {
+			Self::Uniform(TerrainType::default())
+		}
 	}
 
 	impl TerrainStructureBlueprint {
+		/// Where the structure drifted to, rather than where it was first spawned
+		pub fn drifted(
+			&self,
+			transform: Transform,
+			linvel: LinearVelocity,
+			angvel: AngularVelocity,
+		) -> Self {
+			Self {
+				transform,
+				initial_velocity: Some((linvel, angvel)),
+				..self.clone()
+			}
+		}
+
+		/// What is left of blocks that broke off a ship, at `locations` relative to the ship's `transform`
+		pub fn wreckage(
+			locations: Vec<RelativePixel>,
+			transform: Transform,
+			linvel: LinearVelocity,
+			angvel: AngularVelocity,
+		) -> Self {
+			Self {
+				transform,
+				initial_velocity: Some((linvel, angvel)),
+				shape: OptimizableDiscreteShape::Explicit(locations),
+				composition: TerrainComposition::Uniform(TerrainType::Scrap),
+			}
+		}
+
+		/// Removes the item at `location`, turning the shape into an explicit set of the remaining locations.
+		/// Returns the type of the removed item, or [None] if there was nothing there
+		pub fn mine(&mut self, location: RelativePixel) -> Option<TerrainType> {
+			let mut items = self.clone().into_children();
+			let index = items.iter().position(|item| item.location == location)?;
+			let mined = items.swap_remove(index);
+
+			// sorted, so that mining the same locations always leaves the same blueprint
+			items.sort_by_key(|item| item.location.0.to_array());
+			let (locations, types) = items
+				.into_iter()
+				.map(|item| (item.location, item.terrain_type))
+				.unzip();
+			self.shape = OptimizableDiscreteShape::Explicit(locations);
+			self.composition = TerrainComposition::Explicit(types);
+			Some(mined.terrain_type)
+		}
+
+		/// Splits into one blueprint per group of connected items, biggest first,
+		/// so that a structure broken in two becomes two separate bodies, see [ConnectivityGraph].
+		///
+		/// Every part moves like the part of the parent body it was, i.e. the velocity of its own centre of mass
+		/// when the parent spins with [Self::initial_velocity] around its `centre_of_mass`
+		pub fn split(self, centre_of_mass: Vec3) -> Vec<Self> {
+			let mut items: HashMap<RelativePixel, TerrainType> = self
+				.clone()
+				.into_children()
+				.into_iter()
+				.map(|item| (item.location, item.terrain_type))
+				.collect();
+			let graph: ConnectivityGraph = items.keys().copied().collect();
+
+			graph
+				.components()
+				.into_iter()
+				.map(|part| {
+					let offset = self.transform.rotation * (part.centre_of_mass() - centre_of_mass);
+					let initial_velocity = self
+						.initial_velocity
+						.map(|(linvel, angvel)| (LinearVelocity(linvel.0 + angvel.0.cross(offset)), angvel));
+
+					let mut part: Vec<(RelativePixel, TerrainType)> = part
+						.pixels()
+						.filter_map(|location| Some((location, items.remove(&location)?)))
+						.collect();
+					// sorted, so that the same blueprint always splits into the same parts
+					part.sort_by_key(|(location, _)| location.0.to_array());
+					let (locations, types) = part.into_iter().unzip();
+					Self {
+						transform: self.transform,
+						initial_velocity,
+						shape: OptimizableDiscreteShape::Explicit(locations),
+						composition: TerrainComposition::Explicit(types),
+					}
+				})
+				.collect()
+		}
+
+		/// Makes what is `left` of the `structure` after mining or destroying some of its items its new blueprint,
+		/// see [Self::split].
+		/// Parts that broke off are spawned as new structures, and the structure is despawned if nothing is left
+		pub fn break_apart(
+			&mut self,
+			left: Self,
+			centre_of_mass: &CenterOfMass,
+			structure: Entity,
+			commands: &mut Commands,
+		) {
+			let mut parts = left.split(centre_of_mass.0).into_iter();
+			let Some(biggest) = parts.next() else {
+				commands.entity(structure).despawn_recursive();
+				return;
+			};
+			for part in parts {
+				let chunk = ChunkCoord::of(part.transform.translation);
+				commands.spawn((part, InChunk(chunk), Replication));
+			}
+			*self = biggest;
+		}
+
+		/// Whether every item has been mined
+		pub fn is_empty(&self) -> bool {
+			matches!(&self.shape, OptimizableDiscreteShape::Explicit(locations) if locations.is_empty())
+		}
+
 		pub(super) fn into_children(self) -> Vec<TerrainItemBlueprint> {
-			self
-				.shape
-				.get_locations()
+			if let (OptimizableDiscreteShape::Explicit(locations), TerrainComposition::Explicit(types)) =
+				(&self.shape, &self.composition)
+			{
+				return locations
+					.iter()
+					.zip(types)
+					.map(|(location, terrain_type)| TerrainItemBlueprint {
+						terrain_type: terrain_type.clone(),
+						location: *location,
+					})
+					.collect();
+			}
+
+			let locations = self.shape.get_locations();
+			// the depth of each location is relative to the furthest one, so any shape can be layered
+			let extent = locations
+				.iter()
+				.map(|location| location.0.as_vec3().length())
+				.fold(0., f32::max);
+			locations
 				.into_iter()
 				.map(|location| TerrainItemBlueprint {
-					terrain_type: self.terrain_type.clone(),
+					terrain_type: match &self.composition {
+						TerrainComposition::Uniform(terrain_type) => terrain_type.clone(),
+						TerrainComposition::Layered if extent > 0. => {
+							TerrainType::at_depth(1. - location.0.as_vec3().length() / extent)
+						}
+						TerrainComposition::Layered => TerrainType::at_depth(0.),
+						TerrainComposition::Explicit(_) => TerrainType::default(),
+					},
 					location,
 				})
 				.collect()
 		}
 	}
+
+	#[cfg(test)]
+	mod tests {
+		use std::num::NonZeroU8;
+
+		use super::*;
+		use crate::blocks::worldgen::discrete_shapes::DiscreteSphere;
+
+		#[test]
+		fn mining_keeps_the_other_items() {
+			let mut asteroid = TerrainStructureBlueprint {
+				shape: OptimizableDiscreteShape::Sphere(DiscreteSphere {
+					radius: NonZeroU8::new(2).unwrap(),
+				}),
+				composition: TerrainComposition::Layered,
+				..default()
+			};
+			let before: HashMap<RelativePixel, TerrainType> = asteroid
+				.clone()
+				.into_children()
+				.into_iter()
+				.map(|item| (item.location, item.terrain_type))
+				.collect();
+
+			let core = RelativePixel::default();
+			assert_eq!(asteroid.mine(core), Some(TerrainType::IronOre));
+			assert_eq!(asteroid.mine(core), None);
+
+			let after = asteroid.clone().into_children();
+			assert_eq!(after.len(), before.len() - 1);
+			assert!(after
+				.iter()
+				.all(|item| before[&item.location] == item.terrain_type));
+			assert!(!asteroid.is_empty());
+		}
+
+		#[test]
+		fn splits_into_connected_parts() {
+			let rod = |x| (-1..=1).map(move |z| RelativePixel::new(x, 0, z));
+			let asteroid = TerrainStructureBlueprint {
+				shape: OptimizableDiscreteShape::Explicit(rod(0).chain(rod(2)).chain(rod(3)).collect()),
+				composition: TerrainComposition::Uniform(TerrainType::IronOre),
+				..default()
+			};
+
+			let parts = asteroid.clone().split(Vec3::ZERO);
+			assert_eq!(parts.len(), 2);
+			assert_eq!(parts[0].clone().into_children().len(), 6);
+			assert_eq!(parts[1].clone().into_children().len(), 3);
+			assert!(parts[1]
+				.clone()
+				.into_children()
+				.iter()
+				.all(|item| item.location.0.x == 0 && item.terrain_type == TerrainType::IronOre));
+			assert_eq!(parts, asteroid.clone().split(Vec3::ZERO));
+		}
+
+		#[test]
+		fn split_parts_keep_moving_with_the_parent() {
+			let spinning = TerrainStructureBlueprint {
+				initial_velocity: Some((LinearVelocity(Vec3::X), AngularVelocity(Vec3::Y))),
+				shape: OptimizableDiscreteShape::Explicit(vec![
+					RelativePixel::new(0, 0, -1),
+					RelativePixel::new(0, 0, 1),
+				]),
+				..default()
+			};
+
+			let parts = spinning.split(Vec3::ZERO);
+			assert_eq!(parts.len(), 2);
+			for part in parts {
+				let (linvel, angvel) = part.initial_velocity.unwrap();
+				let location = part.clone().into_children()[0].location;
+				// spinning around Y moves the back (+Z) one right and the front one left
+				let expected = Vec3::X + Vec3::X * PIXEL_SIZE * location.0.z as f32;
+				assert!(linvel.0.abs_diff_eq(expected, 1e-5));
+				assert_eq!(angvel.0, Vec3::Y);
+			}
+		}
+
+		#[test]
+		fn any_shape_has_a_connectivity_graph() {
+			let sphere = DiscreteSphere {
+				radius: NonZeroU8::new(3).unwrap(),
+			};
+			let graph = ConnectivityGraph::from(sphere.get_locations());
+			assert_eq!(graph.components().len(), 1);
+			assert!(graph.centre_of_mass().abs_diff_eq(Vec3::ZERO, 1e-5));
+		}
+	}
 }
 
 mod discrete_shapes {
 	use crate::prelude::*;
+	use noise::{NoiseFn, Perlin};
 	use std::num::NonZeroU8;
 
 	use crate::blocks::manual_builder::RelativePixel;
 
-	#[derive(Debug, Serialize, Deserialize, Reflect, Default, Clone)]
+	#[derive(Debug, Serialize, Deserialize, Reflect, Default, Clone, PartialEq)]
 	pub enum OptimizableDiscreteShape {
 		Sphere(DiscreteSphere),
+		Noisy(NoisyAsteroid),
+		/// Every location listed out, e.g. once some of a generated shape was mined
+		Explicit(Vec<RelativePixel>),
 		#[default]
 		Dot,
 	}
 
-	#[derive(Debug, Serialize, Deserialize, Reflect, Clone)]
+	#[derive(Debug, Serialize, Deserialize, Reflect, Clone, PartialEq)]
 	pub struct DiscreteSphere {
 		pub radius: NonZeroU8,
 	}
 
+	/// A lumpy sphere, whose surface is pushed in and out by 3D noise
+	#[derive(Debug, Serialize, Deserialize, Reflect, Clone, PartialEq)]
+	pub struct NoisyAsteroid {
+		pub radius: NonZeroU8,
+		/// Seeds the noise, so the same asteroid always has the same shape
+		pub seed: u32,
+		/// How far the surface strays from the sphere, as a fraction of the radius
+		pub roughness: f32,
+	}
+
+	impl NoisyAsteroid {
+		/// How many bumps there are around the asteroid
+		const FREQUENCY: f64 = 1.5;
+	}
+
 	pub trait DiscreteLocations {
 		fn get_locations(self) -> HashSet<RelativePixel>;
 	}
@@ -185,11 +743,47 @@ mod discrete_shapes {
 		}
 	}
 
+	impl DiscreteLocations for NoisyAsteroid {
+		fn get_locations(self) -> HashSet<RelativePixel> {
+			let NoisyAsteroid {
+				radius,
+				seed,
+				roughness,
+			} = self;
+			let noise = Perlin::new(seed);
+			let radius = radius.get() as f32;
+			let bound = (radius * (1. + roughness)).ceil() as i32;
+
+			// always solid in the middle, so the asteroid is never empty
+			let mut locations: HashSet<RelativePixel> = [RelativePixel::default()].into_iter().collect();
+			for x in -bound..=bound {
+				for y in -bound..=bound {
+					for z in -bound..=bound {
+						let offset = IVec3::new(x, y, z);
+						let distance = offset.as_vec3().length();
+						if distance == 0. {
+							continue;
+						}
+						// sampled on the unit sphere, so the surface has one height in every direction
+						let direction = offset.as_dvec3() / distance as f64 * NoisyAsteroid::FREQUENCY;
+						let height = noise.get([direction.x, direction.y, direction.z]) as f32;
+						if distance <= radius * (1. + roughness * height) {
+							locations.insert(RelativePixel(offset));
+						}
+					}
+				}
+			}
+			locations
+		}
+	}
+
 	impl DiscreteLocations for OptimizableDiscreteShape {
 		fn get_locations(self) -> HashSet<RelativePixel> {
 			match self {
 				Self::Dot => [RelativePixel::default()].into_iter().collect(),
 				Self::Sphere(sphere) => sphere.get_locations(),
+				Self::Noisy(asteroid) => asteroid.get_locations(),
+				Self::Explicit(locations) => locations.into_iter().collect(),
 			}
 		}
 	}
@@ -200,19 +794,28 @@ mod terrain_bundle {
 
 	use super::terrain_blueprint::{TerrainItemBlueprint, TerrainStructureBlueprint};
 
+	/// Marks the children of a [TerrainStructureBlueprint] that can be mined,
+	/// see [TerrainStructureBlueprint::mine]
+	#[derive(Component, Debug)]
+	pub struct TerrainItem;
+
 	/// A single terrain unit
 	#[derive(Bundle)]
 	pub struct TerrainItemBundle {
 		pbr: PbrBundle,
 		name: Name,
 		collider: AsyncCollider,
+		/// The mass of the whole structure is computed up front, see [TerrainStructureBlueprint::mass_properties]
+		density: ColliderDensity,
+		integrity: Integrity,
+		item: TerrainItem,
 	}
 
 	impl Blueprint for TerrainItemBlueprint {
 		type Bundle = TerrainItemBundle;
-		type StampSystemParam<'w, 's> = MMA<'w>;
+		type StampSystemParam = MMA<'static>;
 
-		fn stamp(&self, mma: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
 			let TerrainItemBlueprint {
 				terrain_type,
 				location,
@@ -226,10 +829,43 @@ mod terrain_bundle {
 				},
 				name: Name::new(format!("Terrain: {}", terrain_type.name())),
 				collider: AsyncCollider(ComputedCollider::default()),
+				density: ColliderDensity(0.),
+				integrity: Integrity::new(terrain_type.integrity()),
+				item: TerrainItem,
 			}
 		}
 	}
 
+	impl TerrainStructureBlueprint {
+		/// Computed from every item, so mining an item changes how the structure moves
+		fn mass_properties(&self) -> MassPropertiesBundle {
+			let items: Vec<_> = self
+				.clone()
+				.into_children()
+				.into_iter()
+				.map(|item| {
+					(
+						Position(item.location.into_world_offset()),
+						Rotation::default(),
+						Collider::cuboid(PIXEL_SIZE, PIXEL_SIZE, PIXEL_SIZE),
+					)
+				})
+				.collect();
+			if items.is_empty() {
+				return MassPropertiesBundle::new_computed(&Collider::ball(1.0), 1.0);
+			}
+			MassPropertiesBundle::new_computed(&Collider::compound(items), 1.0)
+		}
+	}
+
+	/// The items a terrain structure was already expanded into, see [ChildrenDiff]
+	#[derive(SystemParam)]
+	pub struct TerrainExpansion<'w, 's> {
+		mma: MMA<'w>,
+		children: Query<'w, 's, &'static Children>,
+		items: Query<'w, 's, (Entity, &'static ExpandedFrom<TerrainItemBlueprint>)>,
+	}
+
 	/// The parent of many [TerrainItemBundle]s
 	#[derive(Bundle)]
 	pub struct TerrainStructureBundle {
@@ -243,14 +879,17 @@ mod terrain_bundle {
 
 	impl Blueprint for TerrainStructureBlueprint {
 		type Bundle = TerrainStructureBundle;
-		type StampSystemParam<'w, 's> = MMA<'w>;
+		type StampSystemParam = TerrainExpansion<'static, 'static>;
 
-		fn stamp(&self, _system_param: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+		fn stamp(
+			&self,
+			_system_param: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) -> Self::Bundle {
 			let TerrainStructureBlueprint {
 				transform,
 				initial_velocity,
 				shape: _,
-				terrain_type: _,
+				composition: _,
 			} = self;
 			let linvel = initial_velocity
 				.as_ref()
@@ -268,9 +907,33 @@ mod terrain_bundle {
 				rigid_body: RigidBody::Dynamic,
 				linvel,
 				angvel,
-				mass_properties: MassPropertiesBundle::new_computed(&Collider::ball(1.0), 1.0),
+				mass_properties: self.mass_properties(),
 			}
 		}
+
+		/// Also spawns a [TerrainItemBundle] child for every location of the shape.
+		/// Items whose location and [TerrainType] didn't change are kept, along with their [Integrity]
+		fn expand(
+			&self,
+			entity: Entity,
+			commands: &mut Commands,
+			expansion: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) {
+			commands.entity(entity).insert(self.stamp(expansion));
+
+			let children: &[Entity] = expansion
+				.children
+				.get(entity)
+				.map(|children| &children[..])
+				.unwrap_or_default();
+			let items = self.clone().into_children();
+			let diff = ChildrenDiff::new_keyed(
+				expansion.items.iter_many(children).map(|(e, b)| (e, &b.0)),
+				&items,
+				|item| item.location,
+			);
+			expand_children(commands, entity, diff, &mut expansion.mma);
+		}
 	}
 }
 
@@ -281,24 +944,90 @@ mod terrain_type {
 		Debug, Serialize, Deserialize, PartialEq, Eq, Hash, IntoStaticStr, Reflect, Clone, Default,
 	)]
 	pub enum TerrainType {
+		/// Loose dust and rubble covering asteroids
+		Regolith,
 		#[default]
 		SilicateRock,
+		/// Found in the cores of bigger asteroids
+		IronOre,
+		/// What is left of the parts of ships that were broken off
+		Scrap,
 	}
 
 	type TT = TerrainType;
 
+	/// What players collect by mining [TerrainType]s
+	#[derive(
+		Debug,
+		Clone,
+		Copy,
+		PartialEq,
+		Eq,
+		Hash,
+		Reflect,
+		Serialize,
+		Deserialize,
+		EnumIter,
+		IntoStaticStr,
+	)]
+	pub enum ResourceKind {
+		Regolith,
+		Silicates,
+		Iron,
+	}
+
+	impl ResourceKind {
+		pub fn name(&self) -> &'static str {
+			self.into()
+		}
+	}
+
 	impl TerrainType {
+		/// What mining a single item of this type gives, and how much of it
+		pub fn yields(&self) -> (ResourceKind, u32) {
+			match self {
+				// loose, so there is more of it per item
+				TT::Regolith => (ResourceKind::Regolith, 2),
+				TT::SilicateRock => (ResourceKind::Silicates, 1),
+				TT::IronOre | TT::Scrap => (ResourceKind::Iron, 1),
+			}
+		}
+
+		/// Which type is found at a `depth` between 0 (the surface) and 1 (the very core)
+		pub fn at_depth(depth: f32) -> Self {
+			match depth {
+				depth if depth < 0.25 => TT::Regolith,
+				depth if depth < 0.7 => TT::SilicateRock,
+				_ => TT::IronOre,
+			}
+		}
+
 		pub(super) const fn mesh(&self) -> OptimizableMesh {
 			match self {
-				TT::SilicateRock => OptimizableMesh::CustomRectangularPrism {
-					size: Vec3::splat(PIXEL_SIZE),
-				},
+				TT::Regolith | TT::SilicateRock | TT::IronOre | TT::Scrap => {
+					OptimizableMesh::CustomRectangularPrism {
+						size: Vec3::splat(PIXEL_SIZE),
+					}
+				}
 			}
 		}
 
 		pub(super) fn material(&self) -> OptimizableMaterial {
 			match self {
+				TT::Regolith => OptimizableMaterial::OpaqueColour(Color::rgb_u8(122, 112, 100)),
 				TT::SilicateRock => OptimizableMaterial::OpaqueColour(Color::rgb_u8(84, 84, 84)),
+				TT::IronOre => OptimizableMaterial::OpaqueColour(Color::rgb_u8(110, 58, 40)),
+				TT::Scrap => OptimizableMaterial::OpaqueColour(Color::rgb_u8(70, 70, 78)),
+			}
+		}
+
+		/// How much damage a single item takes before it is destroyed, see [Integrity]
+		pub fn integrity(&self) -> f32 {
+			match self {
+				TT::Regolith => 30.,
+				TT::SilicateRock => 80.,
+				TT::IronOre => 150.,
+				TT::Scrap => 50.,
 			}
 		}
 
@@ -10,11 +10,17 @@ pub struct CameraBlockBundle {
 }
 
 /// Blueprint for [CameraBlockBundle]
-#[derive(Debug, Reflect, Serialize, Deserialize, Clone)]
+#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CameraBlockBlueprint {
 	pub id: BlockId,
 }
 
+impl GetBlockId for CameraBlockBlueprint {
+	fn get_block_id(&self) -> BlockId {
+		self.id
+	}
+}
+
 /// Marker for [BlockBlueprint]s that are [CameraBlockBlueprint]s,
 /// which spawn [CameraBlockBundle]s.
 #[derive(Component)]
@@ -37,13 +43,17 @@ impl BlockBlueprint<CameraBlockBlueprint> {
 			},
 		}
 	}
+
+	pub fn location(&self) -> manual_builder::RelativePixel {
+		manual_builder::RelativePixel::from_world_offset(self.transform.translation)
+	}
 }
 
 impl Blueprint for BlockBlueprint<CameraBlockBlueprint> {
 	type Bundle = CameraBlockBundle;
-	type StampSystemParam<'w, 's> = MMA<'w>;
+	type StampSystemParam = MMA<'static>;
 
-	fn stamp(&self, mma: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+	fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
 		let BlockBlueprint {
 			transform,
 			mesh,
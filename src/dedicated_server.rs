@@ -0,0 +1,304 @@
+//! Running the game as a dedicated, headless server.
+//! See the `space_craft_server` binary.
+
+use crate::prelude::*;
+
+pub use api::*;
+
+/// Sets up a server-only [App] with no windowing, rendering, picking or particles.
+///
+/// Expects [MinimalPlugins] (and optionally a [bevy::log::LogPlugin]) to already be added.
+pub struct DedicatedServerPlugin {
+	config: DedicatedServerConfig,
+}
+
+impl DedicatedServerPlugin {
+	pub fn new(config: DedicatedServerConfig) -> Self {
+		Self { config }
+	}
+}
+
+impl Plugin for DedicatedServerPlugin {
+	fn build(&self, app: &mut App) {
+		info!("DedicatedServerPlugin initializing with {:?}", self.config);
+
+		// the bare minimum that [DefaultPlugins] would provide for the simulation
+		app
+			.add_plugins((
+				TransformPlugin,
+				HierarchyPlugin,
+				bevy::input::InputPlugin,
+				AssetPlugin::default(),
+			))
+			// blueprints still stamp meshes, materials and particle effects,
+			// which are never rendered but are needed for colliders
+			.init_asset::<Mesh>()
+			.init_asset::<StandardMaterial>()
+			.init_asset::<EffectAsset>();
+
+		app
+			.insert_resource(Time::<Fixed>::from_hz(self.config.tick_rate))
+			.insert_resource(self.config.netcode_config())
+			.init_resource::<ShutdownRequested>()
+			.add_systems(Update, Self::handle_graceful_shutdown);
+		app
+			.insert_resource(self.config.world_gen.clone())
+			.insert_resource(self.config.chunks.clone())
+			.insert_resource(self.config.recipes.clone())
+			.insert_resource(self.config.waves.clone());
+		if let Some(path) = &self.config.starter_ship_file {
+			let ship = crate::players::load_ship(path)
+				.unwrap_or_else(|err| panic!("Couldn't load starter ship from {:?}: {}", path, err));
+			app.insert_resource(crate::players::StarterShip(ship));
+		}
+		if let Some(store) = &self.config.world_store {
+			app.insert_resource(store.clone());
+		}
+		crate::init_global_state(app, GlobalGameStates::InGame);
+
+		app.add_plugins(crate::SimulationPlugin);
+	}
+}
+
+mod api {
+	use std::{
+		path::{Path, PathBuf},
+		sync::{
+			atomic::{AtomicBool, Ordering},
+			Arc,
+		},
+	};
+
+	use crate::{
+		players::{RecipeBook, WaveConfig},
+		prelude::*,
+		WorldStoreConfig,
+	};
+
+	/// Configuration of a dedicated server, typically loaded from a TOML file
+	/// with [DedicatedServerConfig::load].
+	///
+	/// Any missing keys fall back to their [Default] values.
+	#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+	#[serde(default, deny_unknown_fields)]
+	pub struct DedicatedServerConfig {
+		/// Address to bind the server's socket to
+		pub bind_address: IpAddr,
+		pub port: u16,
+		/// Maximum number of clients that can be connected at once
+		pub max_clients: usize,
+		/// How many times per second [FixedUpdate] runs
+		pub tick_rate: f64,
+		/// Private key that connect tokens are signed with, see [PrivateKey].
+		/// If [None], any client can connect unauthenticated.
+		pub private_key_file: Option<PathBuf>,
+		/// RON or JSON ship file that joining players are spawned with,
+		/// see [crate::players::load_ship]. Uses the default ship if [None].
+		pub starter_ship_file: Option<PathBuf>,
+		/// Where the world is saved to and restored from, see [WorldStoreConfig].
+		/// If [None], a new world is created every time the server starts.
+		pub world_store: Option<WorldStoreConfig>,
+		/// How new worlds are generated, including their seed
+		pub world_gen: WorldGenConfig,
+		/// How far around players the world is loaded and replicated
+		pub chunks: ChunkConfig,
+		/// What every block is fabricated from, replaces all of the default recipes if given
+		pub recipes: RecipeBook,
+		/// When enemy waves attack, and how big they get
+		pub waves: WaveConfig,
+	}
+
+	impl Default for DedicatedServerConfig {
+		fn default() -> Self {
+			Self {
+				bind_address: Ipv4Addr::UNSPECIFIED.into(),
+				port: DEFAULT_PORT,
+				max_clients: DEFAULT_MAX_CLIENTS,
+				tick_rate: 64.,
+				private_key_file: None,
+				starter_ship_file: None,
+				world_store: None,
+				world_gen: WorldGenConfig::default(),
+				chunks: ChunkConfig::default(),
+				recipes: RecipeBook::default(),
+				waves: WaveConfig::default(),
+			}
+		}
+	}
+
+	impl DedicatedServerConfig {
+		pub fn from_toml_str(toml: &str) -> Result<Self, toml::de::Error> {
+			toml::from_str(toml)
+		}
+
+		/// Reads and parses a TOML config file
+		pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+			let toml = std::fs::read_to_string(path)?;
+			Ok(Self::from_toml_str(&toml)?)
+		}
+
+		/// How long each tick of the server's main loop should take
+		pub fn tick_duration(&self) -> Duration {
+			Duration::from_secs_f64(1. / self.tick_rate)
+		}
+
+		pub(crate) fn netcode_config(&self) -> NetcodeConfig {
+			NetcodeConfig::Server {
+				ip: self.bind_address,
+				port: self.port,
+				headless: true,
+				max_clients: self.max_clients,
+				private_key: self.private_key_file.clone(),
+			}
+		}
+	}
+
+	/// Set (e.g. from a SIGINT handler) to shut the server down gracefully.
+	/// Cloning shares the same underlying flag.
+	#[derive(Resource, Debug, Default, Clone)]
+	pub struct ShutdownRequested(Arc<AtomicBool>);
+
+	impl ShutdownRequested {
+		/// Can be called from any thread
+		pub fn request(&self) {
+			self.0.store(true, Ordering::SeqCst);
+		}
+
+		pub fn is_requested(&self) -> bool {
+			self.0.load(Ordering::SeqCst)
+		}
+	}
+}
+
+mod systems {
+	use bevy::app::AppExit;
+
+	use crate::prelude::*;
+
+	use super::{DedicatedServerPlugin, ShutdownRequested};
+
+	impl DedicatedServerPlugin {
+		/// Once a shutdown is requested, disconnects every client and then exits on the next frame,
+		/// giving the transport a chance to actually send the disconnect packets.
+		pub(super) fn handle_graceful_shutdown(
+			shutdown: Res<ShutdownRequested>,
+			server: Option<ResMut<RenetServer>>,
+			mut has_disconnected: Local<bool>,
+			mut exit: EventWriter<AppExit>,
+		) {
+			if !shutdown.is_requested() {
+				return;
+			}
+
+			if !*has_disconnected {
+				info!("Shutting down, disconnecting all clients");
+				if let Some(mut server) = server {
+					server.disconnect_all();
+				}
+				*has_disconnected = true;
+			} else {
+				info!("All clients disconnected, exiting");
+				exit.send(AppExit);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::DedicatedServerConfig;
+	use crate::{
+		players::{RecipeBook, WaveConfig},
+		prelude::*,
+		StorageEngine, WorldStoreConfig,
+	};
+
+	#[test]
+	fn empty_config_is_default() {
+		assert_eq!(
+			DedicatedServerConfig::from_toml_str("").unwrap(),
+			DedicatedServerConfig::default()
+		);
+	}
+
+	#[test]
+	fn parses_config() {
+		let config = DedicatedServerConfig::from_toml_str(
+			r#"
+			bind_address = "127.0.0.1"
+			port = 1234
+			max_clients = 4
+			tick_rate = 30.0
+			private_key_file = "server.key"
+			starter_ship_file = "ships/starter.ron"
+
+			[world_store]
+			engine = { file = "world.db" }
+			autosave_seconds = 30.0
+
+			[world_gen]
+			seed = 42
+			density = 0.5
+			radius_bands = [{ inner = 10.0, outer = 20.0 }, { inner = 50.0, outer = 55.0 }]
+			sizes = { uniform = { min = 1, max = 3 } }
+
+			[chunks]
+			load_radius = 1
+			unload_radius = 4
+
+			[waves]
+			seed = 7
+			first_wave_seconds = 10.0
+			max_alive = 3
+			"#,
+		)
+		.unwrap();
+
+		assert_eq!(
+			config,
+			DedicatedServerConfig {
+				bind_address: Ipv4Addr::LOCALHOST.into(),
+				port: 1234,
+				max_clients: 4,
+				tick_rate: 30.,
+				private_key_file: Some("server.key".into()),
+				starter_ship_file: Some("ships/starter.ron".into()),
+				world_store: Some(WorldStoreConfig {
+					engine: StorageEngine::File("world.db".into()),
+					autosave_seconds: 30.,
+				}),
+				world_gen: WorldGenConfig {
+					seed: Some(42),
+					density: 0.5,
+					radius_bands: vec![
+						RadiusBand {
+							inner: 10.,
+							outer: 20.,
+						},
+						RadiusBand {
+							inner: 50.,
+							outer: 55.,
+						},
+					],
+					sizes: SizeDistribution::Uniform { min: 1, max: 3 },
+				},
+				chunks: ChunkConfig {
+					load_radius: 1,
+					unload_radius: 4,
+				},
+				recipes: RecipeBook::default(),
+				waves: WaveConfig {
+					seed: Some(7),
+					first_wave_seconds: 10.,
+					max_alive: 3,
+					..default()
+				},
+			}
+		);
+	}
+
+	#[test]
+	fn rejects_unknown_keys() {
+		assert!(DedicatedServerConfig::from_toml_str("max_players = 4").is_err());
+	}
+}
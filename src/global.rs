@@ -10,7 +10,10 @@ pub mod assets;
 pub mod blueprints;
 
 pub const DEFAULT_PORT: u16 = 5069;
-pub const PROTOCOL_ID: u64 = 0;
+/// Must match between clients and servers (and connect tokens),
+/// bump whenever the network protocol changes incompatibly.
+pub const PROTOCOL_ID: u64 = 0x5370_6163_6543_0001;
+pub const DEFAULT_MAX_CLIENTS: usize = 10;
 pub const PIXEL_SIZE: f32 = 1.; // how many pixels per block
 
 /// For all systems that are order-specific
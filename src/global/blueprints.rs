@@ -2,40 +2,21 @@ use bevy::ecs::schedule::{ScheduleBuildSettings, ScheduleLabel};
 
 use crate::prelude::*;
 
-pub use traits::*;
-
+pub use bevy_blueprints::prelude::{
+	Blueprint, BlueprintAppExt, BlueprintsSet, FreshlyExpanded, NetworkedBlueprintBundle,
+};
+pub use diffing::*;
+
+/// Configures [bevy_blueprints] to expand blueprints in the [Blueprints] [Schedule],
+/// which is run every [FixedUpdate].
+///
+/// Add before any plugins that call [BlueprintAppExt::register_blueprint].
 pub struct BlueprintsPlugin;
 
 impl Plugin for BlueprintsPlugin {
 	fn build(&self, app: &mut App) {
-		type BE = BlueprintExpansion;
 		app
-			.configure_sets(
-				Blueprints,
-				(
-					BE::ClearJustExpandedMarker,
-					(BE::Player, BE::SpawnPoints, BE::Terrain),
-					BE::Expand1, // expands player children like structure blueprints
-					(BE::ThrusterBlocks,),
-					BE::Expand2,
-				)
-					.chain(),
-			)
-			.register_type::<FreshlyExpanded>()
-			.add_systems(
-				Blueprints,
-				(
-					(
-						apply_deferred,
-						Self::clear_blueprint_updated_markers,
-						apply_deferred,
-					)
-						.chain()
-						.in_set(BE::ClearJustExpandedMarker),
-					apply_deferred.in_set(BE::Expand1),
-					apply_deferred.in_set(BE::Expand2),
-				),
-			)
+			.add_plugins(bevy_blueprints::prelude::BlueprintsPlugin::new(Blueprints))
 			.add_systems(
 				FixedUpdate,
 				Self::run_blueprints_schedule.in_set(GlobalSystemSet::BlueprintExpansion),
@@ -53,55 +34,12 @@ impl Plugin for BlueprintsPlugin {
 #[derive(ScheduleLabel, Hash, Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Blueprints;
 
-/// If an entity has this component, it means that it was just spawned
-/// / its blueprint was just expanded.
-#[derive(Component, Debug, Default, Reflect)]
-#[component(storage = "SparseSet")]
-pub struct FreshlyExpanded;
-
-/// Makes sure that the blueprints that the player creates are
-/// expanded before, so that thruster visuals can be spawned on time.
-#[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone, Copy)]
-pub enum BlueprintExpansion {
-	/// Removes the [JustExpanded] marker components and calls [apply_deferred] (twice)
-	ClearJustExpandedMarker,
-
-	/// Expands player's blueprint,
-	/// which also spawns a few children.
-	Player,
-	SpawnPoints,
-	/// Asteroids
-	Terrain,
-
-	/// Runs [apply_deferred]
-	Expand1,
-
-	ThrusterBlocks,
-
-	/// Runs [apply_deferred]
-	Expand2,
-}
-
-// (BlueprintExpansionClass::Player, BlueprintExpansionClass::Thruster)
-// 	.chain()
-// 	.in_set(GlobalSystemSet::BlueprintExpansion),
-
 mod systems {
 	use crate::prelude::*;
 
-	use super::{BlueprintsPlugin, FreshlyExpanded};
+	use super::BlueprintsPlugin;
 
 	impl BlueprintsPlugin {
-		pub(super) fn clear_blueprint_updated_markers(
-			markers: Query<(Entity, Option<&Name>), With<FreshlyExpanded>>,
-			mut commands: Commands,
-		) {
-			for (entity, name) in markers.iter() {
-				trace!("Removing JustExpanded marker from {:?}", name);
-				commands.entity(entity).remove::<FreshlyExpanded>();
-			}
-		}
-
 		pub(super) fn run_blueprints_schedule(world: &mut World) {
 			// trace!("Running Blueprints schedule normally");
 			world.try_run_schedule(Blueprints).ok();
@@ -109,65 +47,171 @@ mod systems {
 	}
 }
 
-mod traits {
+mod diffing {
 	use crate::prelude::*;
+	use std::hash::Hash;
+
+	/// Placed on children expanded from a `B` blueprint, remembering what they were stamped from,
+	/// so that the next expansion only touches the children that changed.
+	/// See [expand_children].
+	#[derive(Component, Debug, Clone, Deref)]
+	pub struct ExpandedFrom<B>(pub B);
+
+	/// How the expanded children of a blueprint have to change to match
+	/// the new child blueprints, keyed by [BlockId] unless built with [ChildrenDiff::new_keyed]
+	#[derive(Debug)]
+	pub struct ChildrenDiff<'b, B> {
+		pub spawn: Vec<&'b B>,
+		/// Children whose blueprint changed, but kept its key
+		pub update: Vec<(Entity, &'b B)>,
+		pub despawn: Vec<Entity>,
+	}
 
-	/// Represents a type [Blueprint] that can be [Blueprint::stamp]ed into
-	/// a bundle that can be spawned, i.e., a [Bundle] that is specifically
-	/// [Blueprint::For]
-	pub trait Blueprint: std::fmt::Debug {
-		/// The bundle type that this blueprint can be stamped into.
-		type Bundle: Bundle;
-		/// A way to access the world when stamping, typically [MMA],
-		/// for things like [AssetServer] or [ResMut<Assets<Mesh>>].
-		type StampSystemParam<'w, 's>: SystemParam;
-
-		/// Stamps this blueprint into a bundle that can be spawned.
-		fn stamp(&self, system_param: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle;
+	impl<'b, B: GetBlockId + PartialEq> ChildrenDiff<'b, B> {
+		pub fn new<'e>(
+			existing: impl IntoIterator<Item = (Entity, &'e B)>,
+			blueprints: impl IntoIterator<Item = &'b B>,
+		) -> Self
+		where
+			B: 'e,
+		{
+			Self::new_keyed(existing, blueprints, B::get_block_id)
+		}
 	}
 
-	/// A blueprint that is synced over the network.
-	/// Hence, it must be serializable and deserializable,
-	/// and contain at least a [bevy_replicon] serializable component.
-	pub trait NetworkedBlueprintBundle:
-		Bundle + std::ops::Deref<Target = Self::NetworkedBlueprintComponent>
+	impl<'b, B: PartialEq> ChildrenDiff<'b, B> {
+		/// Like [ChildrenDiff::new], for children that aren't identified by a [BlockId]
+		pub fn new_keyed<'e, K: Hash + Eq>(
+			existing: impl IntoIterator<Item = (Entity, &'e B)>,
+			blueprints: impl IntoIterator<Item = &'b B>,
+			key: impl Fn(&B) -> K,
+		) -> Self
+		where
+			B: 'e,
+		{
+			let mut existing: HashMap<K, (Entity, &B)> = existing
+				.into_iter()
+				.map(|(entity, blueprint)| (key(blueprint), (entity, blueprint)))
+				.collect();
+			let mut diff = Self {
+				spawn: Vec::new(),
+				update: Vec::new(),
+				despawn: Vec::new(),
+			};
+
+			for blueprint in blueprints {
+				match existing.remove(&key(blueprint)) {
+					None => diff.spawn.push(blueprint),
+					Some((entity, old)) if old != blueprint => diff.update.push((entity, blueprint)),
+					Some(_) => {}
+				}
+			}
+			diff
+				.despawn
+				.extend(existing.into_values().map(|(entity, _)| entity));
+
+			diff
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.spawn.is_empty() && self.update.is_empty() && self.despawn.is_empty()
+		}
+	}
+
+	/// Applies a [ChildrenDiff] to the children of `parent`.
+	/// Only the spawned and re-stamped children are marked as [FreshlyExpanded].
+	pub fn expand_children<B>(
+		commands: &mut Commands,
+		parent: Entity,
+		diff: ChildrenDiff<'_, B>,
+		system_param: &mut SystemParamItem<'_, '_, B::StampSystemParam>,
+	) where
+		B: Blueprint + Clone + Send + Sync + 'static,
 	{
-		type NetworkedBlueprintComponent: Component
-			+ Serialize
-			+ DeserializeOwned
-			+ Blueprint
-			+ ReplicationMarker;
-
-		/// What access is needed when expanding this blueprint.
-		type SpawnSystemParam: SystemParam;
-
-		/// The system that expands this blueprint on both server and client side.
-		/// Runs whenever a new instance of this blueprint is spawned.
-		/// By default, immediately stamps the blueprint bundle on top of the entity.
-		fn expand_system(
-			instances: Query<
-				(Entity, &Self::NetworkedBlueprintComponent),
-				Changed<Self::NetworkedBlueprintComponent>,
-			>,
-			mut commands: Commands,
-			mut expand_system_param: <Self::NetworkedBlueprintComponent as Blueprint>::StampSystemParam<
-				'_,
-				'_,
-			>,
-			_spawn_system_param: Self::SpawnSystemParam,
-		) {
-			for (e, blueprint) in instances.iter() {
-				trace!(
-					"Expanding blueprint: {:?}",
-					// std::any::type_name::<Self::NetworkedBlueprintComponent>(),
-					blueprint
-				);
-				commands
-					.entity(e)
-					.despawn_descendants()
-					.insert(blueprint.stamp(&mut expand_system_param))
-					.insert(FreshlyExpanded);
+		if diff.is_empty() {
+			return;
+		}
+		trace!(
+			"Expanding {} children: {} spawned, {} updated, {} despawned",
+			std::any::type_name::<B>(),
+			diff.spawn.len(),
+			diff.update.len(),
+			diff.despawn.len()
+		);
+
+		for child in diff.despawn {
+			commands.entity(child).despawn_recursive();
+		}
+		for (child, blueprint) in diff.update {
+			commands.entity(child).insert((
+				blueprint.stamp(system_param),
+				ExpandedFrom(blueprint.clone()),
+				FreshlyExpanded,
+			));
+		}
+		commands.entity(parent).with_children(|parent| {
+			for blueprint in diff.spawn {
+				parent.spawn((
+					blueprint.stamp(system_param),
+					ExpandedFrom(blueprint.clone()),
+					FreshlyExpanded,
+				));
+			}
+		});
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[derive(Debug, Clone, Copy, PartialEq)]
+		struct TestBlock {
+			id: BlockId,
+			size: f32,
+		}
+
+		impl GetBlockId for TestBlock {
+			fn get_block_id(&self) -> BlockId {
+				self.id
 			}
 		}
+
+		#[test]
+		fn unchanged_blueprints_do_nothing() {
+			let blocks = [BlockId::random(), BlockId::random()].map(|id| TestBlock { id, size: 1. });
+			let existing = [
+				(Entity::from_raw(10), &blocks[0]),
+				(Entity::from_raw(11), &blocks[1]),
+			];
+
+			assert!(ChildrenDiff::new(existing, &blocks).is_empty());
+		}
+
+		#[test]
+		fn diffs_by_block_id() {
+			let [kept, changed, removed, added] = std::array::from_fn(|_| BlockId::random());
+			let old = [kept, changed, removed].map(|id| TestBlock { id, size: 1. });
+			let existing = [
+				(Entity::from_raw(10), &old[0]),
+				(Entity::from_raw(11), &old[1]),
+				(Entity::from_raw(12), &old[2]),
+			];
+			let new = [
+				TestBlock { id: kept, size: 1. },
+				TestBlock {
+					id: changed,
+					size: 2.,
+				},
+				TestBlock {
+					id: added,
+					size: 1.,
+				},
+			];
+
+			let diff = ChildrenDiff::new(existing, &new);
+			assert_eq!(diff.spawn, vec![&new[2]]);
+			assert_eq!(diff.update, vec![(Entity::from_raw(11), &new[1])]);
+			assert_eq!(diff.despawn, vec![Entity::from_raw(12)]);
+		}
 	}
 }
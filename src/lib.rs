@@ -18,8 +18,10 @@
 
 mod blocks;
 mod cameras;
+mod dedicated_server;
 mod global;
 mod netcode;
+mod persistence;
 mod physics;
 mod players;
 mod prelude;
@@ -38,6 +40,14 @@ use bevy_replicon::ReplicationPlugins;
 
 use crate::prelude::*;
 
+pub use blocks::{ChunkConfig, RadiusBand, SizeDistribution, WorldGenConfig};
+pub use dedicated_server::{DedicatedServerConfig, DedicatedServerPlugin, ShutdownRequested};
+pub use netcode::{load_connect_token, save_connect_token, PrivateKey};
+pub use persistence::{StorageEngine, WorldStoreConfig};
+pub use players::{
+	load_ship, save_ship, ShipFileError, ShipFileFormat, WaveConfig, SHIP_FILE_VERSION,
+};
+
 pub struct MainPlugin;
 
 impl Plugin for MainPlugin {
@@ -47,6 +57,73 @@ impl Plugin for MainPlugin {
 		info!("MainPlugin initializing ...");
 		app.add_systems(Startup, || info!("Startup running"));
 
+		// spawn initial light
+		app.add_systems(Startup, |mut commands: Commands| {
+			// commands.spawn(DirectionalLightBundle {
+			// 	directional_light: DirectionalLight {
+			// 		shadows_enabled: true,
+			// 		..default()
+			// 	},
+			// 	..default()
+			// });
+
+			commands.insert_resource(AmbientLight {
+				color: Color::WHITE,
+				brightness: 0.1,
+			});
+		});
+
+		// will take cli inputs, or default to start menu
+		// app.add_state::<GlobalGameStates>();
+		let state;
+		if std::env::args().len() > 1 {
+			info!("Using options provided by CLI");
+			state = GlobalGameStates::InGame;
+			app.insert_resource(self::netcode::NetcodeConfig::parse());
+		} else {
+			state = GlobalGameStates::StartMenu;
+		}
+		init_global_state(app, state);
+
+		let picking_plugins = DefaultPickingPlugins
+			.build()
+			// .disable::<DebugPickingPlugin>()
+			.disable::<DefaultHighlightingPlugin>();
+		#[cfg(not(feature = "debug"))]
+		let picking_plugins = picking_plugins.disable::<DebugPickingPlugin>();
+
+		// dep plugins
+		app.add_plugins((
+			#[cfg(feature = "editor")]
+			bevy_editor_pls::EditorPlugin::default(),
+			ScreenDiagnosticsPlugin::default(),
+			ScreenFrameDiagnosticsPlugin,
+			picking_plugins,
+			HanabiPlugin,
+			// crate::utils::scenes::HelperScene,
+		));
+
+		// personally built projects
+		app.add_plugins(bevy_starfield::StarfieldPlugin::default());
+
+		// dep configuration
+		#[cfg(feature = "editor")]
+		app.insert_resource(editor_controls());
+
+		// client only game logic plugins,
+		// added before the [SimulationPlugin] since players check for cameras
+		app.add_plugins((self::cameras::CameraPlugin, self::ui::UiPlugins));
+
+		app.add_plugins(SimulationPlugin);
+	}
+}
+
+/// Everything needed to simulate the game world, without any windowing or rendering.
+/// Shared between the [MainPlugin] and the [DedicatedServerPlugin].
+struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+	fn build(&self, app: &mut App) {
 		// global system set configuration
 		app.configure_sets(
 			FixedUpdate,
@@ -95,94 +172,53 @@ impl Plugin for MainPlugin {
 			world.try_run_schedule(GameLogic).ok();
 		}
 
-		// spawn initial light
-		app.add_systems(Startup, |mut commands: Commands| {
-			// commands.spawn(DirectionalLightBundle {
-			// 	directional_light: DirectionalLight {
-			// 		shadows_enabled: true,
-			// 		..default()
-			// 	},
-			// 	..default()
-			// });
-
-			commands.insert_resource(AmbientLight {
-				color: Color::WHITE,
-				brightness: 0.1,
-			});
-		});
-
-		// will take cli inputs, or default to start menu
-		// app.add_state::<GlobalGameStates>();
-		let state;
-		if std::env::args().len() > 1 {
-			info!("Using options provided by CLI");
-			state = GlobalGameStates::InGame;
-			app.insert_resource(self::netcode::NetcodeConfig::parse());
-		} else {
-			state = GlobalGameStates::StartMenu;
-		}
-		app
-			.insert_resource::<State<GlobalGameStates>>(State::new(state))
-			.init_resource::<NextState<GlobalGameStates>>()
-			.add_systems(
-				StateTransition,
-				(
-					bevy::ecs::schedule::run_enter_schedule::<GlobalGameStates>.run_if(run_once()),
-					apply_state_transition::<GlobalGameStates>,
-				)
-					.chain(),
-			);
-
-		let picking_plugins = DefaultPickingPlugins
-			.build()
-			// .disable::<DebugPickingPlugin>()
-			.disable::<DefaultHighlightingPlugin>();
-		#[cfg(not(feature = "debug"))]
-		let picking_plugins = picking_plugins.disable::<DebugPickingPlugin>();
-
 		// dep plugins
 		app.add_plugins((
-			#[cfg(feature = "editor")]
-			bevy_editor_pls::EditorPlugin::default(),
-			ScreenDiagnosticsPlugin::default(),
-			ScreenFrameDiagnosticsPlugin,
-			picking_plugins,
 			physics::PhysicsPlugin,
-			HanabiPlugin,
 			ReplicationPlugins.build().set(ServerPlugin {
 				tick_policy: TickPolicy::Manual,
+				// only the chunks around each player are replicated to them, see [crate::blocks]
+				visibility_policy: VisibilityPolicy::Whitelist,
 				update_timeout: Duration::from_secs(5),
 			}),
+			// rollbacks re-simulate player movement and physics, see [crate::players]
 			TimewarpPlugin::new(TimewarpConfig::new(
-				GlobalSystemSet::ExecuteGameLogic,
+				GlobalSystemSet::PlayerMovement,
 				GlobalSystemSet::ExecuteGameLogic,
 			)),
-			// crate::utils::scenes::HelperScene,
 		));
 
-		// personally built projects
-		app.add_plugins(bevy_starfield::StarfieldPlugin::default());
-
-		// dep configuration
-		#[cfg(feature = "editor")]
-		app.insert_resource(editor_controls());
-
 		// game logic plugins
 		app.add_plugins((
 			global::blueprints::BlueprintsPlugin,
+			self::persistence::PersistencePlugin,
 			self::netcode::NetcodePlugin,
-			self::cameras::CameraPlugin,
-			self::ui::UiPlugins,
 			self::players::PlayerPlugins,
 			self::blocks::BlockPlugins,
 		));
 		app.register_type::<BlockId>();
 
-		// general network replication
-		replicate_marked!(app, Transform);
+		// general network replication,
+		// [Transform] is replicated by [crate::players] since it is predicted
 	}
 }
 
+/// Manually initializes the [GlobalGameStates] with a starting `state`,
+/// since `app.add_state` always starts from the [Default] state.
+fn init_global_state(app: &mut App, state: GlobalGameStates) {
+	app
+		.insert_resource::<State<GlobalGameStates>>(State::new(state))
+		.init_resource::<NextState<GlobalGameStates>>()
+		.add_systems(
+			StateTransition,
+			(
+				bevy::ecs::schedule::run_enter_schedule::<GlobalGameStates>.run_if(run_once()),
+				apply_state_transition::<GlobalGameStates>,
+			)
+				.chain(),
+		);
+}
+
 #[cfg(feature = "editor")]
 fn editor_controls() -> bevy_editor_pls::controls::EditorControls {
 	use bevy_editor_pls::controls;
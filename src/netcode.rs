@@ -16,6 +16,9 @@ pub use api::*;
 
 pub struct NetcodePlugin;
 
+mod authentication;
+mod offline;
+mod player_slots;
 mod world_creation;
 
 impl Plugin for NetcodePlugin {
@@ -29,20 +32,31 @@ impl Plugin for NetcodePlugin {
 			.add_systems(Update, Self::server_event_system.in_set(Server))
 			.add_systems(
 				FixedUpdate,
-				Self::frame_inc_and_replicon_tick_sync,
+				(
+					Self::frame_inc_and_replicon_tick_sync.run_if(not(resource_exists::<RenetClient>())),
+					Self::frame_inc_and_predict_ahead.run_if(resource_exists::<RenetClient>()),
+				),
 			)
 			.configure_sets(GameLogic, Client.run_if(NetcodeConfig::not_headless()))
 			.configure_sets(Update, Client.run_if(NetcodeConfig::not_headless()))
 			.configure_sets(GameLogic, Server.run_if(NetcodeConfig::has_authority()))
 			.add_event::<PlayerJoin>()
 			.add_event::<PlayerLeave>()
-			.add_plugins(self::world_creation::WorldCreationPlugin);
+			.add_event::<PlayerSlotExpired>()
+			.add_plugins((
+				self::world_creation::WorldCreationPlugin,
+				self::player_slots::PlayerSlotsPlugin,
+				self::offline::OfflinePlugin,
+			));
 	}
 }
 
 mod api {
 	use crate::prelude::*;
 
+	pub use super::authentication::{load_connect_token, save_connect_token, PrivateKey};
+	pub use super::offline::OfflineSession;
+	pub use super::player_slots::{JoinRejected, JoinRejection, PlayerSlots, SlotClaim};
 	pub use super::resources::NetcodeConfig;
 	pub use super::world_creation::{WorldCreation, WorldCreationSet};
 
@@ -64,6 +78,11 @@ mod api {
 	#[derive(Event, Debug)]
 	pub struct PlayerLeave(pub ClientId);
 
+	/// Sent once a player that left didn't reconnect within [PlayerSlots::RECONNECT_GRACE],
+	/// so anything kept around for them can be cleaned up.
+	#[derive(Event, Debug)]
+	pub struct PlayerSlotExpired(pub ClientId);
+
 	#[derive(Component, Reflect, Debug, Serialize, Deserialize)]
 	pub struct NetworkId(u64);
 
@@ -127,12 +146,34 @@ impl NetcodePlugin {
 		let delta = game_clock.frame().saturating_sub(replicon_tick.get());
 		replicon_tick.increment_by(delta);
 	}
+
+	/// How many frames clients predict ahead of the last tick received from the server
+	const PREDICTION_LEAD: FrameNumber = 4;
+
+	/// On clients the [RepliconTick] is the last tick received from the server,
+	/// which the [GameClock] is kept ahead of so that authoritative snapshots
+	/// always arrive for frames that have already been predicted.
+	fn frame_inc_and_predict_ahead(
+		mut game_clock: ResMut<GameClock>,
+		replicon_tick: Res<RepliconTick>,
+	) {
+		game_clock.advance(1);
+		if game_clock.frame() < replicon_tick.get() {
+			let behind = replicon_tick.get() - game_clock.frame();
+			game_clock.advance(behind + Self::PREDICTION_LEAD);
+		}
+	}
 }
 
 mod systems {
-	use crate::prelude::*;
+	use std::path::Path;
+
+	use crate::{
+		persistence::{RestoreWorldEvent, WorldStore},
+		prelude::*,
+	};
 
-	use super::world_creation::CreateWorldEvent;
+	use super::{offline::OfflineSession, world_creation::CreateWorldEvent};
 
 	impl NetcodePlugin {
 		/// sets up the server / client depending on [NetcodeConfig]
@@ -140,20 +181,21 @@ mod systems {
 			mut commands: Commands,
 			network_channels: Res<NetworkChannels>,
 			config: Res<NetcodeConfig>,
-			mut creation_event: EventWriter<CreateWorldEvent>,
+			world_store: Option<Res<WorldStore>>,
+			creation_event: EventWriter<CreateWorldEvent>,
+			restore_event: EventWriter<RestoreWorldEvent>,
 			mut server_non_headless_join: EventWriter<PlayerJoin>,
 		) {
 			match config.into_inner() {
-				NetcodeConfig::Server { ip, port, headless } => {
+				NetcodeConfig::Server {
+					ip,
+					port,
+					headless,
+					max_clients,
+					private_key,
+				} => {
 					info!("Setting up as server, hosting on {}:{}", ip, port);
-					let server_channels_config = network_channels.get_server_configs();
-					let client_channels_config = network_channels.get_client_configs();
-
-					let server = RenetServer::new(ConnectionConfig {
-						server_channels_config,
-						client_channels_config,
-						..Default::default()
-					});
+					let server = RenetServer::new(connection_config(&network_channels));
 
 					let current_time = SystemTime::now()
 						.duration_since(SystemTime::UNIX_EPOCH)
@@ -162,80 +204,124 @@ mod systems {
 
 					let socket = UdpSocket::bind(public_addr).expect("Couldn't bind to UdpSocket");
 
+					let authentication = match private_key {
+						Some(path) => {
+							info!("Only accepting clients with connect tokens signed by {:?}", path);
+							let private_key = PrivateKey::load(path).expect("Couldn't load private key");
+							ServerAuthentication::Secure {
+								private_key: *private_key.as_bytes(),
+							}
+						}
+						None => {
+							if !ip.is_loopback() {
+								warn!(
+									"Hosting on {} without a private key, anybody can connect as any client!",
+									ip
+								);
+							}
+							ServerAuthentication::Unsecure
+						}
+					};
+
 					let server_config = ServerConfig {
 						current_time,
-						max_clients: 10,
+						max_clients: *max_clients + PlayerSlots::REJECTION_HEADROOM,
 						protocol_id: PROTOCOL_ID,
 						public_addresses: vec![public_addr],
-						authentication: ServerAuthentication::Unsecure,
+						authentication,
 					};
 					let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
 
 					commands.insert_resource(server);
 					commands.insert_resource(transport);
+					commands.insert_resource(PlayerSlots::new(*max_clients));
+
+					create_or_restore_world(world_store, creation_event, restore_event);
 
 					if !headless {
-						trace!("Sending CreateWorldEvent");
-						creation_event.send(CreateWorldEvent);
 						trace!("Sending PlayerJoin(0)");
 						server_non_headless_join.send(PlayerJoin(SERVER_ID));
 					}
 				}
-				NetcodeConfig::Client { ip, port } => {
-					info!(
-						"Setting up as client, connecting to {:?} on port {}",
-						ip, port
-					);
-					let server_channels_config = network_channels.get_server_configs();
-					let client_channels_config = network_channels.get_client_configs();
-
-					let client = RenetClient::new(ConnectionConfig {
-						server_channels_config,
-						client_channels_config,
-						..Default::default()
-					});
-
-					let current_time = SystemTime::now()
-						.duration_since(SystemTime::UNIX_EPOCH)
-						.unwrap();
-					let client_id = ClientId::from_raw(current_time.as_millis() as u64);
-					let server_addr = SocketAddr::new(*ip, *port);
-					let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
-						.expect("Couldn't bind to (unspecified) socket");
-					let authentication = ClientAuthentication::Unsecure {
-						client_id: client_id.raw(),
-						protocol_id: PROTOCOL_ID,
-						server_addr,
-						user_data: None,
-					};
-					let transport = NetcodeClientTransport::new(current_time, authentication, socket)
-						.expect("Couldn't join to server");
+				NetcodeConfig::Client { ip, port, token } => {
+					match token {
+						// the server's address is baked into the token
+						Some(path) => info!(
+							"Setting up as client, connecting with connect token {:?}",
+							path
+						),
+						None => info!(
+							"Setting up as client, connecting to {:?} on port {}",
+							ip, port
+						),
+					}
+					let (client, transport) = new_client(
+						connection_config(&network_channels),
+						*ip,
+						*port,
+						token.as_deref(),
+					)
+					.expect("Couldn't join to server");
 
 					commands.insert_resource(client);
 					commands.insert_resource(transport);
 				}
+				NetcodeConfig::Offline {
+					ip,
+					port,
+					token,
+					poll_seconds,
+				} => {
+					info!(
+						"Playing offline, trying to reach the server at {}:{} every {} seconds",
+						ip, port, poll_seconds
+					);
+					commands.insert_resource(PlayerSlots::new(1));
+					commands.insert_resource(OfflineSession::new(
+						NetcodeConfig::Client {
+							ip: *ip,
+							port: *port,
+							token: token.clone(),
+						},
+						*poll_seconds,
+					));
+
+					create_or_restore_world(world_store, creation_event, restore_event);
+
+					trace!("Sending PlayerJoin(0)");
+					server_non_headless_join.send(PlayerJoin(SERVER_ID));
+				}
 			}
 		}
 
 		pub(super) fn disconnect_netcode(
 			config: Res<NetcodeConfig>,
-			mut client: ResMut<RenetClient>,
-			mut server: ResMut<RenetServer>,
+			client: Option<ResMut<RenetClient>>,
+			server: Option<ResMut<RenetServer>>,
 			mut commands: Commands,
 		) {
 			match config.into_inner() {
 				NetcodeConfig::Server { .. } => {
 					info!("Disconnecting as server");
-					server.disconnect_all();
+					if let Some(mut server) = server {
+						server.disconnect_all();
+					}
 					commands.remove_resource::<RenetServer>();
 					commands.remove_resource::<NetcodeClientTransport>();
 				}
 				NetcodeConfig::Client { .. } => {
 					info!("Disconnecting client");
-					client.disconnect();
+					if let Some(mut client) = client {
+						client.disconnect();
+					}
 					commands.remove_resource::<RenetClient>();
 					commands.remove_resource::<NetcodeClientTransport>();
 				}
+				NetcodeConfig::Offline { .. } => {
+					info!("Leaving offline game");
+					commands.remove_resource::<OfflineSession>();
+					commands.remove_resource::<PlayerSlots>();
+				}
 			}
 		}
 
@@ -261,9 +347,69 @@ mod systems {
 			}
 		}
 	}
+
+	/// Restores the world if the [WorldStore] has one saved, otherwise creates a new one
+	fn create_or_restore_world(
+		world_store: Option<Res<WorldStore>>,
+		mut creation_event: EventWriter<CreateWorldEvent>,
+		mut restore_event: EventWriter<RestoreWorldEvent>,
+	) {
+		let has_saved_world = world_store.is_some_and(|store| {
+			store
+				.has_saved_world()
+				.expect("Couldn't read the world store")
+		});
+		if has_saved_world {
+			trace!("Sending RestoreWorldEvent");
+			restore_event.send(RestoreWorldEvent);
+		} else {
+			trace!("Sending CreateWorldEvent");
+			creation_event.send(CreateWorldEvent);
+		}
+	}
+
+	pub(super) fn connection_config(network_channels: &NetworkChannels) -> ConnectionConfig {
+		ConnectionConfig {
+			server_channels_config: network_channels.get_server_configs(),
+			client_channels_config: network_channels.get_client_configs(),
+			..Default::default()
+		}
+	}
+
+	/// Starts connecting to the server at `ip` and `port`,
+	/// or to the server baked into the connect `token` if there is one
+	pub(super) fn new_client(
+		connection_config: ConnectionConfig,
+		ip: IpAddr,
+		port: u16,
+		token: Option<&Path>,
+	) -> Result<(RenetClient, NetcodeClientTransport), Box<dyn std::error::Error>> {
+		let client = RenetClient::new(connection_config);
+
+		let current_time = SystemTime::now()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap();
+		let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+		let authentication = match token {
+			Some(path) => ClientAuthentication::Secure {
+				connect_token: load_connect_token(path)?,
+			},
+			None => ClientAuthentication::Unsecure {
+				client_id: current_time.as_millis() as u64,
+				protocol_id: PROTOCOL_ID,
+				server_addr: SocketAddr::new(ip, port),
+				user_data: None,
+			},
+		};
+		let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
+
+		Ok((client, transport))
+	}
 }
 
 mod resources {
+	use std::path::PathBuf;
+
 	use crate::prelude::*;
 
 	/// Holds information about what ip and port to connect to, or host on.
@@ -279,6 +425,15 @@ mod resources {
 			/// Whether or not to run the server in headless mode.
 			#[arg(long, default_value_t = false)]
 			headless: bool,
+
+			/// Maximum number of players that can join at once, see [PlayerSlots].
+			#[arg(long, default_value_t = DEFAULT_MAX_CLIENTS)]
+			max_clients: usize,
+
+			/// File containing the private key that connect tokens are signed with.
+			/// If not provided, any client can connect without a connect token.
+			#[arg(long)]
+			private_key: Option<PathBuf>,
 		},
 		Client {
 			#[arg(short, long, default_value_t = Ipv4Addr::LOCALHOST.into())]
@@ -286,15 +441,40 @@ mod resources {
 
 			#[arg(short, long, default_value_t = DEFAULT_PORT)]
 			port: u16,
+
+			/// Connect token file issued for this client, required to join secure servers.
+			/// Overrides the `ip` and `port`, since the token contains the server's addresses.
+			#[arg(long)]
+			token: Option<PathBuf>,
+		},
+		/// Plays locally without a server, until the server at `ip` and `port` can be reached.
+		/// See [super::OfflineSession].
+		Offline {
+			#[arg(short, long, default_value_t = Ipv4Addr::LOCALHOST.into())]
+			ip: IpAddr,
+
+			#[arg(short, long, default_value_t = DEFAULT_PORT)]
+			port: u16,
+
+			/// Connect token file to join the server with once it can be reached, see `client --token`.
+			#[arg(long)]
+			token: Option<PathBuf>,
+
+			/// Seconds to wait between attempts to reach the server
+			#[arg(long, default_value_t = 5.)]
+			poll_seconds: f32,
 		},
 	}
 
 	impl NetcodeConfig {
+		/// Hosts unsecurely, see [PrivateKey] for letting only authorized clients join.
 		pub const fn new_hosting_public(headless: bool) -> Self {
 			NetcodeConfig::Server {
 				ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
 				port: DEFAULT_PORT,
 				headless,
+				max_clients: DEFAULT_MAX_CLIENTS,
+				private_key: None,
 			}
 		}
 
@@ -303,6 +483,8 @@ mod resources {
 				ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
 				port: DEFAULT_PORT,
 				headless,
+				max_clients: DEFAULT_MAX_CLIENTS,
+				private_key: None,
 			}
 		}
 
@@ -310,13 +492,23 @@ mod resources {
 			NetcodeConfig::Client {
 				ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
 				port: DEFAULT_PORT,
+				token: None,
+			}
+		}
+
+		pub const fn new_offline_machine_local() -> Self {
+			NetcodeConfig::Offline {
+				ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+				port: DEFAULT_PORT,
+				token: None,
+				poll_seconds: 5.,
 			}
 		}
 
 		pub const fn get_headless(&self) -> bool {
 			match self {
 				NetcodeConfig::Server { headless, .. } => *headless,
-				NetcodeConfig::Client { .. } => false,
+				NetcodeConfig::Client { .. } | NetcodeConfig::Offline { .. } => false,
 			}
 		}
 
@@ -326,13 +518,18 @@ mod resources {
 			|config| !config.into_inner().get_headless()
 		}
 
+		/// Offline games are authoritative over their local world
 		pub fn is_authoritative(&self) -> bool {
 			match self {
-				NetcodeConfig::Server { .. } => true,
+				NetcodeConfig::Server { .. } | NetcodeConfig::Offline { .. } => true,
 				NetcodeConfig::Client { .. } => false,
 			}
 		}
 
+		pub const fn is_offline(&self) -> bool {
+			matches!(self, NetcodeConfig::Offline { .. })
+		}
+
 		/// Used in a `.run_if` to signify a system that should only run if
 		/// the current instance is the authoritative server
 		pub fn has_authority() -> impl Fn(Res<NetcodeConfig>) -> bool {
@@ -0,0 +1,139 @@
+//! Secure authentication using renet's connect tokens.
+//!
+//! The server and whatever issues tokens (see the `space_craft_server issue-token` subcommand)
+//! share a [PrivateKey]. Clients are given a signed [ConnectToken] file which they present
+//! when connecting, which the server can verify without ever talking to the issuer.
+
+use std::{
+	fs::File,
+	io::{self, Read, Write},
+	path::Path,
+};
+
+use bevy_replicon::renet::transport::{ConnectToken, TokenGenerationError, NETCODE_KEY_BYTES};
+use rand::rngs::OsRng;
+
+use crate::prelude::*;
+
+/// Seconds without any packets before a connection made with a token times out
+const TOKEN_TIMEOUT_SECONDS: i32 = 15;
+
+/// The key shared between the server and the token issuer.
+/// Anybody with this key can let anybody join, so keep it secret!
+#[derive(Clone, PartialEq, Eq)]
+pub struct PrivateKey([u8; NETCODE_KEY_BYTES]);
+
+/// Doesn't leak the key into logs
+impl std::fmt::Debug for PrivateKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("PrivateKey(..)")
+	}
+}
+
+impl PrivateKey {
+	/// Generates a new random key from the OS's secure random number generator
+	pub fn generate() -> Self {
+		Self(OsRng.gen())
+	}
+
+	pub(super) fn as_bytes(&self) -> &[u8; NETCODE_KEY_BYTES] {
+		&self.0
+	}
+
+	/// Reads a raw key file, as written by [PrivateKey::save]
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		let bytes = std::fs::read(path)?;
+		let key = bytes.try_into().map_err(|bytes: Vec<u8>| {
+			io::Error::new(
+				io::ErrorKind::InvalidData,
+				format!(
+					"Private key must be exactly {} bytes, found {} bytes",
+					NETCODE_KEY_BYTES,
+					bytes.len()
+				),
+			)
+		})?;
+		Ok(Self(key))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		std::fs::write(path, self.0)
+	}
+
+	/// Signs a [ConnectToken] allowing `client_id` to connect to any of the `server_addresses`
+	/// within `expire` from now.
+	pub fn issue_connect_token(
+		&self,
+		client_id: ClientId,
+		server_addresses: Vec<SocketAddr>,
+		expire: Duration,
+	) -> Result<ConnectToken, TokenGenerationError> {
+		let current_time = SystemTime::now()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap();
+		ConnectToken::generate(
+			current_time,
+			PROTOCOL_ID,
+			expire.as_secs(),
+			client_id.raw(),
+			TOKEN_TIMEOUT_SECONDS,
+			server_addresses,
+			None,
+			self.as_bytes(),
+		)
+	}
+}
+
+pub fn save_connect_token(token: &ConnectToken, path: impl AsRef<Path>) -> io::Result<()> {
+	let mut file = File::create(path)?;
+	token.write(&mut file)?;
+	file.flush()
+}
+
+pub fn load_connect_token(path: impl AsRef<Path>) -> io::Result<ConnectToken> {
+	let mut bytes = Vec::new();
+	File::open(path)?.read_to_end(&mut bytes)?;
+	ConnectToken::read(&mut bytes.as_slice())
+		.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn private_key_round_trips() {
+		let path = std::env::temp_dir().join("space_craft_test_private.key");
+		let key = PrivateKey::generate();
+		key.save(&path).unwrap();
+
+		assert_eq!(PrivateKey::load(&path).unwrap(), key);
+	}
+
+	#[test]
+	fn private_key_rejects_wrong_length() {
+		let path = std::env::temp_dir().join("space_craft_test_short.key");
+		std::fs::write(&path, [0u8; 4]).unwrap();
+
+		assert!(PrivateKey::load(&path).is_err());
+	}
+
+	#[test]
+	fn connect_token_round_trips() {
+		let path = std::env::temp_dir().join("space_craft_test_connect.token");
+		let server_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), DEFAULT_PORT);
+		let token = PrivateKey::generate()
+			.issue_connect_token(
+				ClientId::from_raw(42),
+				vec![server_addr],
+				Duration::from_secs(60),
+			)
+			.unwrap();
+		save_connect_token(&token, &path).unwrap();
+
+		let loaded = load_connect_token(&path).unwrap();
+		assert_eq!(loaded.client_id, 42);
+		assert_eq!(loaded.protocol_id, PROTOCOL_ID);
+		assert_eq!(loaded.server_addresses[0], Some(server_addr));
+	}
+}
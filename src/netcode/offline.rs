@@ -0,0 +1,333 @@
+//! Playing without a server, see [NetcodeConfig::Offline].
+//!
+//! Offline games are authoritative over their own local world, like a server without a transport.
+//! Meanwhile they keep trying to reach the configured server, and once they can,
+//! the local world is despawned in favour of the server's and the game continues as a client.
+//! Anything that should survive going online is journaled while offline and replayed to the server,
+//! which validates it like any other client request, e.g. the ship editor's `OfflineJournal`.
+
+use crate::prelude::*;
+
+pub use api::*;
+
+pub(super) struct OfflinePlugin;
+
+impl Plugin for OfflinePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			(
+				Self::poll_server,
+				Self::go_online.run_if(OfflineSession::server_reachable()),
+			)
+				.chain()
+				.run_if(resource_exists::<OfflineSession>()),
+		);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	use super::probe::ServerProbe;
+
+	/// Present while playing offline, polls the server until it can be reached
+	#[derive(Resource)]
+	pub struct OfflineSession {
+		/// What to replace the [NetcodeConfig] with once online
+		pub(super) online: NetcodeConfig,
+		pub(super) poll: Timer,
+		/// The current attempt at reaching the server
+		pub(super) probe: Option<ServerProbe>,
+	}
+
+	impl OfflineSession {
+		pub(crate) fn new(online: NetcodeConfig, poll_seconds: f32) -> Self {
+			let mut poll = Timer::from_seconds(poll_seconds, TimerMode::Repeating);
+			// try reaching the server straight away
+			poll.set_elapsed(poll.duration());
+			Self {
+				online,
+				poll,
+				probe: None,
+			}
+		}
+
+		pub(super) fn server_reachable() -> impl Fn(Res<OfflineSession>) -> bool {
+			|session| {
+				session
+					.probe
+					.as_ref()
+					.is_some_and(ServerProbe::is_connected)
+			}
+		}
+	}
+}
+
+mod probe {
+	use std::path::Path;
+
+	use crate::{netcode::systems::new_client, prelude::*};
+
+	/// Whether a [ServerProbe] has reached the server yet
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub(super) enum ProbeStatus {
+		Connecting,
+		Connected,
+		/// Timed out or refused, try again later
+		Failed,
+	}
+
+	/// A client connection that is updated by hand instead of by the renet plugins,
+	/// so that replicon doesn't treat the offline game as a client until it actually connects.
+	pub(crate) struct ServerProbe {
+		pub(super) client: RenetClient,
+		pub(super) transport: NetcodeClientTransport,
+	}
+
+	impl ServerProbe {
+		pub(super) fn connect(
+			connection_config: ConnectionConfig,
+			ip: IpAddr,
+			port: u16,
+			token: Option<&Path>,
+		) -> Result<Self, Box<dyn std::error::Error>> {
+			let (client, transport) = new_client(connection_config, ip, port, token)?;
+			Ok(Self { client, transport })
+		}
+
+		pub(super) fn is_connected(&self) -> bool {
+			self.client.is_connected()
+		}
+
+		/// Sends and receives packets, like the renet plugins would every frame
+		pub(super) fn update(&mut self, delta: Duration) -> ProbeStatus {
+			self.client.update(delta);
+			if let Err(err) = self.transport.update(delta, &mut self.client) {
+				debug!("Couldn't reach the server: {}", err);
+				return ProbeStatus::Failed;
+			}
+			if self.client.is_disconnected() {
+				debug!(
+					"Couldn't reach the server: {:?}",
+					self.client.disconnect_reason()
+				);
+				return ProbeStatus::Failed;
+			}
+			if self.client.is_connected() {
+				return ProbeStatus::Connected;
+			}
+			if let Err(err) = self.transport.send_packets(&mut self.client) {
+				debug!("Couldn't reach the server: {}", err);
+				return ProbeStatus::Failed;
+			}
+			ProbeStatus::Connecting
+		}
+	}
+}
+
+mod systems {
+	use crate::{
+		netcode::systems::connection_config, persistence::despawn_persistent_bundles, prelude::*,
+	};
+
+	use super::{
+		probe::{ProbeStatus, ServerProbe},
+		OfflinePlugin, OfflineSession,
+	};
+
+	impl OfflinePlugin {
+		/// Starts a [ServerProbe] every poll, and keeps it updated until it connects or fails
+		pub(super) fn poll_server(
+			mut session: ResMut<OfflineSession>,
+			network_channels: Res<NetworkChannels>,
+			time: Res<Time>,
+		) {
+			let session = session.as_mut();
+			match session
+				.probe
+				.as_mut()
+				.map(|probe| probe.update(time.delta()))
+			{
+				Some(ProbeStatus::Connecting | ProbeStatus::Connected) => return,
+				Some(ProbeStatus::Failed) => session.probe = None,
+				None => {}
+			}
+
+			if !session.poll.tick(time.delta()).just_finished() {
+				return;
+			}
+			let NetcodeConfig::Client { ip, port, token } = &session.online else {
+				unreachable!("Offline sessions only go online as clients")
+			};
+			trace!("Trying to reach the server at {}:{}", ip, port);
+			match ServerProbe::connect(
+				connection_config(&network_channels),
+				*ip,
+				*port,
+				token.as_deref(),
+			) {
+				Ok(probe) => session.probe = Some(probe),
+				Err(err) => warn!("Couldn't start connecting to the server: {}", err),
+			}
+		}
+
+		/// Hands the connected probe over to replicon, and replaces the local world with the server's
+		pub(super) fn go_online(world: &mut World) {
+			let Some(OfflineSession {
+				online,
+				probe: Some(ServerProbe { client, transport }),
+				..
+			}) = world.remove_resource::<OfflineSession>()
+			else {
+				return;
+			};
+			info!(
+				"Reached the server as client {}, going online",
+				transport.client_id()
+			);
+
+			despawn_persistent_bundles(world);
+			world.remove_resource::<PlayerSlots>();
+			world.insert_resource(online);
+			world.insert_resource(client);
+			world.insert_resource(transport);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::probe::{ProbeStatus, ServerProbe};
+	use crate::{
+		players::{OfflineJournal, PlayerBlueprintComponent, ShipEdit, ShipEditRequest, WaveConfig},
+		prelude::*,
+		DedicatedServerConfig, DedicatedServerPlugin,
+	};
+
+	/// A headless game, which runs as a server unless its [NetcodeConfig] is replaced
+	fn headless_app(config: DedicatedServerConfig) -> App {
+		let mut app = App::new();
+		app
+			.add_plugins(MinimalPlugins)
+			.add_plugins(DedicatedServerPlugin::new(config));
+		app
+	}
+
+	/// Updates all of the `apps` until `done`, panicking if that takes too long
+	fn update_until(apps: &mut [&mut App], mut done: impl FnMut(&mut [&mut App]) -> bool) {
+		let delta = Duration::from_millis(10);
+		for _ in 0..1000 {
+			for app in apps.iter_mut() {
+				app.update();
+			}
+			if done(apps) {
+				return;
+			}
+			std::thread::sleep(delta);
+		}
+		panic!("Timed out");
+	}
+
+	/// Whether every player in `app` has a block at `location`, or [None] if there are no players
+	fn players_have_block(app: &mut App, location: manual_builder::RelativePixel) -> Option<bool> {
+		let mut players = app.world.query::<&PlayerBlueprintComponent>();
+		let mut players = players.iter(&app.world).peekable();
+		players.peek()?;
+		Some(players.all(|player| player.block_at(location).is_some()))
+	}
+
+	#[test]
+	fn probe_reaches_loopback_server() {
+		let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+		let address = socket.local_addr().unwrap();
+		let mut server = RenetServer::new(ConnectionConfig::default());
+		let mut transport = NetcodeServerTransport::new(
+			ServerConfig {
+				current_time: SystemTime::now()
+					.duration_since(SystemTime::UNIX_EPOCH)
+					.unwrap(),
+				max_clients: 1,
+				protocol_id: PROTOCOL_ID,
+				public_addresses: vec![address],
+				authentication: ServerAuthentication::Unsecure,
+			},
+			socket,
+		)
+		.unwrap();
+
+		let mut probe = ServerProbe::connect(
+			ConnectionConfig::default(),
+			address.ip(),
+			address.port(),
+			None,
+		)
+		.unwrap();
+		assert!(!probe.is_connected());
+
+		let delta = Duration::from_millis(10);
+		for _ in 0..500 {
+			server.update(delta);
+			transport.update(delta, &mut server).unwrap();
+			match probe.update(delta) {
+				ProbeStatus::Connected => {
+					assert_eq!(server.connected_clients(), 1);
+					return;
+				}
+				ProbeStatus::Failed => panic!("Probe couldn't reach the loopback server"),
+				ProbeStatus::Connecting => {}
+			}
+			transport.send_packets(&mut server);
+			std::thread::sleep(delta);
+		}
+		panic!("Probe didn't reach the loopback server in time");
+	}
+
+	#[test]
+	fn replays_offline_edits_once_online() {
+		let port = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+			.unwrap()
+			.local_addr()
+			.unwrap()
+			.port();
+		let config = DedicatedServerConfig {
+			bind_address: Ipv4Addr::LOCALHOST.into(),
+			port,
+			waves: WaveConfig {
+				enabled: false,
+				..default()
+			},
+			..default()
+		};
+		let mut offline = headless_app(config.clone());
+		offline.insert_resource(NetcodeConfig::Offline {
+			ip: Ipv4Addr::LOCALHOST.into(),
+			port,
+			token: None,
+			poll_seconds: 0.1,
+		});
+		let engine = manual_builder::RelativePixel::new(0, 0, 3);
+
+		// nothing to reach yet, so the edit is applied locally and journaled
+		update_until(&mut [&mut offline], |apps| {
+			players_have_block(apps[0], engine) == Some(true)
+		});
+		offline
+			.world
+			.send_event(ShipEditRequest(ShipEdit::Remove { location: engine }));
+		update_until(&mut [&mut offline], |apps| {
+			players_have_block(apps[0], engine) == Some(false)
+		});
+		assert_eq!(offline.world.resource::<OfflineJournal>().0.len(), 1);
+		assert!(offline.world.contains_resource::<OfflineSession>());
+
+		// once the server is up the offline game goes online, and replays the journal to it
+		let mut server = headless_app(config);
+		update_until(&mut [&mut offline, &mut server], |apps| {
+			players_have_block(apps[1], engine) == Some(false)
+		});
+		assert!(!offline.world.contains_resource::<OfflineSession>());
+		assert!(offline.world.contains_resource::<RenetClient>());
+		assert!(offline.world.resource::<OfflineJournal>().0.is_empty());
+	}
+}
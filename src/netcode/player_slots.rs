@@ -0,0 +1,287 @@
+//! Limits how many players can join a server,
+//! and tells the players that can't join why.
+
+use crate::prelude::*;
+
+pub use api::*;
+
+pub(super) struct PlayerSlotsPlugin;
+
+impl Plugin for PlayerSlotsPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.add_server_event::<JoinRejection>(EventType::Ordered)
+			.add_systems(
+				Update,
+				(
+					(
+						Self::reserve_slots_of_leaving_players,
+						Self::expire_reserved_slots,
+						Self::send_join_rejections,
+					)
+						.chain()
+						.run_if(resource_exists::<PlayerSlots>()),
+					Self::receive_join_rejections.in_set(Client),
+				),
+			);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	/// Why a player was not allowed to join, sent to the client just before it is disconnected.
+	#[derive(Event, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+	pub enum JoinRejection {
+		/// Every player slot is taken, including slots reserved for players that may reconnect
+		ServerFull { max_players: usize },
+
+		/// Every spawn point is occupied
+		NoSpawnPoints,
+	}
+
+	impl std::fmt::Display for JoinRejection {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				JoinRejection::ServerFull { max_players } => {
+					write!(f, "The server is full ({} players max)", max_players)
+				}
+				JoinRejection::NoSpawnPoints => write!(f, "There are no free spawn points"),
+			}
+		}
+	}
+
+	/// Inserted on clients when the server rejected them, so the UI can show the player why.
+	#[derive(Resource, Debug, Clone, Deref)]
+	pub struct JoinRejected(pub JoinRejection);
+
+	/// Whether a player joined into a new slot, or reconnected into the slot they left
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum SlotClaim {
+		New,
+		Reclaimed,
+	}
+
+	/// Server side bookkeeping of which players occupy which slots.
+	///
+	/// Players that leave keep their slot for [PlayerSlots::RECONNECT_GRACE],
+	/// so that if they reconnect with the same [ClientId] they get their slot back.
+	#[derive(Resource, Debug)]
+	pub struct PlayerSlots {
+		max_players: usize,
+		connected: HashSet<ClientId>,
+		/// Remaining grace period of players that recently left
+		reserved: HashMap<ClientId, Duration>,
+		/// Rejected players that still need to be told, see [JoinRejection]
+		rejected: Vec<(ClientId, JoinRejection)>,
+	}
+
+	impl PlayerSlots {
+		pub const RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
+		/// Extra connections the transport allows past `max_players`,
+		/// so that players over the limit can still be sent a [JoinRejection]
+		pub const REJECTION_HEADROOM: usize = 4;
+
+		pub fn new(max_players: usize) -> Self {
+			Self {
+				max_players,
+				connected: HashSet::new(),
+				reserved: HashMap::new(),
+				rejected: Vec::new(),
+			}
+		}
+
+		/// Claims a slot for `id`, giving them back their reserved slot if they recently left.
+		pub fn try_claim(&mut self, id: ClientId) -> Result<SlotClaim, JoinRejection> {
+			if self.connected.contains(&id) {
+				return Ok(SlotClaim::Reclaimed);
+			}
+			if self.reserved.remove(&id).is_some() {
+				self.connected.insert(id);
+				return Ok(SlotClaim::Reclaimed);
+			}
+			if self.connected.len() + self.reserved.len() >= self.max_players {
+				return Err(JoinRejection::ServerFull {
+					max_players: self.max_players,
+				});
+			}
+			self.connected.insert(id);
+			Ok(SlotClaim::New)
+		}
+
+		/// Reserves the slot of a player that left, see [PlayerSlots::RECONNECT_GRACE]
+		pub fn leave(&mut self, id: ClientId) {
+			if self.connected.remove(&id) {
+				self.reserved.insert(id, Self::RECONNECT_GRACE);
+			}
+		}
+
+		/// Reserves a slot for `id` as if they had just left,
+		/// e.g. for players restored from a saved world who haven't reconnected yet
+		pub fn park(&mut self, id: ClientId) {
+			if !self.connected.contains(&id) {
+				self.reserved.insert(id, Self::RECONNECT_GRACE);
+			}
+		}
+
+		/// Immediately frees the slot of `id`, without reserving it
+		pub fn free(&mut self, id: ClientId) {
+			self.connected.remove(&id);
+			self.reserved.remove(&id);
+		}
+
+		/// Counts down the grace periods, returning the players whose reserved slots were freed
+		pub fn tick(&mut self, delta: Duration) -> Vec<ClientId> {
+			let mut expired = Vec::new();
+			self.reserved.retain(|id, remaining| {
+				*remaining = remaining.saturating_sub(delta);
+				if remaining.is_zero() {
+					expired.push(*id);
+					false
+				} else {
+					true
+				}
+			});
+			expired
+		}
+
+		/// Queues telling `id` why they were rejected, after which they are disconnected
+		pub fn reject(&mut self, id: ClientId, reason: JoinRejection) {
+			self.rejected.push((id, reason));
+		}
+
+		pub(super) fn drain_rejections(
+			&mut self,
+		) -> impl Iterator<Item = (ClientId, JoinRejection)> + '_ {
+			self.rejected.drain(..)
+		}
+	}
+}
+
+mod systems {
+	use crate::prelude::*;
+
+	use super::{JoinRejected, JoinRejection, PlayerSlots, PlayerSlotsPlugin};
+
+	impl PlayerSlotsPlugin {
+		/// How long to wait between sending a [JoinRejection] and disconnecting,
+		/// so that the rejection actually reaches the client
+		const DISCONNECT_DELAY: Duration = Duration::from_millis(500);
+
+		pub(super) fn reserve_slots_of_leaving_players(
+			mut slots: ResMut<PlayerSlots>,
+			mut player_leaves: EventReader<PlayerLeave>,
+		) {
+			for PlayerLeave(id) in player_leaves.read() {
+				slots.leave(*id);
+			}
+		}
+
+		pub(super) fn expire_reserved_slots(
+			mut slots: ResMut<PlayerSlots>,
+			mut expired: EventWriter<PlayerSlotExpired>,
+			time: Res<Time>,
+		) {
+			for id in slots.tick(time.delta()) {
+				info!("Player {} didn't reconnect in time, freeing their slot", id);
+				expired.send(PlayerSlotExpired(id));
+			}
+		}
+
+		pub(super) fn send_join_rejections(
+			mut slots: ResMut<PlayerSlots>,
+			mut rejections: EventWriter<ToClients<JoinRejection>>,
+			mut server: ResMut<RenetServer>,
+			mut disconnecting: Local<Vec<(ClientId, Duration)>>,
+			time: Res<Time>,
+		) {
+			for (id, reason) in slots.drain_rejections() {
+				warn!("Rejecting player {}: {}", id, reason);
+				rejections.send(ToClients {
+					mode: SendMode::Direct(id),
+					event: reason,
+				});
+				disconnecting.push((id, Self::DISCONNECT_DELAY));
+			}
+
+			disconnecting.retain_mut(|(id, remaining)| {
+				*remaining = remaining.saturating_sub(time.delta());
+				if remaining.is_zero() {
+					server.disconnect(*id);
+					false
+				} else {
+					true
+				}
+			});
+		}
+
+		pub(super) fn receive_join_rejections(
+			mut rejections: EventReader<JoinRejection>,
+			mut commands: Commands,
+		) {
+			for reason in rejections.read() {
+				error!("Server rejected joining: {}", reason);
+				commands.insert_resource(JoinRejected(reason.clone()));
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn id(raw: u64) -> ClientId {
+		ClientId::from_raw(raw)
+	}
+
+	#[test]
+	fn rejects_over_max_players() {
+		let mut slots = PlayerSlots::new(2);
+
+		assert_eq!(slots.try_claim(id(1)), Ok(SlotClaim::New));
+		assert_eq!(slots.try_claim(id(2)), Ok(SlotClaim::New));
+		assert_eq!(
+			slots.try_claim(id(3)),
+			Err(JoinRejection::ServerFull { max_players: 2 })
+		);
+	}
+
+	#[test]
+	fn reconnecting_within_grace_reclaims_slot() {
+		let mut slots = PlayerSlots::new(1);
+
+		assert_eq!(slots.try_claim(id(1)), Ok(SlotClaim::New));
+		slots.leave(id(1));
+		assert!(slots.tick(PlayerSlots::RECONNECT_GRACE / 2).is_empty());
+
+		// slot is still reserved for player 1
+		assert!(slots.try_claim(id(2)).is_err());
+		assert_eq!(slots.try_claim(id(1)), Ok(SlotClaim::Reclaimed));
+	}
+
+	#[test]
+	fn reserved_slot_is_freed_after_grace() {
+		let mut slots = PlayerSlots::new(1);
+
+		assert_eq!(slots.try_claim(id(1)), Ok(SlotClaim::New));
+		slots.leave(id(1));
+		assert_eq!(slots.tick(PlayerSlots::RECONNECT_GRACE), vec![id(1)]);
+
+		assert_eq!(slots.try_claim(id(2)), Ok(SlotClaim::New));
+	}
+
+	#[test]
+	fn parked_players_expire_unless_they_reconnect() {
+		let mut slots = PlayerSlots::new(2);
+
+		slots.park(id(1));
+		slots.park(id(2));
+		assert!(slots.try_claim(id(3)).is_err());
+		assert_eq!(slots.try_claim(id(1)), Ok(SlotClaim::Reclaimed));
+
+		assert_eq!(slots.tick(PlayerSlots::RECONNECT_GRACE), vec![id(2)]);
+		assert_eq!(slots.try_claim(id(3)), Ok(SlotClaim::New));
+	}
+}
@@ -0,0 +1,487 @@
+//! Saving the world to an embedded SurrealDB store, and restoring it when the server restarts.
+//!
+//! Networked blueprints opt in with [PersistAppExt::persist], and are saved
+//! every [WorldStoreConfig::autosave_seconds] and when the app exits.
+//! If the store already has a saved world when the server starts, it is restored
+//! instead of creating a new world, see [RestoreWorldEvent].
+//!
+//! Anything that isn't saved as a [PersistentBundle] can save itself in the [SaveWorld] schedule
+//! and restore itself in the [RestoreWorld] schedule, using [WorldStore::save_record] and [WorldStore::load_record].
+
+use crate::prelude::*;
+
+pub use api::*;
+
+/// Only does anything if a [WorldStoreConfig] was inserted before this plugin is added
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.init_resource::<PersistentTables>()
+			.add_event::<RestoreWorldEvent>()
+			.init_schedule(SaveWorld)
+			.init_schedule(RestoreWorld);
+
+		let Some(config) = app.world.get_resource::<WorldStoreConfig>().cloned() else {
+			return;
+		};
+		info!("Persisting the world to {:?}", config.engine);
+		let store = WorldStore::open(&config.engine)
+			.unwrap_or_else(|err| panic!("Couldn't open world store {:?}: {}", config.engine, err));
+
+		app
+			.insert_resource(store)
+			.insert_resource(Autosave(Timer::from_seconds(
+				config.autosave_seconds,
+				TimerMode::Repeating,
+			)))
+			.add_systems(
+				FixedUpdate,
+				Self::handle_restore_world_events.in_set(GlobalSystemSet::WorldCreation),
+			)
+			.add_systems(Update, Self::autosave)
+			.add_systems(Last, Self::save_on_exit);
+	}
+}
+
+mod api {
+	use std::path::PathBuf;
+
+	use bevy::ecs::schedule::ScheduleLabel;
+
+	use surrealdb::{
+		engine::local::{Db, Mem, RocksDb},
+		Surreal,
+	};
+
+	use crate::prelude::*;
+
+	/// Where and how often the world is saved, see [super::PersistencePlugin]
+	#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+	#[serde(default, deny_unknown_fields)]
+	pub struct WorldStoreConfig {
+		pub engine: StorageEngine,
+		pub autosave_seconds: f32,
+	}
+
+	impl Default for WorldStoreConfig {
+		fn default() -> Self {
+			Self {
+				engine: StorageEngine::Memory,
+				autosave_seconds: 60.,
+			}
+		}
+	}
+
+	/// Which SurrealDB key-value engine the [WorldStore] uses
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	#[serde(rename_all = "snake_case")]
+	pub enum StorageEngine {
+		/// Lost when the process exits, mostly useful for testing
+		Memory,
+		/// A directory on disk
+		File(PathBuf),
+	}
+
+	/// A [Bundle] that is saved to the [WorldStore], and spawned again when the world is restored.
+	/// Register with [PersistAppExt::persist].
+	pub trait PersistentBundle: Bundle + Serialize + DeserializeOwned {
+		/// The SurrealDB table these bundles are saved in
+		const TABLE: &'static str;
+
+		/// Reads the current state of a spawned entity back into a bundle,
+		/// or [None] if the entity isn't one of these
+		fn from_entity(entity: EntityRef) -> Option<Self>;
+
+		/// Spawns a bundle that was restored from the [WorldStore]
+		fn restore(self, world: &mut World) {
+			world.spawn(self);
+		}
+	}
+
+	pub trait PersistAppExt {
+		fn persist<B: PersistentBundle>(&mut self) -> &mut Self;
+	}
+
+	impl PersistAppExt for App {
+		fn persist<B: PersistentBundle>(&mut self) -> &mut Self {
+			self
+				.world
+				.get_resource_or_insert_with(PersistentTables::default)
+				.0
+				.push(PersistentTable {
+					name: B::TABLE,
+					save: save_table::<B>,
+					restore: restore_table::<B>,
+					despawn: despawn_table::<B>,
+				});
+			self
+		}
+	}
+
+	/// Sent instead of running the [crate::netcode::WorldCreation] schedule
+	/// when the [WorldStore] has a saved world
+	#[derive(Event, Debug)]
+	pub struct RestoreWorldEvent;
+
+	/// Run whenever the world is saved, after the [PersistentBundle]s
+	#[derive(ScheduleLabel, Hash, Debug, Clone, Eq, PartialEq)]
+	pub struct SaveWorld;
+
+	/// Run when the world is restored, after the [PersistentBundle]s but before they are expanded
+	#[derive(ScheduleLabel, Hash, Debug, Clone, Eq, PartialEq)]
+	pub struct RestoreWorld;
+
+	/// Every registered [PersistentBundle]
+	#[derive(Resource, Default)]
+	pub(super) struct PersistentTables(pub(super) Vec<PersistentTable>);
+
+	#[derive(Clone, Copy)]
+	pub(super) struct PersistentTable {
+		pub(super) name: &'static str,
+		pub(super) save: fn(&World) -> Result<Vec<StoredBundle>, serde_json::Error>,
+		pub(super) restore: fn(&mut World, Vec<StoredBundle>) -> Result<(), serde_json::Error>,
+		pub(super) despawn: fn(&mut World),
+	}
+
+	fn save_table<B: PersistentBundle>(
+		world: &World,
+	) -> Result<Vec<StoredBundle>, serde_json::Error> {
+		world
+			.iter_entities()
+			.filter_map(B::from_entity)
+			.map(|bundle| {
+				Ok(StoredBundle {
+					json: serde_json::to_string(&bundle)?,
+				})
+			})
+			.collect()
+	}
+
+	fn restore_table<B: PersistentBundle>(
+		world: &mut World,
+		stored: Vec<StoredBundle>,
+	) -> Result<(), serde_json::Error> {
+		for StoredBundle { json } in stored {
+			serde_json::from_str::<B>(&json)?.restore(world);
+		}
+		Ok(())
+	}
+
+	fn despawn_table<B: PersistentBundle>(world: &mut World) {
+		let entities: Vec<Entity> = world
+			.iter_entities()
+			.filter(|entity| B::from_entity(*entity).is_some())
+			.map(|entity| entity.id())
+			.collect();
+		for entity in entities {
+			despawn_with_children_recursive(world, entity);
+		}
+	}
+
+	/// Despawns everything that would be saved, e.g. to replace the local world with a server's.
+	/// Doesn't touch the [WorldStore].
+	pub(crate) fn despawn_persistent_bundles(world: &mut World) {
+		let tables = world.resource::<PersistentTables>().0.clone();
+		for table in tables {
+			(table.despawn)(world);
+		}
+	}
+
+	/// How a single bundle is stored.
+	///
+	/// Bundles are stored as JSON instead of SurrealDB objects, since SurrealDB numbers
+	/// can't hold every [u64], like [BlockId]s and [NetworkId]s.
+	#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+	pub(super) struct StoredBundle {
+		pub(super) json: String,
+	}
+
+	/// Marks that a world was saved, and how
+	#[derive(Debug, Serialize, Deserialize)]
+	struct WorldMeta {
+		version: u32,
+	}
+
+	#[derive(Resource)]
+	pub(super) struct Autosave(pub(super) Timer);
+
+	#[derive(Debug)]
+	pub enum PersistenceError {
+		Db(surrealdb::Error),
+		Json(serde_json::Error),
+		/// The world was saved by a different version of the game
+		UnsupportedVersion(u32),
+	}
+
+	impl std::fmt::Display for PersistenceError {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				Self::Db(err) => write!(f, "World store error: {}", err),
+				Self::Json(err) => write!(f, "Couldn't (de)serialize a saved bundle: {}", err),
+				Self::UnsupportedVersion(version) => write!(
+					f,
+					"The world was saved with version {}, but only version {} is supported",
+					version,
+					WorldStore::VERSION
+				),
+			}
+		}
+	}
+
+	impl std::error::Error for PersistenceError {}
+
+	impl From<surrealdb::Error> for PersistenceError {
+		fn from(err: surrealdb::Error) -> Self {
+			Self::Db(err)
+		}
+	}
+
+	impl From<serde_json::Error> for PersistenceError {
+		fn from(err: serde_json::Error) -> Self {
+			Self::Json(err)
+		}
+	}
+
+	/// Connection to the embedded SurrealDB store.
+	///
+	/// SurrealDB is async, so the store runs its own small runtime and blocks on every call.
+	#[derive(Resource)]
+	pub struct WorldStore {
+		runtime: tokio::runtime::Runtime,
+		db: Surreal<Db>,
+	}
+
+	impl WorldStore {
+		/// Bump when [PersistentBundle]s or records are stored differently
+		pub const VERSION: u32 = 3;
+
+		pub fn open(engine: &StorageEngine) -> Result<Self, PersistenceError> {
+			let runtime = tokio::runtime::Builder::new_multi_thread()
+				.worker_threads(1)
+				.enable_all()
+				.build()
+				.expect("Couldn't start the world store's runtime");
+			let db = runtime.block_on(async {
+				let db = match engine {
+					StorageEngine::Memory => Surreal::new::<Mem>(()).await?,
+					StorageEngine::File(path) => Surreal::new::<RocksDb>(path.as_path()).await?,
+				};
+				db.use_ns("space_craft").use_db("world").await?;
+				Ok::<_, surrealdb::Error>(db)
+			})?;
+			Ok(Self { runtime, db })
+		}
+
+		pub fn has_saved_world(&self) -> Result<bool, PersistenceError> {
+			let meta: Option<WorldMeta> = self
+				.runtime
+				.block_on(async { self.db.select(("world", "meta")).await })?;
+			match meta {
+				None => Ok(false),
+				Some(WorldMeta { version }) if version == Self::VERSION => Ok(true),
+				Some(WorldMeta { version }) => Err(PersistenceError::UnsupportedVersion(version)),
+			}
+		}
+
+		/// Replaces the saved world with the `tables`, in a single transaction
+		pub(super) fn save(
+			&self,
+			tables: Vec<(&'static str, Vec<StoredBundle>)>,
+		) -> Result<(), PersistenceError> {
+			let mut query = self
+				.db
+				.query("BEGIN TRANSACTION")
+				.query("UPDATE world:meta CONTENT $meta")
+				.bind((
+					"meta",
+					WorldMeta {
+						version: Self::VERSION,
+					},
+				));
+			for (table, bundles) in tables {
+				// table names are constants, see [PersistentBundle::TABLE]
+				query = query.query(format!("DELETE {}", table));
+				if !bundles.is_empty() {
+					query = query
+						.query(format!("INSERT INTO {} ${}", table, table))
+						.bind((table, bundles));
+				}
+			}
+			let query = query.query("COMMIT TRANSACTION");
+
+			self.runtime.block_on(async { query.await })?.check()?;
+			Ok(())
+		}
+
+		pub(super) fn load(&self, table: &'static str) -> Result<Vec<StoredBundle>, PersistenceError> {
+			Ok(
+				self
+					.runtime
+					.block_on(async { self.db.select(table).await })?,
+			)
+		}
+
+		/// Saves a single `value` as the record `table:id`, replacing it if it was already saved
+		pub fn save_record<T: Serialize>(
+			&self,
+			table: &'static str,
+			id: &str,
+			value: &T,
+		) -> Result<(), PersistenceError> {
+			let stored = StoredBundle {
+				json: serde_json::to_string(value)?,
+			};
+			let _: Option<StoredBundle> = self
+				.runtime
+				.block_on(async { self.db.update((table, id)).content(stored).await })?;
+			Ok(())
+		}
+
+		/// Loads a record saved with [WorldStore::save_record], or [None] if it was never saved
+		pub fn load_record<T: DeserializeOwned>(
+			&self,
+			table: &'static str,
+			id: &str,
+		) -> Result<Option<T>, PersistenceError> {
+			let stored: Option<StoredBundle> = self
+				.runtime
+				.block_on(async { self.db.select((table, id)).await })?;
+			Ok(
+				stored
+					.map(|StoredBundle { json }| serde_json::from_str(&json))
+					.transpose()?,
+			)
+		}
+	}
+}
+
+mod systems {
+	use bevy::{app::AppExit, ecs::event::ManualEventReader};
+
+	use crate::prelude::*;
+
+	use super::{
+		Autosave, PersistencePlugin, PersistentTables, RestoreWorld, RestoreWorldEvent, SaveWorld,
+		WorldStore,
+	};
+
+	impl PersistencePlugin {
+		pub(super) fn handle_restore_world_events(world: &mut World) {
+			let restore = world
+				.resource_mut::<Events<RestoreWorldEvent>>()
+				.drain()
+				.count() > 0;
+			if !restore {
+				return;
+			}
+
+			info!("Restoring the saved world");
+			let tables = world.resource::<PersistentTables>().0.clone();
+			for table in tables {
+				let stored = match world.resource::<WorldStore>().load(table.name) {
+					Ok(stored) => stored,
+					Err(err) => {
+						error!("Couldn't load {} from the world store: {}", table.name, err);
+						continue;
+					}
+				};
+				debug!("Restoring {} {}", stored.len(), table.name);
+				if let Err(err) = (table.restore)(world, stored) {
+					error!("Couldn't restore {}: {}", table.name, err);
+				}
+			}
+			world.run_schedule(RestoreWorld);
+			info!("Running Blueprints schedule after restoring the world");
+			world.run_schedule(Blueprints);
+		}
+
+		pub(super) fn autosave(world: &mut World) {
+			let delta = world.resource::<Time>().delta();
+			if world
+				.resource_mut::<Autosave>()
+				.0
+				.tick(delta)
+				.just_finished()
+			{
+				Self::save_world(world);
+			}
+		}
+
+		pub(super) fn save_on_exit(world: &mut World, mut exits: Local<ManualEventReader<AppExit>>) {
+			let exiting = exits
+				.read(world.resource::<Events<AppExit>>())
+				.next()
+				.is_some();
+			if exiting {
+				info!("Saving the world before exiting");
+				Self::save_world(world);
+			}
+		}
+
+		fn save_world(world: &mut World) {
+			// clients only have a partial copy of the server's world, which isn't theirs to save
+			if !world
+				.get_resource::<NetcodeConfig>()
+				.is_some_and(NetcodeConfig::is_authoritative)
+			{
+				return;
+			}
+
+			let tables: Result<Vec<_>, serde_json::Error> = world
+				.resource::<PersistentTables>()
+				.0
+				.iter()
+				.map(|table| Ok((table.name, (table.save)(world)?)))
+				.collect();
+			let result = tables
+				.map_err(Into::into)
+				.and_then(|tables| world.resource::<WorldStore>().save(tables));
+			match result {
+				Ok(()) => debug!("Saved the world"),
+				Err(err) => error!("Couldn't save the world: {}", err),
+			}
+			world.run_schedule(SaveWorld);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{StorageEngine, StoredBundle, WorldStore};
+
+	fn stored(json: &str) -> StoredBundle {
+		StoredBundle {
+			json: json.to_owned(),
+		}
+	}
+
+	#[test]
+	fn saves_and_loads() {
+		let store = WorldStore::open(&StorageEngine::Memory).unwrap();
+		assert!(!store.has_saved_world().unwrap());
+
+		store
+			.save(vec![
+				("terrain", vec![stored("{\"a\":1}"), stored("{\"b\":2}")]),
+				("player", vec![]),
+			])
+			.unwrap();
+		assert!(store.has_saved_world().unwrap());
+
+		let mut terrain = store.load("terrain").unwrap();
+		terrain.sort_by(|a, b| a.json.cmp(&b.json));
+		assert_eq!(terrain, vec![stored("{\"a\":1}"), stored("{\"b\":2}")]);
+		assert!(store.load("player").unwrap().is_empty());
+	}
+
+	#[test]
+	fn saving_replaces_the_previous_save() {
+		let store = WorldStore::open(&StorageEngine::Memory).unwrap();
+		store.save(vec![("terrain", vec![stored("1")])]).unwrap();
+		store.save(vec![("terrain", vec![stored("2")])]).unwrap();
+
+		assert_eq!(store.load("terrain").unwrap(), vec![stored("2")]);
+	}
+}
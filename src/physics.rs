@@ -8,8 +8,11 @@ impl Plugin for PhysicsPlugin {
 			PhysicsPlugins::new(FixedUpdate),
 			bevy_xpbd_3d_parenting::PhysicsParentingPlugin::default(),
 		));
+		// the dedicated server has nothing to render debug gizmos with
 		#[cfg(feature = "debug")]
-		app.add_plugins(PhysicsDebugPlugin::default());
+		if app.is_plugin_added::<bevy::render::RenderPlugin>() {
+			app.add_plugins(PhysicsDebugPlugin::default());
+		}
 
 		app.insert_resource(Gravity(Vec3::ZERO));
 	}
@@ -1,9 +1,23 @@
 use crate::prelude::*;
 
+mod enemy_waves;
+mod fabricator_block;
+mod inventory;
+mod mining_tool_block;
 mod player;
 mod player_movement;
+mod recipes;
+mod ship_editor;
 mod spawn_points;
+mod ship_file;
 mod thruster_block;
+mod weapon_block;
+
+pub use enemy_waves::WaveConfig;
+pub use player::{PlayerBlueprintComponent, StarterShip};
+pub use recipes::RecipeBook;
+pub use ship_editor::{OfflineJournal, ShipEdit, ShipEditRequest};
+pub use ship_file::{load_ship, save_ship, ShipFileError, ShipFileFormat, SHIP_FILE_VERSION};
 
 /// Plugin Group
 pub struct PlayerPlugins;
@@ -11,6 +25,10 @@ pub struct PlayerPlugins;
 /// Handled in [self::player_movement]
 #[derive(SystemSet, Hash, Debug, PartialEq, Eq, Clone, Copy)]
 enum PlayerMovementSet {
+	/// Applies inputs received from clients (on the server),
+	/// and sends the local player's inputs (on clients)
+	ProcessInputs,
+
 	/// After this set, the strengths for each player are computed
 	ComputeStrengths,
 
@@ -24,8 +42,14 @@ impl PluginGroup for PlayerPlugins {
 		PluginGroupBuilder::start::<Self>()
 			.add(player::PlayerPlugin)
 			.add(thruster_block::ThrusterPlugin)
+			.add(mining_tool_block::MiningToolPlugin)
+			.add(inventory::InventoryPlugin)
+			.add(fabricator_block::FabricatorPlugin)
+			.add(weapon_block::WeaponPlugin)
+			.add(enemy_waves::EnemyWavesPlugin)
 			.add(spawn_points::SpawnPointsPlugin)
 			.add(player_movement::PlayerMovementPlugin)
+			.add(ship_editor::ShipEditorPlugin)
 			.build()
 	}
 }
@@ -0,0 +1,305 @@
+//! Escalating waves of enemy ships for players to defend against, see [WaveScheduler].
+//!
+//! Only the server spawns waves. Enemies are ships built from the same
+//! [PlayerBlueprintComponent](crate::players::player::PlayerBlueprintComponent) as players' ships,
+//! but without a [NetworkId], and are flown by an [AiPilot](crate::players::player_movement::AiPilot).
+//! There are no bases yet, so the players' ships are what is defended:
+//! every wave spawns around them and flies towards them.
+//! Enemies that can no longer fly or fight break apart into wreckage.
+
+use crate::prelude::*;
+
+pub use api::*;
+
+pub(super) struct EnemyWavesPlugin;
+
+impl Plugin for EnemyWavesPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<WaveConfig>().add_systems(
+			FixedUpdate,
+			(
+				Self::start_waves.run_if(not(resource_exists::<WaveScheduler>())),
+				Self::spawn_waves.run_if(resource_exists::<WaveScheduler>()),
+				Self::scrap_disabled_enemies,
+			)
+				.in_set(GlobalSystemSet::ExecuteGameLogic)
+				.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
+		);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	/// Configures when enemy waves are spawned, and how big they are.
+	/// The same config with the same [WaveConfig::seed] always spawns the same waves
+	#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+	#[serde(default, deny_unknown_fields)]
+	pub struct WaveConfig {
+		/// No waves are spawned if false
+		pub enabled: bool,
+		/// Seed for where enemies spawn, or [None] to use a random (logged) seed
+		pub seed: Option<u64>,
+		/// Before the first wave, counted only while players are online
+		pub first_wave_seconds: f32,
+		/// Between the start of one wave and the next
+		pub interval_seconds: f32,
+		/// Ships in the first wave
+		pub first_wave_ships: u32,
+		/// Extra ships in every wave after the first
+		pub extra_ships_per_wave: u32,
+		/// How far away from the players enemies spawn
+		pub spawn_distance: f32,
+		/// The next wave waits while this many enemies are still alive
+		pub max_alive: usize,
+	}
+
+	impl Default for WaveConfig {
+		fn default() -> Self {
+			Self {
+				enabled: true,
+				seed: None,
+				first_wave_seconds: 60.,
+				interval_seconds: 90.,
+				first_wave_ships: 1,
+				extra_ships_per_wave: 1,
+				spawn_distance: 60.,
+				max_alive: 12,
+			}
+		}
+	}
+
+	/// Decides when the next wave is due, and where its ships spawn.
+	///
+	/// Only depends on its [WaveConfig] and what it is given,
+	/// so the same seed and targets always produce the same waves
+	#[derive(Resource, Debug)]
+	pub struct WaveScheduler {
+		config: WaveConfig,
+		rng: StdRng,
+		/// Waves spawned so far
+		wave: u32,
+		/// Finishes when the next wave is due
+		timer: Timer,
+	}
+
+	impl WaveScheduler {
+		pub fn new(config: WaveConfig) -> Self {
+			let seed = config.seed.unwrap_or_else(random);
+			info!("Spawning enemy waves with seed {}", seed);
+			Self {
+				rng: StdRng::seed_from_u64(seed),
+				wave: 0,
+				timer: Timer::from_seconds(config.first_wave_seconds, TimerMode::Once),
+				config,
+			}
+		}
+
+		pub fn config(&self) -> &WaveConfig {
+			&self.config
+		}
+
+		/// How many waves have been spawned so far
+		pub fn wave(&self) -> u32 {
+			self.wave
+		}
+
+		/// How many ships the `wave`th wave has, counting from 1
+		pub fn ships_in_wave(&self, wave: u32) -> u32 {
+			self.config.first_wave_ships + self.config.extra_ships_per_wave * wave.saturating_sub(1)
+		}
+
+		/// Counts down to the next wave while there are `targets`.
+		/// Returns where the ships of the next wave spawn once it is due, see [Self::next_wave]
+		pub fn tick(&mut self, delta: Duration, targets: &[Vec3]) -> Vec<Transform> {
+			if !self.config.enabled || targets.is_empty() {
+				return Vec::new();
+			}
+			if !self.timer.tick(delta).finished() {
+				return Vec::new();
+			}
+			self.timer = Timer::from_seconds(self.config.interval_seconds, TimerMode::Once);
+			self.next_wave(targets)
+		}
+
+		/// Where the ships of the next wave spawn, spread over the `targets` and facing them.
+		/// Returns no ships if there is nothing to attack
+		pub fn next_wave(&mut self, targets: &[Vec3]) -> Vec<Transform> {
+			if targets.is_empty() {
+				return Vec::new();
+			}
+			self.wave += 1;
+			(0..self.ships_in_wave(self.wave) as usize)
+				.map(|ship| {
+					let target = targets[ship % targets.len()];
+					let direction = self.random_direction();
+					let up = if direction.y.abs() > 0.99 {
+						Vec3::Z
+					} else {
+						Vec3::Y
+					};
+					Transform::from_translation(target + direction * self.config.spawn_distance)
+						.looking_at(target, up)
+				})
+				.collect()
+		}
+
+		/// Uniformly distributed over the unit sphere
+		fn random_direction(&mut self) -> Vec3 {
+			loop {
+				let point = Vec3::new(
+					self.rng.gen_range(-1.0..=1.0),
+					self.rng.gen_range(-1.0..=1.0),
+					self.rng.gen_range(-1.0..=1.0),
+				);
+				let length = point.length();
+				if length > 0.01 && length <= 1. {
+					return point / length;
+				}
+			}
+		}
+	}
+}
+
+mod systems {
+	use crate::{
+		players::{
+			player::{ParkedPlayer, PlayerBlueprintComponent},
+			player_movement::AiPilot,
+		},
+		prelude::*,
+	};
+
+	use super::{EnemyWavesPlugin, WaveConfig, WaveScheduler};
+
+	impl EnemyWavesPlugin {
+		pub(super) fn start_waves(mut commands: Commands, config: Res<WaveConfig>) {
+			commands.insert_resource(WaveScheduler::new(config.clone()));
+		}
+
+		/// Spawns the next wave once it is due, around the ships of the players that are online
+		pub(super) fn spawn_waves(
+			mut commands: Commands,
+			mut scheduler: ResMut<WaveScheduler>,
+			players: Query<
+				(&NetworkId, &Transform),
+				(With<PlayerBlueprintComponent>, Without<ParkedPlayer>),
+			>,
+			enemies: Query<(), With<AiPilot>>,
+			time: Res<Time>,
+		) {
+			if enemies.iter().count() >= scheduler.config().max_alive {
+				return;
+			}
+			// sorted, since the query's order would make waves differ between runs
+			let mut players: Vec<(u64, Vec3)> = players
+				.iter()
+				.map(|(id, transform)| (id.get_network_id().raw(), transform.translation))
+				.collect();
+			players.sort_by_key(|(id, _)| *id);
+			let targets: Vec<Vec3> = players
+				.into_iter()
+				.map(|(_, translation)| translation)
+				.collect();
+
+			let ships = scheduler.tick(time.delta(), &targets);
+			if ships.is_empty() {
+				return;
+			}
+			info!(
+				"Wave {} is coming, with {} enemy ships",
+				scheduler.wave(),
+				ships.len()
+			);
+			for transform in ships {
+				commands.spawn((
+					PlayerBlueprintComponent::default_ship(),
+					transform,
+					AiPilot::default(),
+				));
+			}
+		}
+
+		/// Breaks enemies without any weapons or thrusters left into wreckage
+		pub(super) fn scrap_disabled_enemies(
+			mut commands: Commands,
+			enemies: Query<
+				(
+					Entity,
+					&PlayerBlueprintComponent,
+					&Transform,
+					&LinearVelocity,
+					&AngularVelocity,
+					&CenterOfMass,
+				),
+				(With<AiPilot>, Changed<PlayerBlueprintComponent>),
+			>,
+		) {
+			for (enemy, blueprint, transform, linvel, angvel, centre_of_mass) in enemies.iter() {
+				if !blueprint.is_disabled() {
+					continue;
+				}
+				debug!("An enemy ship was disabled");
+				let wreckage = TerrainStructureBlueprint::wreckage(
+					blueprint.occupied().collect(),
+					*transform,
+					*linvel,
+					*angvel,
+				);
+				for part in wreckage.split(centre_of_mass.0) {
+					let chunk = ChunkCoord::of(part.transform.translation);
+					commands.spawn((part, InChunk(chunk), Replication));
+				}
+				commands.entity(enemy).despawn_recursive();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::prelude::*;
+
+	use super::{WaveConfig, WaveScheduler};
+
+	fn config() -> WaveConfig {
+		WaveConfig {
+			seed: Some(7),
+			..default()
+		}
+	}
+
+	#[test]
+	fn waves_are_reproducible_and_escalate() {
+		let targets = [Vec3::ZERO, Vec3::new(100., 0., 0.)];
+		let mut scheduler = WaveScheduler::new(config());
+		let mut again = WaveScheduler::new(config());
+
+		let waves: Vec<Vec<Transform>> = (0..3).map(|_| scheduler.next_wave(&targets)).collect();
+		let same: Vec<Vec<Transform>> = (0..3).map(|_| again.next_wave(&targets)).collect();
+		assert_eq!(waves, same);
+		assert_eq!(
+			waves.iter().map(Vec::len).collect::<Vec<_>>(),
+			vec![1, 2, 3]
+		);
+		assert_eq!(scheduler.wave(), 3);
+
+		// spawned around their target, facing it
+		let ship = waves[2][1];
+		let target = targets[1];
+		assert_near!(ship.translation.distance(target), config().spawn_distance);
+		assert_vec3_near!(ship.forward(), (target - ship.translation).normalize());
+	}
+
+	#[test]
+	fn waits_for_players() {
+		let config = config();
+		let mut scheduler = WaveScheduler::new(config.clone());
+		let first_wave = Duration::from_secs_f32(config.first_wave_seconds);
+
+		assert!(scheduler.tick(first_wave, &[]).is_empty());
+		assert!(scheduler.tick(first_wave / 2, &[Vec3::ZERO]).is_empty());
+		assert_eq!(scheduler.tick(first_wave / 2, &[Vec3::ZERO]).len(), 1);
+		assert!(scheduler.tick(first_wave, &[Vec3::ZERO]).is_empty());
+	}
+}
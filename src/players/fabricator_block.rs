@@ -0,0 +1,364 @@
+//! Fabricators turn resources into blocks, following the server's [RecipeBook].
+//!
+//! Clients send [FabricationRequest]s, and the server takes the recipe's ingredients
+//! out of the player's inventory straight away and adds a job to their [FabricationQueue].
+//! Every fabricator on the player's ship works on one job at a time,
+//! and finished blocks are put into the player's inventory to be placed in the ship editor.
+
+use crate::{players::recipes::RecipeBook, prelude::*};
+
+pub use api::*;
+pub use blueprint::FabricatorBlockBlueprint;
+
+pub struct FabricatorPlugin;
+
+impl Plugin for FabricatorPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.register_type::<Fabricator>()
+			.register_type::<FabricationQueue>()
+			.init_resource::<RecipeBook>()
+			.add_client_event::<FabricationRequest>(EventType::Ordered)
+			.add_server_event::<FabricationRejected>(EventType::Ordered)
+			.add_systems(
+				Update,
+				(
+					Self::receive_fabrication_requests
+						.run_if(resource_exists::<RenetServer>().or_else(resource_exists::<OfflineSession>())),
+					Self::receive_fabrication_rejections.in_set(Client),
+				),
+			)
+			.add_systems(
+				GameLogic,
+				Self::fabricate
+					.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
+			);
+	}
+}
+
+mod api {
+	use crate::{
+		players::{inventory::Inventory, ship_editor::EditorBlock},
+		prelude::*,
+	};
+
+	/// Component for all fabricators (on a player)
+	#[derive(Debug, Component, Reflect)]
+	pub struct Fabricator {
+		pub(super) block_id: BlockId,
+	}
+
+	impl Fabricator {
+		pub const INTEGRITY: f32 = 120.;
+	}
+
+	impl GetBlockId for Fabricator {
+		fn get_block_id(&self) -> BlockId {
+			self.block_id
+		}
+	}
+
+	/// The blocks a player is waiting on, only kept by the server.
+	///
+	/// Lives on the player rather than its fabricators,
+	/// so that editing the ship doesn't lose any jobs.
+	#[derive(Component, Debug, Default, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+	pub struct FabricationQueue {
+		jobs: Vec<FabricationJob>,
+	}
+
+	#[derive(Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+	struct FabricationJob {
+		output: EditorBlock,
+		remaining_seconds: f32,
+	}
+
+	impl FabricationQueue {
+		pub const MAX_JOBS: usize = 16;
+
+		pub fn len(&self) -> usize {
+			self.jobs.len()
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.jobs.is_empty()
+		}
+
+		pub(super) fn push(&mut self, output: EditorBlock, seconds: f32) {
+			self.jobs.push(FabricationJob {
+				output,
+				remaining_seconds: seconds,
+			});
+		}
+
+		/// Works on the first unfinished job of each of the player's `fabricators`
+		pub(super) fn advance(&mut self, seconds: f32, fabricators: usize) {
+			for job in self
+				.jobs
+				.iter_mut()
+				.filter(|job| job.remaining_seconds > 0.)
+				.take(fabricators)
+			{
+				job.remaining_seconds -= seconds;
+			}
+		}
+
+		/// Moves finished blocks into the `inventory`, while they fit
+		pub(super) fn deliver(&mut self, inventory: &mut Inventory) {
+			self
+				.jobs
+				.retain(|job| job.remaining_seconds > 0. || inventory.try_add_block(job.output).is_err());
+		}
+	}
+
+	/// Sent from clients to fabricate one block
+	#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub struct FabricationRequest {
+		pub block: EditorBlock,
+	}
+
+	/// Why the server refused a [FabricationRequest]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub enum FabricationError {
+		/// The server's [crate::players::recipes::RecipeBook] doesn't have a recipe for the block
+		NoRecipe,
+		/// The player's ship has no fabricators to work on it
+		NoFabricator,
+		/// See [FabricationQueue::MAX_JOBS]
+		QueueFull,
+		/// The player doesn't have all of the recipe's ingredients
+		MissingIngredients,
+	}
+
+	impl std::fmt::Display for FabricationError {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				Self::NoRecipe => write!(f, "That block can't be fabricated"),
+				Self::NoFabricator => write!(f, "The ship has no fabricators"),
+				Self::QueueFull => write!(f, "Too many blocks are already being fabricated"),
+				Self::MissingIngredients => write!(f, "There aren't enough resources to fabricate that"),
+			}
+		}
+	}
+
+	/// Sent to a client whose [FabricationRequest] was refused
+	#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub struct FabricationRejected {
+		pub request: FabricationRequest,
+		pub reason: FabricationError,
+	}
+}
+
+mod bundle {
+	use crate::prelude::*;
+
+	use super::{blueprint::FabricatorBlockBlueprint, Fabricator};
+
+	/// Fabricator that is spawned into the world
+	#[derive(Bundle)]
+	pub struct FabricatorBlockBundle {
+		pbr: PbrBundle,
+		collider: AsyncCollider,
+		integrity: Integrity,
+		name: Name,
+		id: BlockId,
+		fabricator: Fabricator,
+	}
+
+	impl Blueprint for BlockBlueprint<FabricatorBlockBlueprint> {
+		type Bundle = FabricatorBlockBundle;
+		type StampSystemParam = MMA<'static>;
+
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
+			let BlockBlueprint {
+				transform,
+				mesh,
+				material,
+				specific_marker,
+			} = self;
+			Self::Bundle {
+				pbr: PbrBundle {
+					transform: *transform,
+					mesh: mesh.clone().into_mesh(mma),
+					material: material.clone().into_material(&mut mma.mats),
+					..default()
+				},
+				collider: AsyncCollider(ComputedCollider::ConvexHull),
+				integrity: Integrity::new(Fabricator::INTEGRITY),
+				name: Name::new("FabricatorBlock"),
+				id: specific_marker.get_block_id(),
+				fabricator: Fabricator {
+					block_id: specific_marker.get_block_id(),
+				},
+			}
+		}
+	}
+}
+
+mod systems {
+	use crate::{
+		players::{inventory::Inventory, recipes::RecipeBook},
+		prelude::*,
+	};
+
+	use super::{
+		FabricationError, FabricationQueue, FabricationRejected, FabricationRequest, Fabricator,
+		FabricatorPlugin,
+	};
+
+	impl FabricatorPlugin {
+		/// Pays for and queues requested blocks, see [FabricationQueue]
+		pub(super) fn receive_fabrication_requests(
+			mut players: Query<(&NetworkId, &Children, &mut Inventory, &mut FabricationQueue)>,
+			fabricators: Query<(), With<Fabricator>>,
+			recipes: Res<RecipeBook>,
+			mut requests: EventReader<FromClient<FabricationRequest>>,
+			mut rejections: EventWriter<ToClients<FabricationRejected>>,
+		) {
+			for FromClient {
+				client_id,
+				event: request,
+			} in requests.read()
+			{
+				let Some((_, blocks, mut inventory, mut queue)) = players
+					.iter_mut()
+					.find(|(id, _, _, _)| id.get_network_id() == *client_id)
+				else {
+					trace!(
+						"Dropping fabrication request from client {} without a player",
+						client_id
+					);
+					continue;
+				};
+
+				let result = match recipes.get(request.block) {
+					None => Err(FabricationError::NoRecipe),
+					Some(_) if fabricators.iter_many(blocks).next().is_none() => {
+						Err(FabricationError::NoFabricator)
+					}
+					Some(_) if queue.len() >= FabricationQueue::MAX_JOBS => Err(FabricationError::QueueFull),
+					Some(recipe) if !inventory.contains_all(&recipe.ingredients) => {
+						Err(FabricationError::MissingIngredients)
+					}
+					Some(recipe) => inventory
+						.try_remove_all(&recipe.ingredients)
+						.map(|()| queue.push(recipe.output, recipe.seconds))
+						.map_err(|_| FabricationError::MissingIngredients),
+				};
+				if let Err(reason) = result {
+					debug!(
+						"Rejecting {:?} from client {}: {}",
+						request, client_id, reason
+					);
+					rejections.send(ToClients {
+						mode: SendMode::Direct(*client_id),
+						event: FabricationRejected {
+							request: *request,
+							reason,
+						},
+					});
+				}
+			}
+		}
+
+		/// Works on every player's [FabricationQueue], see [FabricationQueue::advance]
+		pub(super) fn fabricate(
+			mut players: Query<(&Children, &mut FabricationQueue, &mut Inventory)>,
+			fabricators: Query<(), With<Fabricator>>,
+			time: Res<Time>,
+		) {
+			for (blocks, mut queue, mut inventory) in players.iter_mut() {
+				if queue.is_empty() {
+					continue;
+				}
+				let working = fabricators.iter_many(blocks).count();
+				queue.advance(time.delta_seconds(), working);
+				queue.deliver(&mut inventory);
+			}
+		}
+
+		pub(super) fn receive_fabrication_rejections(mut rejections: EventReader<FabricationRejected>) {
+			for FabricationRejected { request, reason } in rejections.read() {
+				warn!("Couldn't fabricate a {:?} block: {}", request.block, reason);
+			}
+		}
+	}
+}
+
+mod blueprint {
+	use crate::prelude::*;
+
+	/// Builder for [FabricatorBlockBundle]
+	#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
+	pub struct FabricatorBlockBlueprint {
+		pub(crate) id: BlockId,
+	}
+
+	impl FabricatorBlockBlueprint {
+		pub fn new() -> Self {
+			Self {
+				id: BlockId::random(),
+			}
+		}
+	}
+
+	impl GetBlockId for FabricatorBlockBlueprint {
+		fn get_block_id(&self) -> BlockId {
+			self.id
+		}
+	}
+
+	impl BlockBlueprint<FabricatorBlockBlueprint> {
+		pub fn new_fabricator(location: impl Into<manual_builder::RelativePixel>) -> Self {
+			BlockBlueprint {
+				transform: Transform::from_translation(location.into().into_world_offset()),
+				mesh: OptimizableMesh::StandardBlock,
+				material: OptimizableMaterial::OpaqueColour(Color::TEAL),
+				specific_marker: FabricatorBlockBlueprint::new(),
+			}
+		}
+
+		pub fn location(&self) -> manual_builder::RelativePixel {
+			manual_builder::RelativePixel::from_world_offset(self.transform.translation)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		players::{inventory::Inventory, ship_editor::EditorBlock},
+		prelude::*,
+	};
+
+	use super::FabricationQueue;
+
+	#[test]
+	fn fabricators_work_in_parallel() {
+		let mut queue = FabricationQueue::default();
+		let mut inventory = Inventory::with_capacity(2);
+		queue.push(EditorBlock::Thruster, 1.);
+		queue.push(EditorBlock::Structure, 1.);
+		queue.push(EditorBlock::MiningTool, 1.);
+
+		// no fabricators, no progress
+		queue.advance(1., 0);
+		queue.deliver(&mut inventory);
+		assert_eq!(queue.len(), 3);
+
+		queue.advance(1., 2);
+		queue.deliver(&mut inventory);
+		assert_eq!(queue.len(), 1);
+		assert_eq!(inventory.blocks(EditorBlock::Thruster), 1);
+		assert_eq!(inventory.blocks(EditorBlock::Structure), 1);
+
+		// finished, but waits until there is space for it
+		queue.advance(1., 2);
+		queue.deliver(&mut inventory);
+		assert_eq!(queue.len(), 1);
+		inventory.try_remove_block(EditorBlock::Thruster).unwrap();
+		queue.deliver(&mut inventory);
+		assert!(queue.is_empty());
+		assert_eq!(inventory.blocks(EditorBlock::MiningTool), 1);
+	}
+}
@@ -0,0 +1,354 @@
+//! What each player owns, see [Inventory].
+//!
+//! Inventories are only changed by the server, e.g. by mining, fabricating or building,
+//! and are replicated to clients and saved along with the player's ship.
+//! Players give each other resources with [InventoryTransferRequest]s, see [PlayerInventories::transfer].
+
+use crate::prelude::*;
+
+pub use api::*;
+
+pub(super) struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+	fn build(&self, app: &mut App) {
+		replicate_marked!(app, Inventory);
+
+		app
+			.register_type::<Inventory>()
+			.add_client_event::<InventoryTransferRequest>(EventType::Ordered)
+			.add_server_event::<InventoryTransferRejected>(EventType::Ordered)
+			.add_systems(
+				Update,
+				(
+					Self::receive_transfer_requests
+						.run_if(resource_exists::<RenetServer>().or_else(resource_exists::<OfflineSession>())),
+					Self::receive_transfer_rejections.in_set(Client),
+				),
+			);
+	}
+}
+
+mod api {
+	use crate::{players::ship_editor::EditorBlock, prelude::*};
+
+	/// The resources and fabricated blocks a player owns,
+	/// at most [Inventory::capacity] units of them in total (a block takes up one unit)
+	#[derive(Component, Debug, Clone, PartialEq, Reflect, Serialize, Deserialize)]
+	#[serde(default)]
+	pub struct Inventory {
+		resources: HashMap<ResourceKind, u32>,
+		blocks: HashMap<EditorBlock, u32>,
+		capacity: u32,
+	}
+
+	impl Default for Inventory {
+		fn default() -> Self {
+			Self::with_capacity(Self::DEFAULT_CAPACITY)
+		}
+	}
+
+	impl Inventory {
+		pub const DEFAULT_CAPACITY: u32 = 500;
+
+		pub fn with_capacity(capacity: u32) -> Self {
+			Self {
+				resources: HashMap::default(),
+				blocks: HashMap::default(),
+				capacity,
+			}
+		}
+
+		pub fn amount(&self, kind: ResourceKind) -> u32 {
+			self.resources.get(&kind).copied().unwrap_or_default()
+		}
+
+		/// How many of this block have been fabricated and not placed yet
+		pub fn blocks(&self, block: EditorBlock) -> u32 {
+			self.blocks.get(&block).copied().unwrap_or_default()
+		}
+
+		pub fn total(&self) -> u32 {
+			self.resources.values().chain(self.blocks.values()).sum()
+		}
+
+		pub fn capacity(&self) -> u32 {
+			self.capacity
+		}
+
+		pub fn free_space(&self) -> u32 {
+			self.capacity.saturating_sub(self.total())
+		}
+
+		/// Adds all of `amount`, or nothing if it doesn't fit
+		pub fn try_add(&mut self, kind: ResourceKind, amount: u32) -> Result<(), InventoryError> {
+			if amount > self.free_space() {
+				return Err(InventoryError::Full);
+			}
+			*self.resources.entry(kind).or_default() += amount;
+			Ok(())
+		}
+
+		pub fn contains_all(&self, cost: &[(ResourceKind, u32)]) -> bool {
+			Self::totals(cost)
+				.iter()
+				.all(|(kind, amount)| self.amount(*kind) >= *amount)
+		}
+
+		/// Removes every resource of the `cost`, or nothing if any of them are missing
+		pub fn try_remove_all(&mut self, cost: &[(ResourceKind, u32)]) -> Result<(), InventoryError> {
+			let cost = Self::totals(cost);
+			if let Some((kind, _)) = cost
+				.iter()
+				.find(|(kind, amount)| self.amount(**kind) < **amount)
+			{
+				return Err(InventoryError::Missing(*kind));
+			}
+			for (kind, amount) in cost {
+				if let Some(owned) = self.resources.get_mut(&kind) {
+					*owned -= amount;
+				}
+			}
+			self.resources.retain(|_, amount| *amount > 0);
+			Ok(())
+		}
+
+		/// How much of each kind the `cost` adds up to, since a kind may be listed more than once
+		fn totals(cost: &[(ResourceKind, u32)]) -> HashMap<ResourceKind, u32> {
+			let mut totals = HashMap::new();
+			for (kind, amount) in cost {
+				let total: &mut u32 = totals.entry(*kind).or_default();
+				*total = total.saturating_add(*amount);
+			}
+			totals
+		}
+
+		pub fn try_add_block(&mut self, block: EditorBlock) -> Result<(), InventoryError> {
+			if self.free_space() == 0 {
+				return Err(InventoryError::Full);
+			}
+			*self.blocks.entry(block).or_default() += 1;
+			Ok(())
+		}
+
+		pub fn try_remove_block(&mut self, block: EditorBlock) -> Result<(), InventoryError> {
+			match self.blocks.get_mut(&block) {
+				Some(owned) if *owned > 1 => *owned -= 1,
+				Some(_) => {
+					self.blocks.remove(&block);
+				}
+				None => return Err(InventoryError::MissingBlock(block)),
+			}
+			Ok(())
+		}
+
+		/// Moves `amount` of `kind` into `other`, or nothing if this doesn't have it or it doesn't fit
+		pub fn transfer_to(
+			&mut self,
+			other: &mut Inventory,
+			kind: ResourceKind,
+			amount: u32,
+		) -> Result<(), InventoryError> {
+			if self.amount(kind) < amount {
+				return Err(InventoryError::Missing(kind));
+			}
+			other.try_add(kind, amount)?;
+			self.try_remove_all(&[(kind, amount)])
+		}
+	}
+
+	/// Why resources couldn't be moved into or out of an [Inventory]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub enum InventoryError {
+		/// There isn't enough free space, see [Inventory::capacity]
+		Full,
+		/// There isn't enough of this resource
+		Missing(ResourceKind),
+		/// None of this block have been fabricated
+		MissingBlock(EditorBlock),
+		/// The other player isn't in the game
+		NoSuchPlayer,
+		/// Players can't transfer to themselves
+		SamePlayer,
+	}
+
+	impl std::fmt::Display for InventoryError {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				Self::Full => write!(f, "The inventory is full"),
+				Self::Missing(kind) => write!(f, "There isn't enough {}", kind.name()),
+				Self::MissingBlock(block) => write!(f, "There is no {:?} block", block),
+				Self::NoSuchPlayer => write!(f, "That player isn't in the game"),
+				Self::SamePlayer => write!(f, "Players can't give resources to themselves"),
+			}
+		}
+	}
+
+	/// Sent from clients to give some of their resources to another player
+	#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub struct InventoryTransferRequest {
+		pub to: NetworkId,
+		pub kind: ResourceKind,
+		pub amount: u32,
+	}
+
+	/// Sent to a client whose [InventoryTransferRequest] was refused
+	#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub struct InventoryTransferRejected {
+		pub request: InventoryTransferRequest,
+		pub reason: InventoryError,
+	}
+
+	/// Server side access to every player's [Inventory]
+	#[derive(SystemParam)]
+	pub struct PlayerInventories<'w, 's> {
+		players: Query<'w, 's, (Entity, &'static NetworkId, &'static mut Inventory)>,
+	}
+
+	impl PlayerInventories<'_, '_> {
+		fn find(&self, id: ClientId) -> Result<Entity, InventoryError> {
+			self
+				.players
+				.iter()
+				.find(|(_, player, _)| player.get_network_id() == id)
+				.map(|(entity, _, _)| entity)
+				.ok_or(InventoryError::NoSuchPlayer)
+		}
+
+		pub fn get(&self, id: ClientId) -> Option<&Inventory> {
+			let entity = self.find(id).ok()?;
+			self
+				.players
+				.get(entity)
+				.ok()
+				.map(|(_, _, inventory)| inventory)
+		}
+
+		/// Moves `amount` of `kind` from one player to another, see [Inventory::transfer_to]
+		pub fn transfer(
+			&mut self,
+			from: ClientId,
+			to: ClientId,
+			kind: ResourceKind,
+			amount: u32,
+		) -> Result<(), InventoryError> {
+			if from == to {
+				return Err(InventoryError::SamePlayer);
+			}
+			let players = [self.find(from)?, self.find(to)?];
+			let [(_, _, mut from), (_, _, mut to)] = self
+				.players
+				.get_many_mut(players)
+				.map_err(|_| InventoryError::NoSuchPlayer)?;
+			from.transfer_to(&mut to, kind, amount)
+		}
+	}
+}
+
+mod systems {
+	use crate::prelude::*;
+
+	use super::{
+		InventoryPlugin, InventoryTransferRejected, InventoryTransferRequest, PlayerInventories,
+	};
+
+	impl InventoryPlugin {
+		pub(super) fn receive_transfer_requests(
+			mut inventories: PlayerInventories,
+			mut requests: EventReader<FromClient<InventoryTransferRequest>>,
+			mut rejections: EventWriter<ToClients<InventoryTransferRejected>>,
+		) {
+			for FromClient {
+				client_id,
+				event: request,
+			} in requests.read()
+			{
+				if let Err(reason) = inventories.transfer(
+					*client_id,
+					request.to.get_network_id(),
+					request.kind,
+					request.amount,
+				) {
+					debug!(
+						"Rejecting {:?} from client {}: {}",
+						request, client_id, reason
+					);
+					rejections.send(ToClients {
+						mode: SendMode::Direct(*client_id),
+						event: InventoryTransferRejected {
+							request: *request,
+							reason,
+						},
+					});
+				}
+			}
+		}
+
+		pub(super) fn receive_transfer_rejections(
+			mut rejections: EventReader<InventoryTransferRejected>,
+		) {
+			for InventoryTransferRejected { request, reason } in rejections.read() {
+				warn!("Couldn't transfer resources ({:?}): {}", request, reason);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{players::ship_editor::EditorBlock, prelude::*};
+
+	use super::{Inventory, InventoryError};
+
+	#[test]
+	fn respects_capacity() {
+		let mut inventory = Inventory::with_capacity(10);
+		assert_eq!(inventory.try_add(ResourceKind::Iron, 8), Ok(()));
+		assert_eq!(
+			inventory.try_add(ResourceKind::Silicates, 3),
+			Err(InventoryError::Full)
+		);
+		assert_eq!(inventory.try_add(ResourceKind::Silicates, 2), Ok(()));
+		assert_eq!(inventory.free_space(), 0);
+
+		assert_eq!(
+			inventory.try_remove_all(&[(ResourceKind::Iron, 2), (ResourceKind::Regolith, 1)]),
+			Err(InventoryError::Missing(ResourceKind::Regolith))
+		);
+		assert_eq!(inventory.amount(ResourceKind::Iron), 8);
+		assert_eq!(inventory.try_remove_all(&[(ResourceKind::Iron, 2)]), Ok(()));
+		assert_eq!(inventory.total(), 8);
+		// a kind listed twice costs both amounts
+		assert_eq!(
+			inventory.try_remove_all(&[(ResourceKind::Iron, 4), (ResourceKind::Iron, 4)]),
+			Err(InventoryError::Missing(ResourceKind::Iron))
+		);
+		assert!(!inventory.contains_all(&[(ResourceKind::Iron, 4), (ResourceKind::Iron, 4)]));
+		assert_eq!(inventory.amount(ResourceKind::Iron), 6);
+
+		// fabricated blocks take up space too
+		assert_eq!(inventory.try_add_block(EditorBlock::Thruster), Ok(()));
+		assert_eq!(inventory.try_add_block(EditorBlock::Thruster), Ok(()));
+		assert_eq!(
+			inventory.try_add_block(EditorBlock::Structure),
+			Err(InventoryError::Full)
+		);
+		assert_eq!(inventory.try_remove_block(EditorBlock::Thruster), Ok(()));
+		assert_eq!(inventory.blocks(EditorBlock::Thruster), 1);
+	}
+
+	#[test]
+	fn transfers_all_or_nothing() {
+		let mut from = Inventory::default();
+		from.try_add(ResourceKind::Iron, 5).unwrap();
+		let mut to = Inventory::with_capacity(3);
+
+		assert_eq!(
+			from.transfer_to(&mut to, ResourceKind::Iron, 4),
+			Err(InventoryError::Full)
+		);
+		assert_eq!(from.transfer_to(&mut to, ResourceKind::Iron, 3), Ok(()));
+		assert_eq!(from.amount(ResourceKind::Iron), 2);
+		assert_eq!(to.amount(ResourceKind::Iron), 3);
+	}
+}
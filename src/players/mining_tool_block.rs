@@ -0,0 +1,230 @@
+//! Mining tools break the terrain in front of them while
+//! [PlayerInput::Mine](crate::players::player_movement::PlayerInput::Mine) is held.
+//!
+//! Mining is server authoritative: the server casts a ray out of each tool,
+//! removes the hit item from its [TerrainStructureBlueprint] and adds what it yields
+//! to the player's [Inventory](crate::players::inventory::Inventory).
+//! The changed blueprint is then replicated and re-expanded everywhere.
+
+use crate::prelude::*;
+
+pub use api::*;
+pub use blueprint::MiningToolBlockBlueprint;
+
+pub struct MiningToolPlugin;
+
+impl Plugin for MiningToolPlugin {
+	fn build(&self, app: &mut App) {
+		app.register_type::<MiningTool>().add_systems(
+			FixedUpdate,
+			Self::mine_terrain
+				.in_set(GlobalSystemSet::ExecuteGameLogic)
+				.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
+		);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	/// Component for all mining tools (on a player)
+	#[derive(Debug, Component, Reflect)]
+	pub struct MiningTool {
+		pub(super) block_id: BlockId,
+		/// How far in front of the tool terrain can be mined
+		pub(super) range: f32,
+		/// Ticks while mining, an item is mined every time it finishes
+		pub(super) cooldown: Timer,
+	}
+
+	impl MiningTool {
+		pub const SECONDS_PER_ITEM: f32 = 0.25;
+		pub const INTEGRITY: f32 = 80.;
+	}
+
+	impl GetBlockId for MiningTool {
+		fn get_block_id(&self) -> BlockId {
+			self.block_id
+		}
+	}
+}
+
+mod bundle {
+	use crate::prelude::*;
+
+	use super::{blueprint::MiningToolBlockBlueprint, MiningTool};
+
+	/// Mining tool that is spawned into the world
+	#[derive(Bundle)]
+	pub struct MiningToolBlockBundle {
+		pbr: PbrBundle,
+		collider: AsyncCollider,
+		integrity: Integrity,
+		name: Name,
+		id: BlockId,
+		tool: MiningTool,
+	}
+
+	impl Blueprint for BlockBlueprint<MiningToolBlockBlueprint> {
+		type Bundle = MiningToolBlockBundle;
+		type StampSystemParam = MMA<'static>;
+
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
+			let BlockBlueprint {
+				transform,
+				mesh,
+				material,
+				specific_marker,
+			} = self;
+			Self::Bundle {
+				pbr: PbrBundle {
+					transform: *transform,
+					mesh: mesh.clone().into_mesh(mma),
+					material: material.clone().into_material(&mut mma.mats),
+					..default()
+				},
+				collider: AsyncCollider(ComputedCollider::ConvexHull),
+				integrity: Integrity::new(MiningTool::INTEGRITY),
+				name: Name::new("MiningToolBlock"),
+				id: specific_marker.get_block_id(),
+				tool: specific_marker.clone().into(),
+			}
+		}
+	}
+}
+
+mod systems {
+	use crate::{
+		blocks::manual_builder::RelativePixel,
+		players::{inventory::Inventory, player_movement::PlayerInput},
+		prelude::*,
+	};
+
+	use super::{MiningTool, MiningToolPlugin};
+
+	impl MiningToolPlugin {
+		/// Mines the terrain in front of every tool of the players holding [PlayerInput::Mine]
+		pub(super) fn mine_terrain(
+			mut commands: Commands,
+			mut players: Query<(&ActionState<PlayerInput>, &Children, &mut Inventory)>,
+			mut tools: Query<(&mut MiningTool, &GlobalTransform)>,
+			items: Query<(&Parent, &Transform), With<TerrainItem>>,
+			mut structures: Query<(
+				&mut TerrainStructureBlueprint,
+				&Transform,
+				&LinearVelocity,
+				&AngularVelocity,
+				&CenterOfMass,
+			)>,
+			spatial_query: SpatialQuery,
+			time: Res<Time>,
+		) {
+			for (inputs, blocks, mut inventory) in players.iter_mut() {
+				if !inputs.pressed(PlayerInput::Mine) {
+					continue;
+				}
+				let not_own_blocks = SpatialQueryFilter::new().without_entities(blocks.iter().copied());
+
+				let mut player_tools = tools.iter_many_mut(blocks.iter());
+				while let Some((mut tool, tool_transform)) = player_tools.fetch_next() {
+					if !tool.cooldown.tick(time.delta()).just_finished() {
+						continue;
+					}
+					let forward = tool_transform.forward();
+					let Some(hit) = spatial_query.cast_ray(
+						tool_transform.translation() + forward * PIXEL_SIZE / 2.,
+						forward,
+						tool.range,
+						true,
+						not_own_blocks.clone(),
+					) else {
+						continue;
+					};
+					let Ok((structure, item)) = items.get(hit.entity) else {
+						continue;
+					};
+					let Ok((mut blueprint, transform, linvel, angvel, centre_of_mass)) =
+						structures.get_mut(structure.get())
+					else {
+						continue;
+					};
+
+					// re-expanding moves the structure back to its blueprint's transform
+					let mut mined = blueprint.drifted(*transform, *linvel, *angvel);
+					let Some(terrain_type) = mined.mine(RelativePixel::from_world_offset(item.translation))
+					else {
+						continue;
+					};
+					let (kind, amount) = terrain_type.yields();
+					// leaves the terrain alone if the player can't carry what it yields
+					if inventory.try_add(kind, amount).is_err() {
+						continue;
+					}
+					trace!("Mined {} {}", amount, kind.name());
+
+					blueprint.break_apart(mined, centre_of_mass, structure.get(), &mut commands);
+				}
+			}
+		}
+	}
+}
+
+mod blueprint {
+	use crate::prelude::*;
+
+	use super::MiningTool;
+
+	/// Builder for [MiningToolBlockBundle]
+	#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
+	pub struct MiningToolBlockBlueprint {
+		pub(crate) id: BlockId,
+		range: f32,
+	}
+
+	impl MiningToolBlockBlueprint {
+		pub fn new() -> Self {
+			Self {
+				id: BlockId::random(),
+				range: 8.,
+			}
+		}
+	}
+
+	impl GetBlockId for MiningToolBlockBlueprint {
+		fn get_block_id(&self) -> BlockId {
+			self.id
+		}
+	}
+
+	impl From<MiningToolBlockBlueprint> for MiningTool {
+		fn from(MiningToolBlockBlueprint { id, range }: MiningToolBlockBlueprint) -> Self {
+			MiningTool {
+				block_id: id,
+				range,
+				cooldown: Timer::from_seconds(MiningTool::SECONDS_PER_ITEM, TimerMode::Repeating),
+			}
+		}
+	}
+
+	impl BlockBlueprint<MiningToolBlockBlueprint> {
+		/// The tool mines in the direction it is `facing`
+		pub fn new_mining_tool(
+			location: impl Into<manual_builder::RelativePixel>,
+			facing: impl Into<Quat>,
+		) -> Self {
+			BlockBlueprint {
+				transform: Transform::from_translation(location.into().into_world_offset())
+					.with_rotation(facing.into()),
+				mesh: OptimizableMesh::CustomRectangularPrism {
+					size: Vec3::new(PIXEL_SIZE / 2., PIXEL_SIZE / 2., PIXEL_SIZE),
+				},
+				material: OptimizableMaterial::OpaqueColour(Color::YELLOW),
+				specific_marker: MiningToolBlockBlueprint::new(),
+			}
+		}
+
+		pub fn location(&self) -> manual_builder::RelativePixel {
+			manual_builder::RelativePixel::from_world_offset(self.transform.translation)
+		}
+	}
+}
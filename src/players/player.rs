@@ -7,31 +7,54 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
 	fn build(&self, app: &mut App) {
 		app.depends_on::<RepliconCorePlugin, _>(ReplicationPlugins);
-		app.depends_on::<crate::cameras::CameraPlugin, _>(crate::cameras::CameraPlugin);
 
 		replicate_marked!(app, player_blueprint::PlayerBlueprintComponent);
 
 		app
 			.register_type::<player_blueprint::PlayerBlueprintComponent>()
 			.register_type::<components::ControllablePlayer>()
-			.add_systems(
-				Blueprints,
-				Self::handle_spawn_player_blueprints.in_set(BlueprintExpansion::Player),
-			)
+			.register_type::<components::ParkedPlayer>()
+			.register_blueprint::<player_blueprint::PlayerBlueprintComponent>()
+			.persist::<player_blueprint::PlayerBlueprintBundle>()
 			.add_systems(
 				GameLogic,
 				(
-					Self::manage_primary_camera.run_if(NetcodeConfig::not_headless()),
 					Self::name_player,
-					Self::handle_player_join,
+					Self::handle_player_join.run_if(resource_exists::<PlayerSlots>()),
 				),
+			)
+			.add_systems(
+				Update,
+				(Self::park_leaving_players, Self::despawn_expired_players)
+					.run_if(resource_exists::<PlayerSlots>()),
+			)
+			.add_systems(
+				FixedUpdate,
+				Self::destroy_player_blocks
+					.in_set(GlobalSystemSet::ExecuteGameLogic)
+					.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
 			);
+
+		// the dedicated server has no cameras to manage
+		if app.is_plugin_added::<crate::cameras::CameraPlugin>() {
+			app.add_systems(
+				GameLogic,
+				Self::manage_primary_camera.run_if(NetcodeConfig::not_headless()),
+			);
+		}
 	}
 }
 
 mod api {
-	pub use super::components::ControllablePlayer;
+	pub use super::components::{ControllablePlayer, ParkedPlayer};
 	pub use super::player_blueprint::{PlayerBlueprintBundle, PlayerBlueprintComponent};
+
+	use crate::prelude::*;
+
+	/// If present on the server, joining players are spawned with this ship
+	/// instead of the default one, e.g. loaded with [crate::players::load_ship]
+	#[derive(Resource, Debug, Clone)]
+	pub struct StarterShip(pub PlayerBlueprintComponent);
 }
 
 mod systems {
@@ -42,46 +65,11 @@ mod systems {
 	};
 
 	use super::{
-		player_blueprint::PlayerBlueprintComponent, ControllablePlayer, PlayerBlueprintBundle,
-		PlayerPlugin,
+		ControllablePlayer, ParkedPlayer, PlayerBlueprintBundle, PlayerBlueprintComponent,
+		PlayerPlugin, StarterShip,
 	};
 
 	impl PlayerPlugin {
-		pub(super) fn handle_spawn_player_blueprints(
-			player_blueprints: Query<
-				(Entity, &PlayerBlueprintComponent, &NetworkId),
-				Changed<PlayerBlueprintComponent>,
-			>,
-			mut commands: Commands,
-			mut mma: MMA,
-		) {
-			for (player, player_blueprint, id) in player_blueprints.iter() {
-				debug!("Expanding player blueprint for {:?}", id);
-				commands
-					.entity(player)
-					.despawn_descendants()
-					.insert(FreshlyExpanded)
-					.insert(player_blueprint.stamp())
-					.with_children(|parent| {
-						for blueprint in &player_blueprint.structure_children {
-							parent
-								.spawn(blueprint.stamp(&mut mma))
-								.insert(FreshlyExpanded);
-						}
-
-						for blueprint in &player_blueprint.thruster_children {
-							parent
-								.spawn(blueprint.stamp(&mut mma))
-								.insert(FreshlyExpanded);
-						}
-
-						parent
-							.spawn(player_blueprint.primary_camera.stamp(&mut mma))
-							.insert(FreshlyExpanded);
-					});
-			}
-		}
-
 		/// When new [CameraBlockMarker]s are spawned,
 		/// check if they are the child of the local player.
 		/// If so, set the primary camera to it.
@@ -104,20 +92,130 @@ mod systems {
 			}
 		}
 
-		/// Spawns [PlayerBlueprintBundle]s when [PlayerJoin] events are received
+		/// Spawns [PlayerBlueprintBundle]s when [PlayerJoin] events are received,
+		/// or rejects the player if there are no [PlayerSlots] or spawn points left.
 		pub(super) fn handle_player_join(
 			mut commands: Commands,
 			mut spawn_point: AvailableSpawnPoints,
+			mut slots: ResMut<PlayerSlots>,
+			existing_players: Query<(Entity, &NetworkId), With<PlayerBlueprintComponent>>,
+			starter_ship: Option<Res<StarterShip>>,
 			mut player_joins: EventReader<PlayerJoin>,
 		) {
-			for id in player_joins.read() {
-				trace!("Received {:?}", id);
+			for join in player_joins.read() {
+				trace!("Received {:?}", join);
+				let id = join.0;
 
-				let transform = spawn_point
-					.try_get_spawn_location(id.0)
-					.expect("No more spawn points left!");
+				if let Err(reason) = slots.try_claim(id) {
+					slots.reject(id, reason);
+					continue;
+				}
 
-				commands.spawn(PlayerBlueprintBundle::new(id.0, transform));
+				if let Some((player, _)) = existing_players
+					.iter()
+					.find(|(_, player)| player.get_network_id() == id)
+				{
+					info!("Player {} reconnected into their previous slot", id);
+					commands
+						.entity(player)
+						.remove::<ParkedPlayer>()
+						.insert(RigidBody::Dynamic);
+					continue;
+				}
+
+				match spawn_point.try_get_spawn_location(id) {
+					Some(transform) => {
+						let player = match &starter_ship {
+							Some(ship) => {
+								PlayerBlueprintBundle::with_ship(id, transform, ship.0.clone().with_new_block_ids())
+							}
+							None => PlayerBlueprintBundle::new(id, transform),
+						};
+						commands.spawn(player);
+					}
+					None => {
+						slots.free(id);
+						slots.reject(id, JoinRejection::NoSpawnPoints);
+					}
+				}
+			}
+		}
+
+		/// Parks the ships of players that left, keeping them (and their spawn point)
+		/// in place in case they reconnect within [PlayerSlots::RECONNECT_GRACE].
+		pub(super) fn park_leaving_players(
+			mut commands: Commands,
+			players: Query<(Entity, &NetworkId), With<PlayerBlueprintComponent>>,
+			mut player_leaves: EventReader<PlayerLeave>,
+		) {
+			for PlayerLeave(id) in player_leaves.read() {
+				for (player, _) in players
+					.iter()
+					.filter(|(_, player)| player.get_network_id() == *id)
+				{
+					debug!("Parking player {}", id);
+					commands
+						.entity(player)
+						.insert((ParkedPlayer, RigidBody::Static));
+				}
+			}
+		}
+
+		/// Removes destroyed blocks from their ship's blueprint, see [Integrity].
+		/// Blocks that were only held on by them break off as wreckage
+		pub(super) fn destroy_player_blocks(
+			mut commands: Commands,
+			blocks: Query<(&Integrity, &BlockId, &Parent), Changed<Integrity>>,
+			mut players: Query<(
+				&mut PlayerBlueprintComponent,
+				&Transform,
+				&LinearVelocity,
+				&AngularVelocity,
+				&CenterOfMass,
+			)>,
+		) {
+			for (integrity, id, player) in blocks.iter() {
+				if !integrity.is_destroyed() {
+					continue;
+				}
+				let Ok((mut blueprint, transform, linvel, angvel, centre_of_mass)) =
+					players.get_mut(player.get())
+				else {
+					continue;
+				};
+				if !blueprint.destroy_block(*id) {
+					continue;
+				}
+				let detached = blueprint.detach_disconnected();
+				if detached.is_empty() {
+					continue;
+				}
+				debug!("{} blocks broke off a ship", detached.len());
+				let wreckage = TerrainStructureBlueprint::wreckage(detached, *transform, *linvel, *angvel);
+				for part in wreckage.split(centre_of_mass.0) {
+					let chunk = ChunkCoord::of(part.transform.translation);
+					commands.spawn((part, InChunk(chunk), Replication));
+				}
+			}
+		}
+
+		/// Despawns the ships of players that didn't reconnect in time,
+		/// and frees their spawn points.
+		pub(super) fn despawn_expired_players(
+			mut commands: Commands,
+			players: Query<(Entity, &NetworkId), With<PlayerBlueprintComponent>>,
+			mut spawn_points: AvailableSpawnPoints,
+			mut expired: EventReader<PlayerSlotExpired>,
+		) {
+			for PlayerSlotExpired(id) in expired.read() {
+				for (player, _) in players
+					.iter()
+					.filter(|(_, player)| player.get_network_id() == *id)
+				{
+					debug!("Despawning player {}", id);
+					commands.entity(player).despawn_recursive();
+				}
+				spawn_points.release_spawn_location(*id);
 			}
 		}
 
@@ -140,19 +238,44 @@ mod components {
 	/// The marker component for player entities.
 	#[derive(Component, Reflect, Debug)]
 	pub struct ControllablePlayer;
+
+	/// Marks players whose client left, and whose ship is frozen in place
+	/// until they reconnect or their slot expires, see [PlayerSlots].
+	#[derive(Component, Reflect, Debug)]
+	pub struct ParkedPlayer;
 }
 
 mod player_blueprint {
 	use crate::{
-		blocks::manual_builder::Facing, cameras::CameraBlockBlueprint,
-		players::thruster_block::ThrusterBlockBlueprint, prelude::*,
+		blocks::manual_builder::{Facing, RelativePixel},
+		cameras::CameraBlockBlueprint,
+		players::{
+			fabricator_block::{FabricationQueue, FabricatorBlockBlueprint},
+			inventory::Inventory,
+			mining_tool_block::MiningToolBlockBlueprint,
+			ship_editor::{EditorBlock, ShipEdit, ShipEditRejection},
+			thruster_block::{ThrusterBlockBlueprint, ThrusterTier},
+			weapon_block::WeaponBlockBlueprint,
+		},
+		prelude::*,
 	};
 
-	/// What is used to construct a [PlayerBundle]
-	#[derive(Component, Reflect, Serialize, Deserialize, Debug)]
+	/// What is used to construct a [PlayerBundle].
+	///
+	/// Players can change their own blueprint in build mode, see [crate::players::ship_editor]
+	#[derive(Component, Reflect, Serialize, Deserialize, Debug, Clone, PartialEq)]
 	pub struct PlayerBlueprintComponent {
 		pub(super) structure_children: Vec<BlockBlueprint<StructureBlockBlueprint>>,
 		pub(super) thruster_children: Vec<BlockBlueprint<ThrusterBlockBlueprint>>,
+		/// Defaulted, since older ship files don't have any
+		#[serde(default)]
+		pub(super) mining_tool_children: Vec<BlockBlueprint<MiningToolBlockBlueprint>>,
+		/// Defaulted, since older ship files don't have any
+		#[serde(default)]
+		pub(super) fabricator_children: Vec<BlockBlueprint<FabricatorBlockBlueprint>>,
+		/// Defaulted, since older ship files don't have any
+		#[serde(default)]
+		pub(super) weapon_children: Vec<BlockBlueprint<WeaponBlockBlueprint>>,
 		pub(super) primary_camera: BlockBlueprint<CameraBlockBlueprint>,
 	}
 
@@ -167,62 +290,427 @@ mod player_blueprint {
 
 		/// Synced
 		pub(super) network_id: NetworkId,
+
+		/// Synced, defaulted since older saves don't have one
+		#[serde(default)]
+		pub(super) inventory: Inventory,
+
+		/// Server only, defaulted since older saves don't have one
+		#[serde(default)]
+		pub(super) fabrication_queue: FabricationQueue,
 	}
 
 	impl PlayerBlueprintBundle {
-		pub fn new(network_id: ClientId, transform: Transform) -> Self {
+		pub fn with_ship(
+			network_id: ClientId,
+			transform: Transform,
+			blueprint: PlayerBlueprintComponent,
+		) -> Self {
 			PlayerBlueprintBundle {
 				transform,
 				network_id: NetworkId::from_raw(network_id.raw()),
-				blueprint: PlayerBlueprintComponent {
-					structure_children: vec![
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::ZERO), // center
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(0, 0, -1)), // front
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(0, 0, 1)),
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(0, 0, 2)),
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(1, 0, 2)),
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(-1, 0, 2)),
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(1, 0, 3)),
-						BlockBlueprint::new_structure(StructureBlockBlueprint::Aluminum, IVec3::new(-1, 0, 3)),
-					],
-					thruster_children: vec![
-						BlockBlueprint::new_thruster(IVec3::new(-1, 0, 0), Facing::Left),
-						BlockBlueprint::new_thruster(IVec3::new(1, 0, 0), Facing::Right),
-						BlockBlueprint::new_thruster(IVec3::new(-1, 0, 1), Facing::Left),
-						BlockBlueprint::new_thruster(IVec3::new(1, 0, 1), Facing::Right),
-						BlockBlueprint::new_thruster(IVec3::new(-2, 0, 2), Facing::Left),
-						BlockBlueprint::new_thruster(IVec3::new(2, 0, 2), Facing::Right),
-						BlockBlueprint::new_thruster(IVec3::new(-2, 0, 3), Facing::Left),
-						BlockBlueprint::new_thruster(IVec3::new(2, 0, 3), Facing::Right),
-						// BlockBlueprint::new_thruster(IVec3::new(1, 1, 3), Facing::Up),
-						// BlockBlueprint::new_thruster(IVec3::new(1, -1, 3), Facing::Down),
-						// BlockBlueprint::new_thruster(IVec3::new(-1, 1, 3), Facing::Up),
-						// BlockBlueprint::new_thruster(IVec3::new(-1, -1, 3), Facing::Down),
-					],
-					primary_camera: BlockBlueprint::new_camera(IVec3::new(0, 1, 0), Facing::Forwards),
-				},
+				blueprint,
+				inventory: Inventory::default(),
+				fabrication_queue: FabricationQueue::default(),
 			}
 		}
+
+		/// Spawns with the default ship
+		pub fn new(network_id: ClientId, transform: Transform) -> Self {
+			Self::with_ship(
+				network_id,
+				transform,
+				PlayerBlueprintComponent::default_ship(),
+			)
+		}
 	}
 	impl PlayerBlueprintComponent {
+		/// What new players start with, and what enemy ships are built from
+		pub fn default_ship() -> Self {
+			Self {
+				structure_children: vec![
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::ZERO), // center
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(0, 0, -1)), // front
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(0, 0, 1)),
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(0, 0, 2)),
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(1, 0, 2)),
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(-1, 0, 2)),
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(1, 0, 3)),
+					BlockBlueprint::new_structure(StructureMaterial::Aluminum, IVec3::new(-1, 0, 3)),
+				],
+				thruster_children: vec![
+					BlockBlueprint::new_thruster(IVec3::new(-1, 0, 0), Facing::Left),
+					BlockBlueprint::new_thruster(IVec3::new(1, 0, 0), Facing::Right),
+					BlockBlueprint::new_thruster(IVec3::new(-1, 0, 1), Facing::Left),
+					BlockBlueprint::new_thruster(IVec3::new(1, 0, 1), Facing::Right),
+					BlockBlueprint::new_thruster(IVec3::new(-2, 0, 2), Facing::Left),
+					BlockBlueprint::new_thruster(IVec3::new(2, 0, 2), Facing::Right),
+					BlockBlueprint::new_thruster(IVec3::new(-2, 0, 3), Facing::Left),
+					BlockBlueprint::new_thruster(IVec3::new(2, 0, 3), Facing::Right),
+					// main engine
+					BlockBlueprint::new_thruster(IVec3::new(0, 0, 3), Facing::Backwards),
+					// up/down thrusters, spread out to also pitch and roll
+					BlockBlueprint::new_thruster(IVec3::new(1, 1, 3), Facing::Up),
+					BlockBlueprint::new_thruster(IVec3::new(1, -1, 3), Facing::Down),
+					BlockBlueprint::new_thruster(IVec3::new(-1, 1, 3), Facing::Up),
+					BlockBlueprint::new_thruster(IVec3::new(-1, -1, 3), Facing::Down),
+					BlockBlueprint::new_thruster(IVec3::new(0, 1, -1), Facing::Up),
+					BlockBlueprint::new_thruster(IVec3::new(0, -1, -1), Facing::Down),
+				],
+				mining_tool_children: vec![BlockBlueprint::new_mining_tool(
+					IVec3::new(1, 0, -1),
+					Facing::Forwards,
+				)],
+				fabricator_children: vec![BlockBlueprint::new_fabricator(IVec3::new(0, -1, 1))],
+				weapon_children: vec![BlockBlueprint::new_weapon(
+					IVec3::new(-1, 0, -1),
+					Facing::Forwards,
+				)],
+				primary_camera: BlockBlueprint::new_camera(IVec3::new(0, 1, 0), Facing::Forwards),
+			}
+		}
+
 		pub fn derive_thruster_ids(&self) -> impl Iterator<Item = BlockId> + '_ {
 			self.thruster_children.iter().map(|b| b.get_block_id())
 		}
 	}
 
 	impl PlayerBlueprintComponent {
-		// todo: impl spawn point semantics
-		pub fn stamp(&self) -> <PlayerBlueprintComponent as Blueprint>::Bundle {
-			Blueprint::stamp(self, &mut ())
+		/// How many pixels away from the center of the ship blocks can be placed, in any direction
+		pub const MAX_EXTENT: i32 = 16;
+		pub const MAX_BLOCKS: usize = 256;
+
+		/// Every pixel taken up by a block of this ship
+		pub fn occupied(&self) -> impl Iterator<Item = RelativePixel> + '_ {
+			self
+				.structure_children
+				.iter()
+				.map(|block| block.location())
+				.chain(self.thruster_children.iter().map(|block| block.location()))
+				.chain(
+					self
+						.mining_tool_children
+						.iter()
+						.map(|block| block.location()),
+				)
+				.chain(
+					self
+						.fabricator_children
+						.iter()
+						.map(|block| block.location()),
+				)
+				.chain(self.weapon_children.iter().map(|block| block.location()))
+				.chain(std::iter::once(self.primary_camera.location()))
+		}
+
+		/// Which kind of block is at `location`, if any
+		pub fn block_at(&self, location: RelativePixel) -> Option<EditorBlock> {
+			if self.primary_camera.location() == location {
+				return Some(EditorBlock::Camera);
+			}
+			if let Some(block) = self
+				.structure_children
+				.iter()
+				.find(|block| block.location() == location)
+			{
+				return Some(block.material.into());
+			}
+			if let Some(block) = self
+				.thruster_children
+				.iter()
+				.find(|block| block.location() == location)
+			{
+				return Some(block.tier.into());
+			}
+			if self
+				.mining_tool_children
+				.iter()
+				.any(|block| block.location() == location)
+			{
+				return Some(EditorBlock::MiningTool);
+			}
+			if self
+				.fabricator_children
+				.iter()
+				.any(|block| block.location() == location)
+			{
+				return Some(EditorBlock::Fabricator);
+			}
+			if self
+				.weapon_children
+				.iter()
+				.any(|block| block.location() == location)
+			{
+				return Some(EditorBlock::Weapon);
+			}
+			None
+		}
+
+		/// Checks a whole ship against the same rules as [Self::try_edit],
+		/// e.g. after loading it from a file
+		pub fn validate(&self) -> Result<(), ShipEditRejection> {
+			if self.structure_children.is_empty() {
+				return Err(ShipEditRejection::RemovesLastStructure);
+			}
+			let mut occupied = HashSet::new();
+			for pixel in self.occupied() {
+				if pixel.0.abs().max_element() > Self::MAX_EXTENT {
+					return Err(ShipEditRejection::OutOfBounds);
+				}
+				if !occupied.insert(pixel) {
+					return Err(ShipEditRejection::Occupied);
+				}
+			}
+			if occupied.len() > Self::MAX_BLOCKS {
+				return Err(ShipEditRejection::TooManyBlocks);
+			}
+
+			// every block has to be reachable from the camera
+			if self.connected_to_camera().len() != occupied.len() {
+				return Err(ShipEditRejection::Disconnected);
+			}
+			Ok(())
+		}
+
+		/// Every block of the ship
+		pub fn connectivity(&self) -> ConnectivityGraph {
+			ConnectivityGraph::default()
+				.with_blocks(&self.structure_children)
+				.with_blocks(&self.thruster_children)
+				.with_blocks(&self.mining_tool_children)
+				.with_blocks(&self.fabricator_children)
+				.with_blocks(&self.weapon_children)
+				.with_blocks([&self.primary_camera])
+		}
+
+		/// The blocks that can be reached from the camera through other blocks
+		fn connected_to_camera(&self) -> ConnectivityGraph {
+			self
+				.connectivity()
+				.reachable_from(self.primary_camera.location())
+		}
+
+		/// Can no longer fight or fly, once all of its weapons or thrusters were destroyed
+		pub fn is_disabled(&self) -> bool {
+			self.weapon_children.is_empty() || self.thruster_children.is_empty()
+		}
+
+		/// Removes a block that was destroyed, see [Integrity].
+		/// Unlike [Self::try_edit] this can remove the last structure, but never the camera.
+		/// Returns whether the ship had the block
+		pub fn destroy_block(&mut self, id: BlockId) -> bool {
+			let before = self.occupied().count();
+			self
+				.structure_children
+				.retain(|block| block.get_block_id() != id);
+			self
+				.thruster_children
+				.retain(|block| block.get_block_id() != id);
+			self
+				.mining_tool_children
+				.retain(|block| block.get_block_id() != id);
+			self
+				.fabricator_children
+				.retain(|block| block.get_block_id() != id);
+			self
+				.weapon_children
+				.retain(|block| block.get_block_id() != id);
+			self.occupied().count() != before
+		}
+
+		/// Removes every block that can't be reached from the camera any more,
+		/// returning where they were so that they can break off as wreckage
+		pub fn detach_disconnected(&mut self) -> Vec<RelativePixel> {
+			let connected = self.connected_to_camera();
+			let mut detached: Vec<RelativePixel> = self
+				.occupied()
+				.filter(|pixel| !connected.contains(*pixel))
+				.collect();
+			if detached.is_empty() {
+				return detached;
+			}
+			detached.sort_by_key(|pixel| pixel.0.to_array());
+
+			self
+				.structure_children
+				.retain(|block| connected.contains(block.location()));
+			self
+				.thruster_children
+				.retain(|block| connected.contains(block.location()));
+			self
+				.mining_tool_children
+				.retain(|block| connected.contains(block.location()));
+			self
+				.fabricator_children
+				.retain(|block| connected.contains(block.location()));
+			self
+				.weapon_children
+				.retain(|block| connected.contains(block.location()));
+			detached
+		}
+
+		/// Gives every block a new [BlockId], so that many players can be spawned
+		/// from the same ship without their blocks' ids clashing
+		pub fn with_new_block_ids(mut self) -> Self {
+			for block in self.structure_children.iter_mut() {
+				block.specific_marker.id = BlockId::random();
+			}
+			for block in self.thruster_children.iter_mut() {
+				block.specific_marker.id = BlockId::random();
+			}
+			for block in self.mining_tool_children.iter_mut() {
+				block.specific_marker.id = BlockId::random();
+			}
+			for block in self.fabricator_children.iter_mut() {
+				block.specific_marker.id = BlockId::random();
+			}
+			for block in self.weapon_children.iter_mut() {
+				block.specific_marker.id = BlockId::random();
+			}
+			self.primary_camera.specific_marker.id = BlockId::random();
+			self
+		}
+
+		/// Applies the `edit` if it is valid, otherwise leaves the blueprint untouched
+		pub fn try_edit(&mut self, edit: ShipEdit) -> Result<(), ShipEditRejection> {
+			match edit {
+				ShipEdit::Place {
+					location,
+					block,
+					facing,
+				} => {
+					let occupied: HashSet<RelativePixel> = self.occupied().collect();
+					if occupied.contains(&location) {
+						return Err(ShipEditRejection::Occupied);
+					}
+					if location.0.abs().max_element() > Self::MAX_EXTENT {
+						return Err(ShipEditRejection::OutOfBounds);
+					}
+					if block != EditorBlock::Camera && occupied.len() >= Self::MAX_BLOCKS {
+						return Err(ShipEditRejection::TooManyBlocks);
+					}
+					let mut placed = self.clone();
+					let mut structure = |material| {
+						placed
+							.structure_children
+							.push(BlockBlueprint::new_structure(material, location))
+					};
+					let mut thruster = |tier| {
+						placed
+							.thruster_children
+							.push(BlockBlueprint::new_thruster_tier(tier, location, facing))
+					};
+					match block {
+						EditorBlock::Structure => structure(StructureMaterial::Aluminum),
+						EditorBlock::SteelStructure => structure(StructureMaterial::Steel),
+						EditorBlock::CompositeStructure => structure(StructureMaterial::Composite),
+						EditorBlock::Thruster => thruster(ThrusterTier::Standard),
+						EditorBlock::HeavyThruster => thruster(ThrusterTier::Heavy),
+						EditorBlock::FusionThruster => thruster(ThrusterTier::Fusion),
+						EditorBlock::MiningTool => placed
+							.mining_tool_children
+							.push(BlockBlueprint::new_mining_tool(location, facing)),
+						EditorBlock::Fabricator => placed
+							.fabricator_children
+							.push(BlockBlueprint::new_fabricator(location)),
+						EditorBlock::Weapon => placed
+							.weapon_children
+							.push(BlockBlueprint::new_weapon(location, facing)),
+						EditorBlock::Camera => {
+							let id = placed.primary_camera.specific_marker.id;
+							placed.primary_camera = BlockBlueprint::new_camera(location, facing);
+							placed.primary_camera.specific_marker.id = id;
+						}
+					}
+					// the new block has to be attached, and a moved camera can't leave anything behind
+					if placed.connected_to_camera().len() != placed.occupied().count() {
+						return Err(ShipEditRejection::Disconnected);
+					}
+					*self = placed;
+					Ok(())
+				}
+				ShipEdit::Remove { location } => {
+					if self.primary_camera.location() == location {
+						return Err(ShipEditRejection::RemovesCamera);
+					}
+					let mut removed = self.clone();
+					removed.remove_block_at(location)?;
+					// the rest of the ship can't be left floating
+					if removed.connected_to_camera().len() != removed.occupied().count() {
+						return Err(ShipEditRejection::Disconnected);
+					}
+					*self = removed;
+					Ok(())
+				}
+			}
+		}
+
+		/// Removes whichever block is at `location`, except the camera
+		fn remove_block_at(&mut self, location: RelativePixel) -> Result<(), ShipEditRejection> {
+			if let Some(index) = self
+				.structure_children
+				.iter()
+				.position(|block| block.location() == location)
+			{
+				if self.structure_children.len() == 1 {
+					return Err(ShipEditRejection::RemovesLastStructure);
+				}
+				self.structure_children.remove(index);
+				return Ok(());
+			}
+			if let Some(index) = self
+				.thruster_children
+				.iter()
+				.position(|block| block.location() == location)
+			{
+				self.thruster_children.remove(index);
+				return Ok(());
+			}
+			if let Some(index) = self
+				.mining_tool_children
+				.iter()
+				.position(|block| block.location() == location)
+			{
+				self.mining_tool_children.remove(index);
+				return Ok(());
+			}
+			if let Some(index) = self
+				.fabricator_children
+				.iter()
+				.position(|block| block.location() == location)
+			{
+				self.fabricator_children.remove(index);
+				return Ok(());
+			}
+			if let Some(index) = self
+				.weapon_children
+				.iter()
+				.position(|block| block.location() == location)
+			{
+				self.weapon_children.remove(index);
+				return Ok(());
+			}
+			Err(ShipEditRejection::NothingThere)
 		}
 	}
 }
 mod player_bundle {
 	use bevy::render::view::NoFrustumCulling;
 
-	use crate::{players::player_movement::PlayerBundleMovementExt, prelude::*};
+	use crate::{
+		cameras::CameraBlockBlueprint,
+		players::{
+			fabricator_block::{FabricationQueue, FabricatorBlockBlueprint},
+			inventory::Inventory,
+			mining_tool_block::MiningToolBlockBlueprint,
+			player_movement::PlayerBundleMovementExt,
+			thruster_block::ThrusterBlockBlueprint,
+			weapon_block::WeaponBlockBlueprint,
+		},
+		prelude::*,
+	};
 
-	use super::{ControllablePlayer, PlayerBlueprintBundle, PlayerBlueprintComponent};
+	use super::{ControllablePlayer, ParkedPlayer, PlayerBlueprintBundle, PlayerBlueprintComponent};
 
 	/// Parent entity of a player.
 	/// Doesn't actually have its own [Mesh] / [Collider],
@@ -246,14 +734,74 @@ mod player_bundle {
 		no_frustum: NoFrustumCulling,
 	}
 
+	/// The blocks a player was already expanded into, see [ChildrenDiff]
+	#[derive(SystemParam)]
+	pub struct PlayerExpansion<'w, 's> {
+		mma: MMA<'w>,
+		stamped: Query<'w, 's, (), With<ControllablePlayer>>,
+		parked: Query<'w, 's, (), With<ParkedPlayer>>,
+		children: Query<'w, 's, &'static Children>,
+		structures: Query<
+			'w,
+			's,
+			(
+				Entity,
+				&'static ExpandedFrom<BlockBlueprint<StructureBlockBlueprint>>,
+			),
+		>,
+		thrusters: Query<
+			'w,
+			's,
+			(
+				Entity,
+				&'static ExpandedFrom<BlockBlueprint<ThrusterBlockBlueprint>>,
+			),
+		>,
+		mining_tools: Query<
+			'w,
+			's,
+			(
+				Entity,
+				&'static ExpandedFrom<BlockBlueprint<MiningToolBlockBlueprint>>,
+			),
+		>,
+		fabricators: Query<
+			'w,
+			's,
+			(
+				Entity,
+				&'static ExpandedFrom<BlockBlueprint<FabricatorBlockBlueprint>>,
+			),
+		>,
+		weapons: Query<
+			'w,
+			's,
+			(
+				Entity,
+				&'static ExpandedFrom<BlockBlueprint<WeaponBlockBlueprint>>,
+			),
+		>,
+		cameras: Query<
+			'w,
+			's,
+			(
+				Entity,
+				&'static ExpandedFrom<BlockBlueprint<CameraBlockBlueprint>>,
+			),
+		>,
+	}
+
 	impl Blueprint for PlayerBlueprintComponent {
 		type Bundle = PlayerBundle;
-		type StampSystemParam<'w, 's> = ();
+		type StampSystemParam = PlayerExpansion<'static, 'static>;
 
-		fn stamp(&self, _system_param: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+		fn stamp(&self, _: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
 			let PlayerBlueprintComponent {
 				structure_children: _,
 				thruster_children: _,
+				mining_tool_children: _,
+				fabricator_children: _,
+				weapon_children: _,
 				primary_camera: _,
 			} = self;
 			Self::Bundle {
@@ -268,11 +816,319 @@ mod player_bundle {
 				no_frustum: NoFrustumCulling,
 			}
 		}
+
+		/// Stamps the player itself when it is first expanded,
+		/// and then only spawns, updates or despawns the blocks that changed
+		fn expand(
+			&self,
+			player: Entity,
+			commands: &mut Commands,
+			expansion: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) {
+			if !expansion.stamped.contains(player) {
+				debug!("Expanding player blueprint");
+				commands.entity(player).insert(self.stamp(expansion));
+				if expansion.parked.contains(player) {
+					// e.g. restored from the [crate::persistence::WorldStore]
+					commands.entity(player).insert(RigidBody::Static);
+				}
+			} else {
+				debug!("Updating player blueprint");
+			}
+			let children: &[Entity] = expansion
+				.children
+				.get(player)
+				.map(|children| &children[..])
+				.unwrap_or_default();
+
+			let structure_diff = ChildrenDiff::new(
+				expansion
+					.structures
+					.iter_many(children)
+					.map(|(e, b)| (e, &b.0)),
+				&self.structure_children,
+			);
+			let thruster_diff = ChildrenDiff::new(
+				expansion
+					.thrusters
+					.iter_many(children)
+					.map(|(e, b)| (e, &b.0)),
+				&self.thruster_children,
+			);
+			let mining_tool_diff = ChildrenDiff::new(
+				expansion
+					.mining_tools
+					.iter_many(children)
+					.map(|(e, b)| (e, &b.0)),
+				&self.mining_tool_children,
+			);
+			let fabricator_diff = ChildrenDiff::new(
+				expansion
+					.fabricators
+					.iter_many(children)
+					.map(|(e, b)| (e, &b.0)),
+				&self.fabricator_children,
+			);
+			let weapon_diff = ChildrenDiff::new(
+				expansion
+					.weapons
+					.iter_many(children)
+					.map(|(e, b)| (e, &b.0)),
+				&self.weapon_children,
+			);
+			let camera_diff = ChildrenDiff::new(
+				expansion
+					.cameras
+					.iter_many(children)
+					.map(|(e, b)| (e, &b.0)),
+				[&self.primary_camera],
+			);
+			expand_children(commands, player, structure_diff, &mut expansion.mma);
+			expand_children(commands, player, thruster_diff, &mut expansion.mma);
+			expand_children(commands, player, mining_tool_diff, &mut expansion.mma);
+			expand_children(commands, player, fabricator_diff, &mut expansion.mma);
+			expand_children(commands, player, weapon_diff, &mut expansion.mma);
+			expand_children(commands, player, camera_diff, &mut expansion.mma);
+		}
 	}
 
 	impl NetworkedBlueprintBundle for PlayerBlueprintBundle {
 		type NetworkedBlueprintComponent = PlayerBlueprintComponent;
+	}
+
+	impl PersistentBundle for PlayerBlueprintBundle {
+		const TABLE: &'static str = "player";
+
+		fn from_entity(entity: EntityRef) -> Option<Self> {
+			Some(Self {
+				transform: *entity.get::<Transform>()?,
+				blueprint: entity.get::<PlayerBlueprintComponent>()?.clone(),
+				network_id: NetworkId::from_raw(entity.get::<NetworkId>()?.get_network_id().raw()),
+				inventory: entity.get::<Inventory>().cloned().unwrap_or_default(),
+				fabrication_queue: entity
+					.get::<FabricationQueue>()
+					.cloned()
+					.unwrap_or_default(),
+			})
+		}
+
+		/// Restored players are parked until they reconnect,
+		/// and are despawned like any other player that left if they don't, see [PlayerSlots::park]
+		fn restore(self, world: &mut World) {
+			let id = self.network_id.get_network_id();
+			match world.get_resource_mut::<PlayerSlots>() {
+				Some(mut slots) => slots.park(id),
+				None => warn!(
+					"Restored player {} without any player slots to park them in",
+					id
+				),
+			}
+			world.spawn((self, ParkedPlayer));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		blocks::manual_builder::{Facing, RelativePixel},
+		players::ship_editor::{EditorBlock, ShipEdit, ShipEditRejection},
+		prelude::*,
+	};
+
+	use super::{PlayerBlueprintBundle, PlayerBlueprintComponent};
+
+	fn default_ship() -> PlayerBlueprintComponent {
+		PlayerBlueprintBundle::new(ClientId::from_raw(1), Transform::default()).blueprint
+	}
+
+	fn place(block: EditorBlock, x: i32, y: i32, z: i32) -> ShipEdit {
+		ShipEdit::Place {
+			location: RelativePixel::new(x, y, z),
+			block,
+			facing: Facing::Backwards,
+		}
+	}
+
+	#[test]
+	fn places_next_to_the_ship() {
+		let mut ship = default_ship();
+		let blocks = ship.occupied().count();
+
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::Structure, 0, 0, -2)),
+			Ok(())
+		);
+		assert_eq!(ship.occupied().count(), blocks + 1);
+		assert!(ship
+			.occupied()
+			.any(|pixel| pixel == RelativePixel::new(0, 0, -2)));
+	}
+
+	#[test]
+	fn rejects_invalid_placements() {
+		let mut ship = default_ship();
+
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::Structure, 0, 0, 0)),
+			Err(ShipEditRejection::Occupied)
+		);
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::Thruster, 0, 0, -5)),
+			Err(ShipEditRejection::Disconnected)
+		);
+		assert_eq!(
+			ship.try_edit(place(
+				EditorBlock::Structure,
+				PlayerBlueprintComponent::MAX_EXTENT + 1,
+				0,
+				0
+			)),
+			Err(ShipEditRejection::OutOfBounds)
+		);
+	}
+
+	#[test]
+	fn removes_blocks_but_not_the_camera() {
+		let mut ship = default_ship();
+		let blocks = ship.occupied().count();
+
+		// main engine
+		let engine = ShipEdit::Remove {
+			location: RelativePixel::new(0, 0, 3),
+		};
+		assert_eq!(ship.try_edit(engine), Ok(()));
+		assert_eq!(ship.occupied().count(), blocks - 1);
+		assert_eq!(ship.try_edit(engine), Err(ShipEditRejection::NothingThere));
+
+		assert_eq!(
+			ship.try_edit(ShipEdit::Remove {
+				location: RelativePixel::new(0, 1, 0)
+			}),
+			Err(ShipEditRejection::RemovesCamera)
+		);
+	}
+
+	#[test]
+	fn places_and_removes_mining_tools() {
+		let mut ship = default_ship();
+		let blocks = ship.occupied().count();
+
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::MiningTool, 0, 0, -2)),
+			Ok(())
+		);
+		assert_eq!(ship.occupied().count(), blocks + 1);
+		assert_eq!(
+			ship.try_edit(ShipEdit::Remove {
+				location: RelativePixel::new(0, 0, -2)
+			}),
+			Ok(())
+		);
+		assert_eq!(ship.occupied().count(), blocks);
+	}
+
+	#[test]
+	fn places_block_tiers() {
+		let mut ship = default_ship();
+
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::SteelStructure, 0, 0, -2)),
+			Ok(())
+		);
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::HeavyThruster, 0, 0, -3)),
+			Ok(())
+		);
+		assert_eq!(
+			ship.block_at(RelativePixel::new(0, 0, -2)),
+			Some(EditorBlock::SteelStructure)
+		);
+		assert_eq!(
+			ship.block_at(RelativePixel::new(0, 0, -3)),
+			Some(EditorBlock::HeavyThruster)
+		);
+		assert_eq!(
+			ship.block_at(RelativePixel::new(0, -1, 1)),
+			Some(EditorBlock::Fabricator)
+		);
+		assert_eq!(
+			ship.block_at(RelativePixel::new(-1, 0, -1)),
+			Some(EditorBlock::Weapon)
+		);
+		assert_eq!(ship.block_at(RelativePixel::new(0, 0, -4)), None);
+		assert_eq!(ship.validate(), Ok(()));
+	}
+
+	#[test]
+	fn moves_the_camera() {
+		let mut ship = default_ship();
+		let blocks = ship.occupied().count();
+
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::Camera, 0, 1, -1)),
+			Err(ShipEditRejection::Occupied)
+		);
+		assert_eq!(ship.try_edit(place(EditorBlock::Camera, 0, 1, 1)), Ok(()));
+		assert_eq!(ship.occupied().count(), blocks);
+
+		// only attached to the camera, so moving the camera again would leave it floating
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::Structure, 0, 2, 1)),
+			Ok(())
+		);
+		let before = ship.clone();
+		assert_eq!(
+			ship.try_edit(place(EditorBlock::Camera, 0, 1, 0)),
+			Err(ShipEditRejection::Disconnected)
+		);
+		assert_eq!(ship, before);
+	}
+
+	#[test]
+	fn destroyed_blocks_detach_what_they_held() {
+		let mut ship = default_ship();
+		let front = ship
+			.structure_children
+			.iter()
+			.find(|block| block.location() == RelativePixel::new(0, 0, -1))
+			.unwrap()
+			.get_block_id();
+
+		assert!(ship.destroy_block(front));
+		assert!(!ship.destroy_block(front));
+		// the down thruster was only attached to the front block
+		assert_eq!(
+			ship.detach_disconnected(),
+			vec![RelativePixel::new(0, -1, -1)]
+		);
+		assert_eq!(ship.block_at(RelativePixel::new(0, -1, -1)), None);
+		assert!(ship.detach_disconnected().is_empty());
+		assert!(!ship.destroy_block(ship.primary_camera.get_block_id()));
+		assert_eq!(ship.validate(), Ok(()));
+	}
+
+	#[test]
+	fn validates_whole_ships() {
+		let mut ship = default_ship();
+		assert_eq!(ship.validate(), Ok(()));
+		let before = ship.clone();
+
+		// would leave the bottom front thruster floating
+		let front = ShipEdit::Remove {
+			location: RelativePixel::new(0, 0, -1),
+		};
+		assert_eq!(ship.try_edit(front), Err(ShipEditRejection::Disconnected));
+		assert_eq!(ship, before);
 
-		type SpawnSystemParam = ();
+		// destroying it does, which is caught by validation
+		let front = ship
+			.structure_children
+			.iter()
+			.find(|block| block.location() == RelativePixel::new(0, 0, -1))
+			.unwrap()
+			.get_block_id();
+		assert!(ship.destroy_block(front));
+		assert_eq!(ship.validate(), Err(ShipEditRejection::Disconnected));
 	}
 }
@@ -3,6 +3,7 @@
 //!
 //! Is designed to be generic over where thrusters are placed
 //! and their rotations, so that building your own ships is possible.
+//! Ships without a player are steered by an [AiPilot] instead.
 
 use crate::prelude::*;
 
@@ -13,10 +14,10 @@ pub struct PlayerMovementPlugin;
 impl Plugin for PlayerMovementPlugin {
 	fn build(&self, app: &mut App) {
 		app
-			.replicate::<components::IntendedVelocity>()
 			.configure_sets(
 				FixedUpdate,
 				(
+					PlayerMovementSet::ProcessInputs,
 					PlayerMovementSet::ComputeStrengths,
 					PlayerMovementSet::EnactThrusters,
 				)
@@ -31,16 +32,25 @@ impl Plugin for PlayerMovementPlugin {
 						Self::calculate_intended_velocity,
 						Self::calculate_actual_velocity,
 					),
+					Self::steer_ai_ships
+						.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
+					Self::cycle_flight_assist,
 					Self::calculate_thruster_strengths,
 				)
 					.chain()
 					.in_set(PlayerMovementSet::ComputeStrengths),
 			)
-			.add_plugins(InputManagerPlugin::<PlayerInput>::default())
+			.add_plugins((
+				InputManagerPlugin::<PlayerInput>::default(),
+				input_processing::InputProcessingPlugin,
+				prediction::PredictionPlugin,
+			))
 			.register_type::<components::ThrusterAxis>()
 			.register_type::<components::ThrusterStrengths>()
 			.register_type::<components::IntendedVelocity>()
-			.register_type::<components::ActualVelocity>();
+			.register_type::<components::ActualVelocity>()
+			.register_type::<FlightAssist>()
+			.register_type::<AiPilot>();
 	}
 }
 
@@ -49,7 +59,10 @@ mod api {
 	use super::components::{ActualVelocity, IntendedVelocity, ThrusterAxis, ThrusterStrengths};
 	use crate::prelude::*;
 
-	pub use super::input_processing::PlayerInput;
+	pub use super::ai_pilot::AiPilot;
+	pub use super::flight_assist::{FlightAssist, FlightAssistMode, PidController, PidGains};
+	pub use super::input_processing::{InputBuffer, InputDiff, PlayerInput, PlayerInputDiffs};
+	pub use super::prediction::{Predicted, VisualError};
 
 	#[derive(SystemParam, Debug)]
 	pub struct GetThrusterData<'w, 's> {
@@ -63,6 +76,7 @@ mod api {
 		thruster_axis: ThrusterAxis,
 		intended_velocity: IntendedVelocity,
 		actual_velocity: ActualVelocity,
+		flight_assist: FlightAssist,
 	}
 
 	impl PlayerBundleMovementExt {
@@ -73,6 +87,7 @@ mod api {
 				thruster_axis: ThrusterAxis::default(),
 				intended_velocity: IntendedVelocity::default(),
 				actual_velocity: ActualVelocity::default(),
+				flight_assist: FlightAssist::default(),
 			}
 		}
 	}
@@ -89,8 +104,14 @@ mod api {
 	}
 }
 
+mod ai_pilot;
+
+mod flight_assist;
+
 mod input_processing;
 
+mod prediction;
+
 mod systems;
 
 mod components;
@@ -0,0 +1,139 @@
+//! Flies ships that no player controls, e.g. the enemies of [crate::players::enemy_waves].
+//!
+//! An [AiPilot] only decides the ship's [IntendedVelocity] and whether to fire,
+//! just like a player's inputs would, so its ship is flown by the same flight assist
+//! and thruster allocation as every player's ship.
+
+use crate::{players::player::ParkedPlayer, prelude::*};
+
+use super::{
+	components::IntendedVelocity, PlayerInput, PlayerMovementPlugin, Velocity6DimensionsMut,
+};
+
+/// Marks ships flown by the server, which chase and shoot at the nearest player.
+///
+/// Only exists on the server, clients see these ships like any other
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct AiPilot {
+	/// Keeps about this far away from its target
+	pub engagement_distance: f32,
+	/// Fires once the target is within this angle of straight ahead, in radians
+	pub firing_cone: f32,
+}
+
+impl Default for AiPilot {
+	fn default() -> Self {
+		Self {
+			engagement_distance: 15.,
+			firing_cone: 0.15,
+		}
+	}
+}
+
+impl AiPilot {
+	/// How fast the pilot turns towards its target for every radian it is off
+	const TURN_GAIN: f32 = 2.;
+	/// Doesn't bother firing at targets further away than this many engagement distances
+	const FIRING_RANGE: f32 = 2.;
+
+	/// What to do about a target at `offset`, given in the ship's local frame.
+	/// Returns the intended velocity and whether to fire
+	pub(super) fn steer(&self, offset: Vec3) -> (IntendedVelocity, bool) {
+		let mut intended = IntendedVelocity::default();
+		let Some(direction) = offset.try_normalize() else {
+			return (intended, false);
+		};
+		let distance = offset.length();
+
+		// forwards is -Z, so these are zero when facing the target
+		let yaw = offset.x.atan2(-offset.z);
+		let pitch = offset.y.atan2(Vec2::new(offset.x, offset.z).length());
+		const ROTATION: f32 = PlayerInput::ROTATION_FACTOR;
+		intended.add_turn_right((yaw * Self::TURN_GAIN).clamp(-ROTATION, ROTATION));
+		intended.add_tilt_up((pitch * Self::TURN_GAIN).clamp(-ROTATION, ROTATION));
+
+		// only closes in while roughly facing the target, so it doesn't fly past it
+		let facing = direction.dot(Vec3::NEG_Z);
+		const FORCE: f32 = PlayerInput::FORCE_FACTOR;
+		if distance > self.engagement_distance && facing > 0.5 {
+			intended.add_forward(FORCE);
+		} else if distance < self.engagement_distance / 2. {
+			intended.add_backward(FORCE);
+		}
+
+		let aimed = facing.clamp(-1., 1.).acos() <= self.firing_cone;
+		let in_range = distance <= self.engagement_distance * Self::FIRING_RANGE;
+		(intended, aimed && in_range)
+	}
+}
+
+impl PlayerMovementPlugin {
+	/// Steers every [AiPilot]'s ship towards the nearest player, see [AiPilot::steer]
+	pub(super) fn steer_ai_ships(
+		mut ships: Query<(
+			&AiPilot,
+			&Transform,
+			&mut IntendedVelocity,
+			&mut ActionState<PlayerInput>,
+		)>,
+		players: Query<&Transform, (With<NetworkId>, Without<ParkedPlayer>)>,
+	) {
+		for (pilot, transform, mut intended_velocity, mut inputs) in ships.iter_mut() {
+			let target = players
+				.iter()
+				.map(|player| player.translation)
+				.min_by(|a, b| {
+					a.distance_squared(transform.translation)
+						.total_cmp(&b.distance_squared(transform.translation))
+				});
+			let (intended, fire) = match target {
+				Some(target) => {
+					pilot.steer(transform.rotation.inverse() * (target - transform.translation))
+				}
+				None => (IntendedVelocity::default(), false),
+			};
+
+			*intended_velocity = intended;
+			if fire {
+				inputs.press(PlayerInput::Fire);
+			} else {
+				inputs.release(PlayerInput::Fire);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::prelude::*;
+
+	use super::{super::Velocity6Dimensions, AiPilot};
+
+	#[test]
+	fn turns_towards_and_shoots_at_its_target() {
+		let pilot = AiPilot::default();
+
+		// to the right and far away, turns right without firing
+		let (intended, fire) = pilot.steer(Vec3::new(50., 0., 0.));
+		assert!(intended.turn_right() > 0.);
+		assert_eq!(intended.forward(), 0.);
+		assert!(!fire);
+
+		// above, pitches up
+		let (intended, _) = pilot.steer(Vec3::new(0., 10., -1.));
+		assert!(intended.tilt_up() > 0.);
+
+		// straight ahead and far away, closes in and fires once in range
+		let (intended, fire) = pilot.steer(Vec3::new(0., 0., -100.));
+		assert!(intended.forward() > 0.);
+		assert!(!fire);
+		let (intended, fire) = pilot.steer(Vec3::NEG_Z * pilot.engagement_distance * 1.5);
+		assert!(intended.forward() > 0.);
+		assert!(fire);
+
+		// too close, backs off while still firing
+		let (intended, fire) = pilot.steer(Vec3::new(0., 0., -1.));
+		assert!(intended.forward() < 0.);
+		assert!(fire);
+	}
+}
@@ -40,14 +40,23 @@ use super::{Velocity6Dimensions, Velocity6DimensionsMut};
 mod force_axis;
 
 impl ThrusterAxis {
+	/// Takes each thruster's relative [Transform] and strength factor,
+	/// and the mass properties of the player they are on
 	pub(super) fn new<'w>(
 		center_of_mass: &'w CenterOfMass,
-		blocks: impl IntoIterator<Item = (BlockId, &'w Transform)>,
+		inverse_mass: &'w InverseMass,
+		inverse_inertia: &'w InverseInertia,
+		blocks: impl IntoIterator<Item = (BlockId, &'w Transform, f32)>,
 	) -> Self {
 		Self {
 			blocks: blocks
 				.into_iter()
-				.map(|(id, t)| (id, ForceAxis::new(t, center_of_mass)))
+				.map(|(id, t, strength_factor)| {
+					(
+						id,
+						ForceAxis::new(t, strength_factor, center_of_mass, inverse_mass, inverse_inertia),
+					)
+				})
 				.collect(),
 		}
 	}
@@ -57,7 +66,7 @@ impl ThrusterAxis {
 	}
 }
 
-#[derive(Debug, Reflect, Component, Default, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Reflect, Component, Default, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub(super) struct IntendedVelocity {
 	forward: f32,
 	right: f32,
@@ -128,6 +137,22 @@ pub(super) struct ActualVelocity {
 	roll_right: f32,
 }
 
+impl ActualVelocity {
+	/// Takes velocities already in the ship's local frame,
+	/// with rotations using the same conventions as [ForceAxis]
+	pub(super) fn from_local(lin: Vec3, ang: Vec3) -> Self {
+		Self {
+			forward: -lin.z,
+			right: lin.x,
+			up: lin.y,
+			// right handed rotations, e.g. turning right is rotating around -Y
+			turn_right: -ang.y,
+			tilt_up: ang.x,
+			roll_right: -ang.z,
+		}
+	}
+}
+
 impl Velocity6Dimensions for ActualVelocity {
 	fn velocity_forward(&self) -> f32 {
 		self.forward
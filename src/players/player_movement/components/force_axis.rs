@@ -3,8 +3,11 @@ use crate::{
 	prelude::*,
 };
 
-/// Forces are between [-1, 1],
-/// but torque can be infinite so is [SignedFlag] instead
+/// The linear (m/s^2) and angular (rad/s^2) acceleration a thruster
+/// firing at full strength gives its ship, in the ship's local frame.
+///
+/// Scales with the thruster's strength, its lever arm around the center of mass,
+/// and the ship's mass and [Inertia].
 ///
 /// The Greek philosopher, Archimedes, said,
 /// “Give me a lever long enough and a fulcrum on which to place it, and I shall move the world.”
@@ -41,51 +44,37 @@ pub struct ForceAxis {
 // }
 
 impl ForceAxis {
-	pub fn from_iter(
-		mut forces: impl FnMut(Vec3) -> f32,
-		mut torques: impl FnMut(Vec3) -> f32,
-	) -> Self {
-		Self {
-			forward: forces(-Vec3::Z),
-			right: forces(Vec3::X),
-			upwards: forces(Vec3::Y),
-			turn_right: torques(-Vec3::Y),
-			pitch_up: torques(Vec3::X),
-			roll_right: torques(Vec3::Z),
-		}
-	}
-
 	/// Takes the transform of a thruster, including its relative translation and rotation,
-	/// and the center of mass of the player, and computes what effect in
-	/// each of the 3 force and 3 torque axis it would have on the player
+	/// its strength, and the mass properties of the player, and computes what effect in
+	/// each of the 3 linear and 3 angular axis it would have on the player.
+	///
+	/// See [bevy_xpbd3d_thrusters::prelude::ForceAxis::new], which does the maths
 	pub(super) fn new(
-		Transform {
-			translation,
-			rotation,
-			..
-		}: &Transform,
+		thruster: &Transform,
+		strength_factor: f32,
 		center_of_mass: &CenterOfMass,
+		inverse_mass: &InverseMass,
+		inverse_inertia: &InverseInertia,
 	) -> Self {
-		let relative_force = rotation.mul_vec3(Vec3::Z);
+		use bevy_xpbd3d_thrusters::prelude::Relative6DVector;
 
-		#[cfg(test)]
-		println!(
-			"Relative force: {:?}, translation: {:?}",
-			relative_force, translation
-		);
-
-		let ef = *ExternalForce::new(Vec3::ZERO).apply_force_at_point(
-			relative_force,
-			*translation,
+		let axis = bevy_xpbd3d_thrusters::prelude::ForceAxis::new(
+			thruster,
+			strength_factor,
 			center_of_mass.0,
-		);
-		let force = ef.force().normalize();
-		let forces = |dir: Vec3| force.dot(dir);
+			inverse_mass.0,
+			inverse_inertia.0,
+		)
+		.get_generic();
 
-		let torque = ef.torque().normalize();
-		let torques = |dir: Vec3| torque.dot(dir);
-
-		Self::from_iter(forces, torques)
+		Self {
+			forward: axis.forward,
+			right: axis.right,
+			upwards: axis.upwards,
+			turn_right: axis.turn_right,
+			pitch_up: axis.pitch_up,
+			roll_right: axis.roll_right,
+		}
 	}
 
 	// /// How much strength should a thruster exert?
@@ -216,6 +205,25 @@ mod test {
 
 	use super::ForceAxis;
 
+	/// A ship with unit mass and inertia, so accelerations equal forces and torques
+	fn unit_force_axis(thruster: &Transform, strength_factor: f32) -> ForceAxis {
+		ForceAxis::new(
+			thruster,
+			strength_factor,
+			&CenterOfMass(Vec3::ZERO),
+			&InverseMass(1.),
+			&InverseInertia(Mat3::IDENTITY),
+		)
+	}
+
+	fn thruster_at(z: i32, facing: Facing) -> Transform {
+		Transform {
+			translation: RelativePixel::new(0, 0, z).into_world_offset(),
+			rotation: facing.into_quat(),
+			..default()
+		}
+	}
+
 	// #[test]
 	// fn force_axis_dot() {
 	// 	let global_goal = ForceAxis {
@@ -254,10 +262,11 @@ mod test {
 			rotation: Facing::Right.into_quat(),
 			..default()
 		};
-		let force_axis = ForceAxis::new(&thruster_location, &CenterOfMass(Vec3::ZERO));
+		let force_axis = unit_force_axis(&thruster_location, 1.);
 		println!("Force axis {:?}", force_axis);
 
-		assert!(force_axis.turn_right == 1.0);
+		// unit strength and mass, one block behind the center of mass
+		assert_near!(force_axis.turn_right, 1.0);
 		assert!(force_axis.pitch_up == 0.0);
 		assert!(force_axis.roll_right == 0.0);
 		assert!(force_axis.right < 0.0);
@@ -265,6 +274,56 @@ mod test {
 		assert!(force_axis.forward == 0.0);
 	}
 
+	#[test]
+	fn scales_with_lever_arm() {
+		let near = unit_force_axis(&thruster_at(1, Facing::Right), 1.);
+		let far = unit_force_axis(&thruster_at(10, Facing::Right), 1.);
+
+		assert_near!(far.turn_right, near.turn_right * 10.);
+		// same push, regardless of where it is applied
+		assert_near!(far.right, near.right);
+	}
+
+	#[test]
+	fn scales_with_strength() {
+		let weak = unit_force_axis(&thruster_at(1, Facing::Right), 1.);
+		let strong = unit_force_axis(&thruster_at(1, Facing::Right), 10.);
+
+		assert_near!(strong.right, weak.right * 10.);
+		assert_near!(strong.turn_right, weak.turn_right * 10.);
+	}
+
+	#[test]
+	fn scales_with_mass_properties() {
+		let thruster = thruster_at(1, Facing::Right);
+		let light = unit_force_axis(&thruster, 1.);
+		let heavy = ForceAxis::new(
+			&thruster,
+			1.,
+			&CenterOfMass(Vec3::ZERO),
+			&InverseMass(1. / 4.),
+			&InverseInertia(Mat3::from_diagonal(Vec3::splat(1. / 2.))),
+		);
+
+		assert_near!(heavy.right, light.right / 4.);
+		assert_near!(heavy.turn_right, light.turn_right / 2.);
+	}
+
+	#[test]
+	fn lever_arm_is_relative_to_center_of_mass() {
+		let thruster = thruster_at(1, Facing::Right);
+		let centered = ForceAxis::new(
+			&thruster,
+			1.,
+			&CenterOfMass(thruster.translation),
+			&InverseMass(1.),
+			&InverseInertia(Mat3::IDENTITY),
+		);
+
+		assert_near!(centered.turn_right, 0.);
+		assert!(centered.right < 0.);
+	}
+
 	#[test]
 	fn apply_force_at_point() {
 		// force rightwards at back of ship
@@ -0,0 +1,293 @@
+//! Flight assist turns what the player intends into what the thrusters should do,
+//! using a [PidController] for each of the six axis.
+
+use crate::prelude::*;
+
+use super::{
+	components::{ActualVelocity, IntendedVelocity},
+	Velocity6Dimensions,
+};
+use bevy_xpbd3d_thrusters::prelude::Vec6;
+
+/// How the [FlightAssist] interprets the player's inputs
+#[derive(Debug, Default, Reflect, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlightAssistMode {
+	/// Inputs set the target velocity, letting go of every input brings the ship to a stop
+	#[default]
+	FullDampening,
+
+	/// Inputs accelerate the target velocity, which is held when letting go
+	VelocityHold,
+
+	/// Flight assist is off, inputs fire thrusters directly and the ship coasts with its inertia
+	Newtonian,
+}
+
+impl FlightAssistMode {
+	pub fn next(self) -> Self {
+		match self {
+			Self::FullDampening => Self::VelocityHold,
+			Self::VelocityHold => Self::Newtonian,
+			Self::Newtonian => Self::FullDampening,
+		}
+	}
+}
+
+#[derive(Debug, Reflect, Clone, Copy, PartialEq)]
+pub struct PidGains {
+	pub proportional: f32,
+	pub integral: f32,
+	pub derivative: f32,
+	/// Anti-windup, the accumulated integral is clamped to `[-integral_limit, integral_limit]`
+	pub integral_limit: f32,
+}
+
+impl PidGains {
+	pub const LINEAR: Self = Self {
+		proportional: 1.,
+		integral: 0.2,
+		derivative: 0.05,
+		integral_limit: 2.,
+	};
+
+	pub const ANGULAR: Self = Self {
+		proportional: 2.,
+		integral: 0.1,
+		derivative: 0.1,
+		integral_limit: 1.,
+	};
+}
+
+/// Controls one axis, see [PidGains]
+#[derive(Debug, Reflect, Clone, PartialEq)]
+pub struct PidController {
+	pub gains: PidGains,
+	#[reflect(ignore)]
+	integral: f32,
+	#[reflect(ignore)]
+	previous_error: Option<f32>,
+}
+
+impl PidController {
+	pub fn new(gains: PidGains) -> Self {
+		Self {
+			gains,
+			integral: 0.,
+			previous_error: None,
+		}
+	}
+
+	/// Returns the control output for the current `error`, `delta` seconds after the last update
+	pub fn update(&mut self, error: f32, delta: f32) -> f32 {
+		let limit = self.gains.integral_limit;
+		self.integral = (self.integral + error * delta).clamp(-limit, limit);
+
+		let derivative = match self.previous_error {
+			Some(previous) if delta > 0. => (error - previous) / delta,
+			_ => 0.,
+		};
+		self.previous_error = Some(error);
+
+		self.gains.proportional * error
+			+ self.gains.integral * self.integral
+			+ self.gains.derivative * derivative
+	}
+
+	pub fn reset(&mut self) {
+		self.integral = 0.;
+		self.previous_error = None;
+	}
+}
+
+/// Per-player flight assist, placed on players.
+///
+/// Tune the gains in the editor.
+#[derive(Component, Debug, Reflect, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct FlightAssist {
+	mode: FlightAssistMode,
+
+	pub forward: PidController,
+	pub right: PidController,
+	pub up: PidController,
+	pub turn_right: PidController,
+	pub tilt_up: PidController,
+	pub roll_right: PidController,
+
+	/// Target velocity in [FlightAssistMode::VelocityHold]
+	#[reflect(ignore)]
+	held: [f32; 6],
+
+	/// Whether [super::PlayerInput::CycleFlightAssist] was pressed last tick
+	#[reflect(ignore)]
+	cycle_pressed: bool,
+}
+
+impl Default for FlightAssist {
+	fn default() -> Self {
+		Self {
+			mode: FlightAssistMode::default(),
+			forward: PidController::new(PidGains::LINEAR),
+			right: PidController::new(PidGains::LINEAR),
+			up: PidController::new(PidGains::LINEAR),
+			turn_right: PidController::new(PidGains::ANGULAR),
+			tilt_up: PidController::new(PidGains::ANGULAR),
+			roll_right: PidController::new(PidGains::ANGULAR),
+			held: [0.; 6],
+			cycle_pressed: false,
+		}
+	}
+}
+
+impl FlightAssist {
+	pub fn get_mode(&self) -> FlightAssistMode {
+		self.mode
+	}
+
+	/// Switches modes, starting to hold the `current` velocity when
+	/// switching to [FlightAssistMode::VelocityHold]
+	pub(super) fn set_mode(&mut self, mode: FlightAssistMode, current: &ActualVelocity) {
+		self.mode = mode;
+		self.held = axes(current);
+		for controller in self.controllers_mut() {
+			controller.reset();
+		}
+	}
+
+	/// Cycles to the [FlightAssistMode::next] mode once per press
+	pub(super) fn handle_cycle_input(&mut self, pressed: bool, current: &ActualVelocity) {
+		if pressed && !self.cycle_pressed {
+			let mode = self.mode.next();
+			debug!("Switching flight assist to {:?}", mode);
+			self.set_mode(mode, current);
+		}
+		self.cycle_pressed = pressed;
+	}
+
+	fn controllers_mut(&mut self) -> [&mut PidController; 6] {
+		[
+			&mut self.forward,
+			&mut self.right,
+			&mut self.up,
+			&mut self.turn_right,
+			&mut self.tilt_up,
+			&mut self.roll_right,
+		]
+	}
+
+	/// Computes the acceleration the thrusters should give the ship this tick,
+	/// to be fed into the thruster allocator along with each thruster's [super::components::ThrusterAxis]
+	pub(super) fn command(
+		&mut self,
+		intended: &IntendedVelocity,
+		actual: &ActualVelocity,
+		delta: f32,
+	) -> Vec6 {
+		let intended = axes(intended);
+		let actual = axes(actual);
+
+		let target = match self.mode {
+			FlightAssistMode::Newtonian => return vec6(intended),
+			FlightAssistMode::FullDampening => intended,
+			FlightAssistMode::VelocityHold => {
+				for (held, intended) in self.held.iter_mut().zip(intended) {
+					*held += intended * delta;
+				}
+				self.held
+			}
+		};
+
+		let mut output = [0.; 6];
+		for (i, controller) in self.controllers_mut().into_iter().enumerate() {
+			output[i] = controller.update(target[i] - actual[i], delta);
+		}
+		vec6(output)
+	}
+}
+
+fn axes(velocity: &impl Velocity6Dimensions) -> [f32; 6] {
+	[
+		velocity.forward(),
+		velocity.right(),
+		velocity.up(),
+		velocity.turn_right(),
+		velocity.tilt_up(),
+		velocity.roll_right(),
+	]
+}
+
+fn vec6([forward, right, upwards, turn_right, pitch_up, roll_right]: [f32; 6]) -> Vec6 {
+	Vec6 {
+		forward,
+		right,
+		upwards,
+		turn_right,
+		pitch_up,
+		roll_right,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::players::player_movement::Velocity6DimensionsMut;
+
+	const DELTA: f32 = 1. / 64.;
+
+	#[test]
+	fn proportional_only() {
+		let mut pid = PidController::new(PidGains {
+			proportional: 2.,
+			integral: 0.,
+			derivative: 0.,
+			integral_limit: 1.,
+		});
+		assert_near!(pid.update(0.5, DELTA), 1.);
+	}
+
+	#[test]
+	fn integral_is_clamped() {
+		let mut pid = PidController::new(PidGains {
+			proportional: 0.,
+			integral: 1.,
+			derivative: 0.,
+			integral_limit: 0.5,
+		});
+		for _ in 0..1000 {
+			pid.update(1., DELTA);
+		}
+		assert_near!(pid.update(1., DELTA), 0.5);
+	}
+
+	#[test]
+	fn full_dampening_stops_the_ship() {
+		let mut assist = FlightAssist::default();
+		let moving = ActualVelocity::from_local(Vec3::new(0., 0., -1.), Vec3::ZERO);
+
+		let command = assist.command(&IntendedVelocity::default(), &moving, DELTA);
+		assert!(command.forward < 0.);
+	}
+
+	#[test]
+	fn velocity_hold_keeps_going() {
+		let mut assist = FlightAssist::default();
+		let moving = ActualVelocity::from_local(Vec3::new(0., 0., -1.), Vec3::ZERO);
+		assist.set_mode(FlightAssistMode::VelocityHold, &moving);
+
+		let command = assist.command(&IntendedVelocity::default(), &moving, DELTA);
+		assert_near!(command.forward, 0.);
+	}
+
+	#[test]
+	fn newtonian_passes_inputs_through() {
+		let mut assist = FlightAssist::default();
+		let moving = ActualVelocity::from_local(Vec3::new(0., 0., -1.), Vec3::ZERO);
+		assist.set_mode(FlightAssistMode::Newtonian, &moving);
+
+		let mut intended = IntendedVelocity::default();
+		intended.add_turn_right(1.);
+		let command = assist.command(&intended, &moving, DELTA);
+		assert_near!(command.forward, 0.);
+		assert_near!(command.turn_right, 1.);
+	}
+}
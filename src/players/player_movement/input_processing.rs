@@ -1,66 +1,316 @@
-use crate::prelude::*;
-
-// pub struct InputProcessingPlugin;
-
-// impl Plugin for InputProcessingPlugin {
-// 	fn build(&self, app: &mut App) {
-// 		app
-// 			.add_systems(PreUpdate, process_action_diffs::<PlayerInput, Key>)
-// 			.add_systems(
-// 				PostUpdate,
-// 				generate_action_diffs::<PlayerInput, Key>.run_if(NetcodeConfig::not_headless()),
-// 			)
-// 			// .register_type::<Key>()
-// 			.add_client_event::<ActionDiff<PlayerInput, Key>>(SendType::Unreliable);
-// 	}
-// }
-
-#[derive(ActionLike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
-pub enum PlayerInput {
-	Forward,
-	Backward,
-	Left,
-	Right,
-}
+//! Sends the local player's inputs to the server, since movement is derived
+//! from inputs on both the client (see [super::prediction]) and the server.
+//!
+//! Clients send [PlayerInputDiffs] tagged with the [GameClock] frame they were made on,
+//! which the server buffers in an [InputBuffer] until it simulates that frame.
+
+use crate::{players::PlayerMovementSet, prelude::*};
+
+pub use api::*;
+
+pub(super) struct InputProcessingPlugin;
 
-impl PlayerInput {
-	pub const FORCE_FACTOR: f32 = 2.;
-	pub const ROTATION_FACTOR: f32 = 2.;
+impl Plugin for InputProcessingPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.add_client_event::<PlayerInputDiffs>(EventType::Ordered)
+			.add_systems(
+				Update,
+				Self::remove_remote_input_maps.run_if(resource_exists::<NetcodeConfig>()),
+			)
+			.add_systems(
+				Update,
+				Self::receive_input_diffs.run_if(resource_exists::<RenetServer>()),
+			)
+			.add_systems(
+				FixedUpdate,
+				(
+					Self::send_input_diffs
+						.run_if(resource_exists::<RenetClient>())
+						.run_if(not(resource_exists::<Rollback>())),
+					Self::apply_buffered_inputs.run_if(resource_exists::<RenetServer>()),
+				)
+					.in_set(PlayerMovementSet::ProcessInputs),
+			);
+	}
 }
 
-// #[derive(SystemParam, Debug)]
-// pub struct PlayerInputs<'w, 's> {
-// 	query: Query<'w, 's, (Entity, &'static ActionState<PlayerInput>)>,
-// }
-
-// impl PlayerInputs<'_, '_> {
-// 	// pub fn get_from_id(&self, player_id: ClientId) -> Option<&ActionState<PlayerInput>> {
-// 	// 	self
-// 	// 		.query
-// 	// 		.iter()
-// 	// 		.find(|(_, player)| player.get_network_id() == player_id)
-// 	// 		.map(|(action_state, _)| action_state)
-// 	// }
-
-// 	// pub fn get(&self, e: Entity) -> Option<&ActionState<PlayerInput>> {
-// 	// 	self.query.get(e).ok().map(|(action_state, _)| action_state)
-// 	// }
-
-// 	pub fn iter(&self) -> impl Iterator<Item = (Entity, &ActionState<PlayerInput>)> {
-// 		self.query.iter()
-// 	}
-// }
-
-impl PlayerInput {
-	pub fn new() -> InputManagerBundle<Self> {
-		InputManagerBundle {
-			action_state: ActionState::default(),
-			input_map: InputMap::new([
+mod api {
+	use std::collections::BTreeMap;
+
+	use crate::prelude::*;
+
+	/// Every action a player can take to control their ship, covering all 6 degrees of freedom
+	#[derive(ActionLike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect, Serialize, Deserialize)]
+	pub enum PlayerInput {
+		Forward,
+		Backward,
+		/// Turn (yaw) left
+		Left,
+		/// Turn (yaw) right
+		Right,
+		StrafeLeft,
+		StrafeRight,
+		Ascend,
+		Descend,
+		PitchUp,
+		PitchDown,
+		RollLeft,
+		RollRight,
+		/// Cycles through the [FlightAssistMode]s
+		CycleFlightAssist,
+		/// Fires every mining tool on the ship, see [crate::players::mining_tool_block]
+		Mine,
+		/// Fires every weapon on the ship, see [crate::players::weapon_block]
+		Fire,
+	}
+
+	impl PlayerInput {
+		pub const FORCE_FACTOR: f32 = 2.;
+		pub const ROTATION_FACTOR: f32 = 2.;
+	}
+
+	impl PlayerInput {
+		/// How far a gamepad stick has to be pushed to count as pressed
+		const STICK_THRESHOLD: f32 = 0.3;
+
+		pub fn new() -> InputManagerBundle<Self> {
+			InputManagerBundle {
+				action_state: ActionState::default(),
+				input_map: Self::default_input_map(),
+			}
+		}
+
+		fn default_input_map() -> InputMap<Self> {
+			use GamepadAxisType::*;
+			let pushed = |axis: GamepadAxisType| SingleAxis::positive_only(axis, Self::STICK_THRESHOLD);
+			let pulled = |axis: GamepadAxisType| SingleAxis::negative_only(axis, -Self::STICK_THRESHOLD);
+
+			let mut input_map = InputMap::new([
 				(KeyCode::W, PlayerInput::Forward),
 				(KeyCode::S, PlayerInput::Backward),
 				(KeyCode::A, PlayerInput::Left),
 				(KeyCode::D, PlayerInput::Right),
-			]),
+				(KeyCode::Left, PlayerInput::StrafeLeft),
+				(KeyCode::Right, PlayerInput::StrafeRight),
+				(KeyCode::Space, PlayerInput::Ascend),
+				(KeyCode::C, PlayerInput::Descend),
+				(KeyCode::Up, PlayerInput::PitchUp),
+				(KeyCode::Down, PlayerInput::PitchDown),
+				(KeyCode::Q, PlayerInput::RollLeft),
+				(KeyCode::E, PlayerInput::RollRight),
+				(KeyCode::V, PlayerInput::CycleFlightAssist),
+				(KeyCode::F, PlayerInput::Mine),
+				(KeyCode::X, PlayerInput::Fire),
+			]);
+			input_map
+				.insert(pushed(LeftStickY), PlayerInput::Forward)
+				.insert(pulled(LeftStickY), PlayerInput::Backward)
+				.insert(pulled(LeftStickX), PlayerInput::StrafeLeft)
+				.insert(pushed(LeftStickX), PlayerInput::StrafeRight)
+				.insert(pulled(RightStickX), PlayerInput::Left)
+				.insert(pushed(RightStickX), PlayerInput::Right)
+				.insert(pushed(RightStickY), PlayerInput::PitchUp)
+				.insert(pulled(RightStickY), PlayerInput::PitchDown)
+				.insert(GamepadButtonType::RightTrigger2, PlayerInput::Ascend)
+				.insert(GamepadButtonType::LeftTrigger2, PlayerInput::Descend)
+				.insert(GamepadButtonType::LeftTrigger, PlayerInput::RollLeft)
+				.insert(GamepadButtonType::RightTrigger, PlayerInput::RollRight)
+				.insert(GamepadButtonType::North, PlayerInput::CycleFlightAssist)
+				.insert(GamepadButtonType::West, PlayerInput::Mine)
+				.insert(GamepadButtonType::South, PlayerInput::Fire);
+			input_map
+		}
+	}
+
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub enum InputDiff {
+		Pressed(PlayerInput),
+		Released(PlayerInput),
+	}
+
+	/// Sent from clients whenever their [ActionState<PlayerInput>] changes
+	#[derive(Event, Debug, Clone, PartialEq, Serialize, Deserialize)]
+	pub struct PlayerInputDiffs {
+		/// The [GameClock] frame the inputs changed on
+		pub frame: FrameNumber,
+		pub diffs: Vec<InputDiff>,
+	}
+
+	/// Server side buffer of inputs received for frames that haven't been simulated yet.
+	///
+	/// Inputs for frames that were already simulated (late packets) are applied as soon as possible.
+	#[derive(Component, Debug, Default)]
+	pub struct InputBuffer {
+		frames: BTreeMap<FrameNumber, Vec<InputDiff>>,
+	}
+
+	impl InputBuffer {
+		/// Inputs further in the future than this are assumed to come from
+		/// a client whose clock is off, and are applied immediately
+		pub const MAX_BUFFERED_FRAMES: FrameNumber = 32;
+
+		pub fn push(&mut self, diffs: PlayerInputDiffs) {
+			self.frames.entry(diffs.frame).or_default().extend(diffs.diffs);
+		}
+
+		/// Removes all of the inputs that should be applied by `current_frame`, oldest first
+		pub fn drain_until(&mut self, current_frame: FrameNumber) -> Vec<InputDiff> {
+			let mut later = self.frames.split_off(&current_frame.saturating_add(1));
+			let mut too_far = later.split_off(
+				&current_frame
+					.saturating_add(Self::MAX_BUFFERED_FRAMES)
+					.saturating_add(1),
+			);
+			if !too_far.is_empty() {
+				warn!("Received inputs too far in the future, applying them immediately");
+			}
+
+			let mut due = std::mem::replace(&mut self.frames, later);
+			due.append(&mut too_far);
+			due.into_values().flatten().collect()
 		}
 	}
 }
+
+mod systems {
+	use super::*;
+
+	impl InputProcessingPlugin {
+		/// Only the local player is controlled by this instance's keyboard,
+		/// everybody else's [ActionState] is driven by their [PlayerInputDiffs].
+		/// Ships without a [NetworkId] are flown by the server, see [super::super::AiPilot]
+		pub(super) fn remove_remote_input_maps(
+			mut commands: Commands,
+			players: Query<(Entity, Option<&NetworkId>), With<InputMap<PlayerInput>>>,
+			client_id: ClientID,
+		) {
+			let local_id = client_id.get();
+			for (player, id) in players.iter() {
+				match id {
+					Some(id) if Some(id.get_network_id()) == local_id => {}
+					Some(_) => {
+						commands
+							.entity(player)
+							.remove::<InputMap<PlayerInput>>()
+							.insert(InputBuffer::default());
+					}
+					None => {
+						commands.entity(player).remove::<InputMap<PlayerInput>>();
+					}
+				}
+			}
+		}
+
+		pub(super) fn send_input_diffs(
+			players: Query<&ActionState<PlayerInput>, With<InputMap<PlayerInput>>>,
+			mut last_sent: Local<HashSet<PlayerInput>>,
+			mut input_diffs: EventWriter<PlayerInputDiffs>,
+			game_clock: Res<GameClock>,
+		) {
+			let Ok(action_state) = players.get_single() else {
+				return;
+			};
+			let pressed: HashSet<PlayerInput> = action_state.get_pressed().into_iter().collect();
+
+			let diffs: Vec<InputDiff> = pressed
+				.difference(&last_sent)
+				.map(|action| InputDiff::Pressed(*action))
+				.chain(
+					last_sent
+						.difference(&pressed)
+						.map(|action| InputDiff::Released(*action)),
+				)
+				.collect();
+			if diffs.is_empty() {
+				return;
+			}
+
+			input_diffs.send(PlayerInputDiffs {
+				frame: game_clock.frame(),
+				diffs,
+			});
+			*last_sent = pressed;
+		}
+
+		pub(super) fn receive_input_diffs(
+			mut players: Query<(&NetworkId, &mut InputBuffer)>,
+			mut input_diffs: EventReader<FromClient<PlayerInputDiffs>>,
+		) {
+			for FromClient { client_id, event } in input_diffs.read() {
+				match players
+					.iter_mut()
+					.find(|(id, _)| id.get_network_id() == *client_id)
+				{
+					Some((_, mut buffer)) => buffer.push(event.clone()),
+					None => trace!("Dropping inputs from client {} without a player", client_id),
+				}
+			}
+		}
+
+		pub(super) fn apply_buffered_inputs(
+			mut players: Query<(&mut ActionState<PlayerInput>, &mut InputBuffer)>,
+			game_clock: Res<GameClock>,
+		) {
+			for (mut action_state, mut buffer) in players.iter_mut() {
+				for diff in buffer.drain_until(game_clock.frame()) {
+					match diff {
+						InputDiff::Pressed(action) => action_state.press(action),
+						InputDiff::Released(action) => action_state.release(action),
+					}
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn diffs(frame: FrameNumber, action: PlayerInput) -> PlayerInputDiffs {
+		PlayerInputDiffs {
+			frame,
+			diffs: vec![InputDiff::Pressed(action)],
+		}
+	}
+
+	#[test]
+	fn buffers_future_inputs() {
+		let mut buffer = InputBuffer::default();
+		buffer.push(diffs(12, PlayerInput::Forward));
+
+		assert!(buffer.drain_until(10).is_empty());
+		assert_eq!(
+			buffer.drain_until(12),
+			vec![InputDiff::Pressed(PlayerInput::Forward)]
+		);
+		assert!(buffer.drain_until(13).is_empty());
+	}
+
+	#[test]
+	fn applies_late_inputs_in_order() {
+		let mut buffer = InputBuffer::default();
+		buffer.push(diffs(6, PlayerInput::Left));
+		buffer.push(diffs(5, PlayerInput::Forward));
+
+		assert_eq!(
+			buffer.drain_until(10),
+			vec![
+				InputDiff::Pressed(PlayerInput::Forward),
+				InputDiff::Pressed(PlayerInput::Left)
+			]
+		);
+	}
+
+	#[test]
+	fn applies_inputs_too_far_ahead_immediately() {
+		let mut buffer = InputBuffer::default();
+		buffer.push(diffs(10 + InputBuffer::MAX_BUFFERED_FRAMES + 1, PlayerInput::Right));
+
+		assert_eq!(
+			buffer.drain_until(10),
+			vec![InputDiff::Pressed(PlayerInput::Right)]
+		);
+	}
+}
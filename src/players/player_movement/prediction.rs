@@ -0,0 +1,215 @@
+//! Client side prediction of the local player's ship.
+//!
+//! The server is authoritative, so without prediction a client would only see
+//! its own ship move once its input had round-tripped through the server.
+//! Instead the local player's movement is simulated locally, and whenever an
+//! authoritative snapshot arrives it is handed to [bevy_timewarp] which rolls back to
+//! the snapshot's [GameClock] frame and re-simulates up to the present.
+//!
+//! Movement is derived from inputs on both sides, see [super::input_processing],
+//! so only the [Transform] is corrected by the server.
+//!
+//! Any jump between the predicted and corrected [Transform] is smoothed out visually,
+//! see [VisualError].
+
+use std::io::Cursor;
+
+use bevy::transform::TransformSystem;
+use bevy_replicon::{
+	client::client_mapper::ServerEntityMap,
+	replicon_core::replication_rules::{deserialize_component, remove_component, serialize_component},
+};
+
+use super::{components::IntendedVelocity, FlightAssist};
+use crate::prelude::*;
+
+pub use api::*;
+
+pub(super) struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.replicate_predicted::<Transform>()
+			.register_rollback_with_correction_logging::<Transform>()
+			.register_rollback::<LinearVelocity>()
+			.register_rollback::<AngularVelocity>()
+			.register_rollback::<IntendedVelocity>()
+			.register_rollback::<FlightAssist>()
+			.register_type::<Predicted>()
+			.add_systems(First, Self::remove_visual_error)
+			.add_systems(
+				Update,
+				(
+					Self::mark_local_player_predicted.run_if(resource_exists::<RenetClient>()),
+					Self::accumulate_visual_error,
+				),
+			)
+			.add_systems(
+				PostUpdate,
+				Self::apply_visual_error.before(TransformSystem::TransformPropagate),
+			);
+	}
+}
+
+impl ReplicationMarker for Transform {}
+
+mod api {
+	use crate::prelude::*;
+
+	/// Marks the local player's ship on clients, whose movement is predicted
+	/// instead of waiting for the server.
+	#[derive(Component, Reflect, Debug)]
+	pub struct Predicted;
+
+	/// How far the rendered ship is from its simulated [Transform],
+	/// after a rollback corrected the prediction.
+	///
+	/// Only applied to the [Transform] between [PostUpdate] and the next [First],
+	/// so the simulation never sees it.
+	#[derive(Component, Debug)]
+	pub struct VisualError {
+		pub(super) translation: Vec3,
+		pub(super) rotation: Quat,
+		/// Whether the error is currently applied to the [Transform]
+		pub(super) applied: bool,
+	}
+
+	impl Default for VisualError {
+		fn default() -> Self {
+			Self {
+				translation: Vec3::ZERO,
+				rotation: Quat::IDENTITY,
+				applied: false,
+			}
+		}
+	}
+}
+
+#[extension_traits::extension(trait AppPredictionExt)]
+impl &mut App {
+	/// Replicates `T` like [AppReplicationExt::replicate], except that for [Predicted] entities
+	/// the authoritative value is handed to [bevy_timewarp] as a [ServerSnapshot] instead of
+	/// overwriting the predicted value.
+	fn replicate_predicted<T>(self) -> Self
+	where
+		T: TimewarpComponent + Serialize + DeserializeOwned,
+	{
+		self.replicate_with::<T>(
+			serialize_component::<T>,
+			PredictionPlugin::deserialize_predicted::<T>,
+			remove_component::<T>,
+		)
+	}
+}
+
+mod systems {
+	use super::*;
+
+	impl PredictionPlugin {
+		/// How long it takes for a correction to (mostly) fade out
+		const SMOOTHING_TIME: Duration = Duration::from_millis(100);
+
+		/// Corrections further than this are snapped to instead of smoothed,
+		/// e.g. after a respawn
+		const SNAP_DISTANCE: f32 = 10.;
+
+		pub(super) fn deserialize_predicted<T>(
+			entity: &mut EntityWorldMut,
+			entity_map: &mut ServerEntityMap,
+			cursor: &mut Cursor<&[u8]>,
+			replicon_tick: RepliconTick,
+		) -> bincode::Result<()>
+		where
+			T: TimewarpComponent + DeserializeOwned,
+		{
+			let predicted = if entity.contains::<Predicted>() {
+				entity.get::<T>().cloned()
+			} else {
+				None
+			};
+			deserialize_component::<T>(entity, entity_map, cursor, replicon_tick)?;
+
+			let Some(predicted) = predicted else {
+				return Ok(());
+			};
+			// keep predicting, the authoritative value is applied at its frame during the rollback
+			let authoritative = std::mem::replace(&mut *entity.get_mut::<T>().unwrap(), predicted);
+			if let Some(mut snapshots) = entity.get_mut::<ServerSnapshot<T>>() {
+				if let Err(err) = snapshots.insert(replicon_tick.get(), authoritative) {
+					trace!("Dropping authoritative snapshot: {:?}", err);
+				}
+			}
+			Ok(())
+		}
+
+		pub(super) fn mark_local_player_predicted(
+			mut commands: Commands,
+			players: Query<(Entity, &NetworkId), (With<IntendedVelocity>, Without<Predicted>)>,
+			client_id: ClientID,
+		) {
+			let Some(local_id) = client_id.get() else {
+				return;
+			};
+			for (player, _) in players
+				.iter()
+				.filter(|(_, id)| id.get_network_id() == local_id)
+			{
+				debug!("Predicting the local player's movement");
+				commands
+					.entity(player)
+					.insert((Predicted, VisualError::default()));
+			}
+		}
+
+		/// Adds any jump caused by a rollback to the [VisualError], so that the ship
+		/// is rendered where it was before and then eased into its corrected position.
+		pub(super) fn accumulate_visual_error(
+			mut players: Query<
+				(&TimewarpCorrection<Transform>, &mut VisualError),
+				Changed<TimewarpCorrection<Transform>>,
+			>,
+		) {
+			for (correction, mut error) in players.iter_mut() {
+				let before = correction.before;
+				let after = correction.after;
+
+				error.translation += before.translation - after.translation;
+				error.rotation = (before.rotation * after.rotation.inverse() * error.rotation).normalize();
+
+				if error.translation.length() > Self::SNAP_DISTANCE {
+					debug!("Snapping to corrected position instead of smoothing");
+					error.translation = Vec3::ZERO;
+					error.rotation = Quat::IDENTITY;
+				}
+			}
+		}
+
+		pub(super) fn apply_visual_error(
+			mut players: Query<(&mut Transform, &mut VisualError)>,
+			time: Res<Time>,
+		) {
+			let decay = (-time.delta_seconds() / Self::SMOOTHING_TIME.as_secs_f32()).exp();
+			for (mut transform, mut error) in players.iter_mut() {
+				error.translation *= decay;
+				error.rotation = Quat::IDENTITY.slerp(error.rotation, decay);
+
+				transform.translation += error.translation;
+				transform.rotation = error.rotation * transform.rotation;
+				error.applied = true;
+			}
+		}
+
+		/// Undoes [PredictionPlugin::apply_visual_error] before anything else runs this frame
+		pub(super) fn remove_visual_error(mut players: Query<(&mut Transform, &mut VisualError)>) {
+			for (mut transform, mut error) in players.iter_mut() {
+				if !error.applied {
+					continue;
+				}
+				transform.translation -= error.translation;
+				transform.rotation = error.rotation.inverse() * transform.rotation;
+				error.applied = false;
+			}
+		}
+	}
+}
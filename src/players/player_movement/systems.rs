@@ -1,12 +1,16 @@
 use super::{
 	components::{ActualVelocity, IntendedVelocity, ThrusterAxis, ThrusterStrengths},
 	utils::ActualVelocityQuery,
-	PlayerInput, PlayerMovementPlugin, Velocity6DimensionsMut,
+	AiPilot, FlightAssist, PlayerInput, PlayerMovementPlugin, Velocity6DimensionsMut,
 };
 use crate::{
 	players::{player::PlayerBlueprintComponent, thruster_block::Thruster},
 	prelude::*,
 };
+use bevy_xpbd3d_thrusters::{
+	prelude::{OptimalAllocationStrategy, PureStrategy},
+	strategies::{ParentInfo, ThrusterInfo},
+};
 
 impl PlayerMovementPlugin {
 	/// Adds the [ThrusterAxis] component to players.
@@ -17,48 +21,61 @@ impl PlayerMovementPlugin {
 				&Children,
 				&PlayerBlueprintComponent,
 				&CenterOfMass,
+				&InverseMass,
+				&InverseInertia,
 			),
 			Or<(
 				Changed<PlayerBlueprintComponent>,
 				Changed<CenterOfMass>,
+				Changed<InverseMass>,
+				Changed<InverseInertia>,
 				Changed<Children>,
 			)>,
 		>,
 		thrusters: Query<(&Transform, &Thruster)>,
 	) {
-		for (mut player, children, blueprint, center_of_mass) in players.iter_mut() {
+		for (mut player, children, blueprint, center_of_mass, inverse_mass, inverse_inertia) in
+			players.iter_mut()
+		{
 			let block_ids: HashSet<BlockId> = blueprint.derive_thruster_ids().collect();
-			let mut thrusters: HashMap<BlockId, &Transform> = children
+			let thrusters = children
 				.iter()
 				.filter_map(|e| thrusters.get(*e).ok())
 				.filter(|(_, thruster)| block_ids.contains(&thruster.get_block_id()))
-				.map(|(t, id)| (id.get_block_id(), t))
-				.collect();
+				.map(|(t, thruster)| (thruster.get_block_id(), t, thruster.get_strength_factor()));
 
-			let thruster_axis = ThrusterAxis::new(center_of_mass, thrusters.drain());
+			let thruster_axis =
+				ThrusterAxis::new(center_of_mass, inverse_mass, inverse_inertia, thrusters);
 			*player = thruster_axis;
 		}
 	}
 
-	/// Adds the [IntendedVelocity] component to players.
+	/// Adds the [IntendedVelocity] component to players,
+	/// ships flown by an [AiPilot] are steered in [Self::steer_ai_ships] instead.
 	pub(super) fn calculate_intended_velocity(
-		mut players: Query<(&mut IntendedVelocity, &ActionState<PlayerInput>)>,
+		mut players: Query<(&mut IntendedVelocity, &ActionState<PlayerInput>), Without<AiPilot>>,
 	) {
 		for (mut player, inputs) in players.iter_mut() {
 			let mut intended_velocity = IntendedVelocity::default();
 
-			if inputs.pressed(PlayerInput::Forward) {
-				intended_velocity.add_forward(PlayerInput::FORCE_FACTOR);
-			}
-			if inputs.pressed(PlayerInput::Backward) {
-				intended_velocity.add_backward(PlayerInput::FORCE_FACTOR);
-			}
-
-			if inputs.pressed(PlayerInput::Left) {
-				intended_velocity.add_turn_left(PlayerInput::ROTATION_FACTOR);
-			}
-			if inputs.pressed(PlayerInput::Right) {
-				intended_velocity.add_turn_right(PlayerInput::ROTATION_FACTOR);
+			const FORCE: f32 = PlayerInput::FORCE_FACTOR;
+			const ROTATION: f32 = PlayerInput::ROTATION_FACTOR;
+			for action in inputs.get_pressed() {
+				match action {
+					PlayerInput::Forward => intended_velocity.add_forward(FORCE),
+					PlayerInput::Backward => intended_velocity.add_backward(FORCE),
+					PlayerInput::StrafeLeft => intended_velocity.add_left(FORCE),
+					PlayerInput::StrafeRight => intended_velocity.add_right(FORCE),
+					PlayerInput::Ascend => intended_velocity.add_up(FORCE),
+					PlayerInput::Descend => intended_velocity.add_down(FORCE),
+					PlayerInput::Left => intended_velocity.add_turn_left(ROTATION),
+					PlayerInput::Right => intended_velocity.add_turn_right(ROTATION),
+					PlayerInput::PitchUp => intended_velocity.add_tilt_up(ROTATION),
+					PlayerInput::PitchDown => intended_velocity.add_tilt_down(ROTATION),
+					PlayerInput::RollLeft => intended_velocity.add_roll_left(ROTATION),
+					PlayerInput::RollRight => intended_velocity.add_roll_right(ROTATION),
+					PlayerInput::CycleFlightAssist | PlayerInput::Mine | PlayerInput::Fire => {}
+				}
 			}
 
 			*player = intended_velocity;
@@ -75,31 +92,64 @@ impl PlayerMovementPlugin {
 		}
 	}
 
-	/// Calculates [ThrusterStrengths] from [ThrusterAxis],
-	/// [IntendedVelocity], and [ActualVelocity].
+	/// Switches [FlightAssistMode]s, see [PlayerInput::CycleFlightAssist]
+	pub(super) fn cycle_flight_assist(
+		mut players: Query<(&mut FlightAssist, &ActionState<PlayerInput>, &ActualVelocity)>,
+	) {
+		for (mut assist, inputs, actual) in players.iter_mut() {
+			assist.handle_cycle_input(inputs.pressed(PlayerInput::CycleFlightAssist), actual);
+		}
+	}
+
+	/// Calculates [ThrusterStrengths] from [ThrusterAxis] and what the [FlightAssist] commands
+	/// given the [IntendedVelocity] and [ActualVelocity],
+	/// using the [OptimalAllocationStrategy] solver through its [PureStrategy] interface.
 	// #[bevycheck::system]
 	pub(super) fn calculate_thruster_strengths(
 		mut players: Query<
 			(
 				&mut ThrusterStrengths,
+				&mut FlightAssist,
 				&ThrusterAxis,
 				&IntendedVelocity,
 				&ActualVelocity,
 			),
 			With<PlayerBlueprintComponent>,
 		>,
+		time: Res<Time>,
 	) {
-		for (mut player, axis, intended, actual) in players.iter_mut() {
-			let desired_delta = &intended.sub(*actual);
-			let strengths = ThrusterStrengths::new(axis.get_blocks().map(|(id, force_axis)| {
-				let mut dot = force_axis.dot(*desired_delta);
-				const CUTOFF: f32 = 0.3;
-				if dot.abs() < CUTOFF {
-					dot = 0.;
-				}
-				(id, dot)
-			}));
-			*player = strengths;
+		let strategy = OptimalAllocationStrategy::default();
+		// the strategy only reads the force axis, every thruster is the same to it
+		let thruster = bevy_xpbd3d_thrusters::prelude::Thruster::default();
+		for (mut player, mut assist, axis, intended, actual) in players.iter_mut() {
+			let command = assist.command(intended, actual, time.delta_seconds());
+			let blocks: Vec<(BlockId, bevy_xpbd3d_thrusters::prelude::ForceAxis)> = axis
+				.get_blocks()
+				.map(|(id, force_axis)| (id, force_axis.into_vec6().into()))
+				.collect();
+
+			// the flight assist already subtracted the actual velocity
+			let parent = ParentInfo {
+				current_velocity: &default(),
+				intended_velocity: &command.into(),
+			};
+			let statuses = PureStrategy::<BlockId>::calculate(
+				&strategy,
+				blocks
+					.iter()
+					.map(|(id, force_axis)| {
+						(
+							id,
+							ThrusterInfo {
+								thruster: &thruster,
+								force_axis,
+							},
+						)
+					})
+					.collect(),
+				parent,
+			);
+			*player = ThrusterStrengths::new(statuses.into_iter().map(|(id, status)| (*id, status)));
 		}
 	}
 }
@@ -12,10 +12,13 @@ pub(super) struct ActualVelocityQuery {
 }
 
 impl<'w> ActualVelocityQueryItem<'w> {
+	/// Converts the global velocities into the ship's local frame,
+	/// so that they can be compared against the [super::components::IntendedVelocity]
 	pub fn into_actual_velocity(self) -> ActualVelocity {
-		let lin = self.rotation.rotation.mul_vec3(self.lin.0);
-		let ang = self.ang.0;
-		ActualVelocity::from_vec3(lin, ang)
+		let to_local = self.rotation.rotation.inverse();
+		let lin = to_local.mul_vec3(self.lin.0);
+		let ang = to_local.mul_vec3(self.ang.0);
+		ActualVelocity::from_local(lin, ang)
 	}
 }
 
@@ -111,6 +114,18 @@ pub(super) trait Velocity6Dimensions: Default + std::fmt::Debug + Copy {
 	fn roll_left(&self) -> f32 {
 		self.angular_roll_left()
 	}
+
+	/// For use with [bevy_xpbd3d_thrusters::strategies]
+	fn into_vec6(self) -> bevy_xpbd3d_thrusters::prelude::Vec6 {
+		bevy_xpbd3d_thrusters::prelude::Vec6 {
+			forward: self.forward(),
+			right: self.right(),
+			upwards: self.up(),
+			turn_right: self.turn_right(),
+			pitch_up: self.tilt_up(),
+			roll_right: self.roll_right(),
+		}
+	}
 }
 
 pub(super) trait Velocity6DimensionsMut: Velocity6Dimensions {
@@ -230,6 +245,23 @@ pub(super) trait Velocity6DimensionsMut: Velocity6Dimensions {
 		self.add_leftward(amount);
 	}
 
+	/// Velocity
+	fn add_upward(&mut self, amount: f32) {
+		*self.up_mut() += amount;
+	}
+	/// Velocity
+	fn add_up(&mut self, amount: f32) {
+		self.add_upward(amount);
+	}
+	/// Velocity
+	fn add_downward(&mut self, amount: f32) {
+		self.add_upward(-amount);
+	}
+	/// Velocity
+	fn add_down(&mut self, amount: f32) {
+		self.add_downward(amount);
+	}
+
 	fn add_turn_right(&mut self, amount: f32) {
 		*self.turn_right_mut() += amount;
 	}
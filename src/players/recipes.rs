@@ -0,0 +1,156 @@
+//! What every block is fabricated from, see [RecipeBook].
+//!
+//! The recipes are data, so servers can change them in their config
+//! without changing any code, see [crate::DedicatedServerConfig::recipes].
+
+use crate::{players::ship_editor::EditorBlock, prelude::*};
+
+/// Turns resources from a player's inventory into a block, see [crate::players::fabricator_block]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+	pub output: EditorBlock,
+	/// Every [ResourceKind] at most once
+	#[serde(deserialize_with = "unique_ingredients")]
+	pub ingredients: Vec<(ResourceKind, u32)>,
+	/// How long one fabricator takes to make the output
+	pub seconds: f32,
+}
+
+fn unique_ingredients<'de, D: serde::Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Vec<(ResourceKind, u32)>, D::Error> {
+	let ingredients = Vec::<(ResourceKind, u32)>::deserialize(deserializer)?;
+	for (index, (kind, _)) in ingredients.iter().enumerate() {
+		if ingredients[..index].iter().any(|(other, _)| other == kind) {
+			return Err(serde::de::Error::custom(format!(
+				"{:?} is listed more than once",
+				kind
+			)));
+		}
+	}
+	Ok(ingredients)
+}
+
+/// Every [Recipe] the server knows about, at most one per [EditorBlock].
+///
+/// Blocks without a recipe can't be fabricated.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecipeBook(#[serde(deserialize_with = "unique_outputs")] Vec<Recipe>);
+
+fn unique_outputs<'de, D: serde::Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Vec<Recipe>, D::Error> {
+	let recipes = Vec::<Recipe>::deserialize(deserializer)?;
+	for (index, recipe) in recipes.iter().enumerate() {
+		if recipes[..index]
+			.iter()
+			.any(|other| other.output == recipe.output)
+		{
+			return Err(serde::de::Error::custom(format!(
+				"{:?} has more than one recipe",
+				recipe.output
+			)));
+		}
+	}
+	Ok(recipes)
+}
+
+impl Default for RecipeBook {
+	fn default() -> Self {
+		use EditorBlock as B;
+		use ResourceKind as R;
+
+		let recipe = |output, ingredients: &[(ResourceKind, u32)], seconds| Recipe {
+			output,
+			ingredients: ingredients.to_vec(),
+			seconds,
+		};
+		Self(vec![
+			// aluminium is refined from silicates
+			recipe(B::Structure, &[(R::Silicates, 2)], 1.),
+			recipe(B::SteelStructure, &[(R::Iron, 3)], 2.),
+			recipe(
+				B::CompositeStructure,
+				&[(R::Silicates, 3), (R::Iron, 2)],
+				4.,
+			),
+			recipe(B::Thruster, &[(R::Iron, 2), (R::Regolith, 1)], 2.),
+			recipe(B::HeavyThruster, &[(R::Iron, 6), (R::Regolith, 2)], 4.),
+			recipe(
+				B::FusionThruster,
+				&[(R::Iron, 4), (R::Silicates, 4), (R::Regolith, 4)],
+				8.,
+			),
+			recipe(B::MiningTool, &[(R::Iron, 3)], 3.),
+			recipe(B::Fabricator, &[(R::Iron, 4), (R::Silicates, 2)], 5.),
+			recipe(B::Weapon, &[(R::Iron, 5), (R::Regolith, 2)], 4.),
+		])
+	}
+}
+
+impl RecipeBook {
+	pub fn get(&self, output: EditorBlock) -> Option<&Recipe> {
+		self.0.iter().find(|recipe| recipe.output == output)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{players::ship_editor::EditorBlock, prelude::*, DedicatedServerConfig};
+
+	#[test]
+	fn recipes_are_configurable() {
+		let config = DedicatedServerConfig::from_toml_str(
+			r#"
+			[[recipes]]
+			output = "Structure"
+			ingredients = [["Regolith", 5]]
+			seconds = 0.5
+			"#,
+		)
+		.unwrap();
+
+		let recipe = config.recipes.get(EditorBlock::Structure).unwrap();
+		assert_eq!(recipe.ingredients, vec![(ResourceKind::Regolith, 5)]);
+		assert_eq!(recipe.seconds, 0.5);
+		// replaces the default recipes
+		assert_eq!(config.recipes.get(EditorBlock::Thruster), None);
+		assert!(DedicatedServerConfig::default()
+			.recipes
+			.get(EditorBlock::Thruster)
+			.is_some());
+	}
+
+	#[test]
+	fn rejects_duplicate_ingredients() {
+		assert!(DedicatedServerConfig::from_toml_str(
+			r#"
+			[[recipes]]
+			output = "Structure"
+			ingredients = [["Iron", 1], ["Iron", 2]]
+			seconds = 1.0
+			"#,
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn rejects_duplicate_outputs() {
+		assert!(DedicatedServerConfig::from_toml_str(
+			r#"
+			[[recipes]]
+			output = "Structure"
+			ingredients = [["Iron", 1]]
+			seconds = 1.0
+
+			[[recipes]]
+			output = "Structure"
+			ingredients = [["Regolith", 2]]
+			seconds = 1.0
+			"#,
+		)
+		.is_err());
+	}
+}
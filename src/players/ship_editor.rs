@@ -0,0 +1,443 @@
+//! Lets players build their own ships, see [BuildMode].
+//!
+//! Clients never touch their [PlayerBlueprintComponent] directly,
+//! they send [ShipEditRequest]s which the server validates and applies.
+//! The changed blueprint is then replicated and re-expanded everywhere.
+//!
+//! Edits made while offline are kept in the [OfflineJournal],
+//! and sent to the server once the game goes online.
+
+use crate::{players::player::PlayerBlueprintComponent, prelude::*};
+
+pub use api::*;
+
+pub(super) struct ShipEditorPlugin;
+
+impl Plugin for ShipEditorPlugin {
+	fn build(&self, app: &mut App) {
+		app
+			.add_client_event::<ShipEditRequest>(EventType::Ordered)
+			.add_server_event::<ShipEditRejected>(EventType::Ordered)
+			.init_resource::<OfflineJournal>()
+			.add_systems(
+				Update,
+				(
+					Self::receive_ship_edits
+						.run_if(resource_exists::<RenetServer>().or_else(resource_exists::<OfflineSession>())),
+					Self::receive_ship_edit_rejections.in_set(Client),
+					Self::replay_offline_journal
+						.in_set(Client)
+						.run_if(resource_exists::<RenetClient>()),
+				),
+			);
+
+		// building needs picking, which the dedicated server doesn't have
+		if app.is_plugin_added::<crate::cameras::CameraPlugin>() {
+			app
+				.add_plugins(InputManagerPlugin::<ShipEditorInput>::default())
+				.init_resource::<ActionState<ShipEditorInput>>()
+				.insert_resource(ShipEditorInput::default_input_map())
+				.add_systems(
+					Update,
+					(
+						Self::handle_build_mode_inputs,
+						Self::handle_block_clicks.run_if(resource_exists::<BuildMode>()),
+					)
+						.chain()
+						.run_if(in_state(GlobalGameStates::InGame))
+						.run_if(NetcodeConfig::not_headless()),
+				)
+				.add_systems(OnExit(GlobalGameStates::InGame), Self::exit_build_mode);
+		}
+	}
+}
+
+mod api {
+	use crate::{blocks::manual_builder::Facing, players::thruster_block::ThrusterTier, prelude::*};
+
+	/// Controls for the ship editor, which are global (not per player)
+	#[derive(ActionLike, PartialEq, Eq, Clone, Copy, Hash, Debug, Reflect)]
+	pub enum ShipEditorInput {
+		/// Enters or leaves [BuildMode]
+		ToggleBuildMode,
+		/// Selects the next [EditorBlock]
+		CycleBlock,
+		/// Rotates the next placed block
+		RotateBlock,
+		/// Fabricates one of the selected [EditorBlock], see [crate::players::fabricator_block]
+		FabricateBlock,
+	}
+
+	impl ShipEditorInput {
+		pub(super) fn default_input_map() -> InputMap<Self> {
+			InputMap::new([
+				(KeyCode::B, ShipEditorInput::ToggleBuildMode),
+				(KeyCode::Tab, ShipEditorInput::CycleBlock),
+				(KeyCode::R, ShipEditorInput::RotateBlock),
+				(KeyCode::G, ShipEditorInput::FabricateBlock),
+			])
+		}
+	}
+
+	/// The kinds of blocks players can place, each tier of a block is its own kind.
+	///
+	/// Apart from the camera, blocks are fabricated into the player's [crate::players::inventory::Inventory]
+	/// before they can be placed, see [crate::players::fabricator_block]
+	#[derive(
+		Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize, EnumIter,
+	)]
+	pub enum EditorBlock {
+		/// Aluminium, see [StructureMaterial]
+		#[default]
+		Structure,
+		SteelStructure,
+		CompositeStructure,
+		/// See [ThrusterTier]
+		Thruster,
+		HeavyThruster,
+		FusionThruster,
+		/// Mines the terrain it faces, see [crate::players::player_movement::PlayerInput::Mine]
+		MiningTool,
+		/// Fabricates blocks out of resources, see [crate::players::fabricator_block]
+		Fabricator,
+		/// Fires projectiles the way it faces, see [crate::players::player_movement::PlayerInput::Fire]
+		Weapon,
+		/// Moves the ship's primary camera, since ships only have one
+		Camera,
+	}
+
+	impl EditorBlock {
+		/// Whether placing this block takes it out of the player's inventory
+		pub fn is_fabricated(&self) -> bool {
+			*self != Self::Camera
+		}
+	}
+
+	impl From<StructureMaterial> for EditorBlock {
+		fn from(material: StructureMaterial) -> Self {
+			match material {
+				StructureMaterial::Aluminum => Self::Structure,
+				StructureMaterial::Steel => Self::SteelStructure,
+				StructureMaterial::Composite => Self::CompositeStructure,
+			}
+		}
+	}
+
+	impl From<ThrusterTier> for EditorBlock {
+		fn from(tier: ThrusterTier) -> Self {
+			match tier {
+				ThrusterTier::Standard => Self::Thruster,
+				ThrusterTier::Heavy => Self::HeavyThruster,
+				ThrusterTier::Fusion => Self::FusionThruster,
+			}
+		}
+	}
+
+	/// Present on clients while the local player is building their ship.
+	///
+	/// Left clicking a face of the ship places the selected block there,
+	/// right clicking a block removes it.
+	#[derive(Resource, Debug, Clone, Copy)]
+	pub struct BuildMode {
+		pub block: EditorBlock,
+		pub facing: Facing,
+	}
+
+	impl Default for BuildMode {
+		fn default() -> Self {
+			Self {
+				block: EditorBlock::default(),
+				facing: Facing::Backwards,
+			}
+		}
+	}
+
+	/// A change to a ship, relative to the ship's center
+	#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+	pub enum ShipEdit {
+		Place {
+			location: manual_builder::RelativePixel,
+			block: EditorBlock,
+			facing: Facing,
+		},
+		Remove {
+			location: manual_builder::RelativePixel,
+		},
+	}
+
+	/// Sent from clients to edit their own ship
+	#[derive(Event, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+	pub struct ShipEditRequest(pub ShipEdit);
+
+	/// Why the server refused a [ShipEdit]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub enum ShipEditRejection {
+		/// There is already a block there
+		Occupied,
+		/// New blocks must share a face with an existing block,
+		/// and removing a block can't leave others floating
+		Disconnected,
+		/// Too far from the center of the ship, see [super::PlayerBlueprintComponent::MAX_EXTENT]
+		OutOfBounds,
+		/// See [super::PlayerBlueprintComponent::MAX_BLOCKS]
+		TooManyBlocks,
+		/// There is no block there to remove
+		NothingThere,
+		/// Ships always have a camera, place it somewhere else instead
+		RemovesCamera,
+		/// Ships need at least one structure block
+		RemovesLastStructure,
+		/// The block has to be fabricated into the player's inventory first
+		NotInInventory,
+	}
+
+	impl std::fmt::Display for ShipEditRejection {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				Self::Occupied => write!(f, "There is already a block there"),
+				Self::Disconnected => write!(f, "Every block must stay attached to the ship"),
+				Self::OutOfBounds => write!(f, "That is too far from the center of the ship"),
+				Self::TooManyBlocks => write!(f, "The ship has too many blocks"),
+				Self::NothingThere => write!(f, "There is no block there"),
+				Self::RemovesCamera => write!(f, "The camera can only be moved, not removed"),
+				Self::RemovesLastStructure => write!(f, "Ships need at least one structure block"),
+				Self::NotInInventory => write!(f, "That block has to be fabricated first"),
+			}
+		}
+	}
+
+	/// Edits the local player made to their ship while offline, see [NetcodeConfig::Offline].
+	///
+	/// Once online they are replayed to the server, which may still reject some of them,
+	/// e.g. if the player's ship on the server is different to the one they edited offline.
+	#[derive(Resource, Debug, Default)]
+	pub struct OfflineJournal(pub(crate) Vec<ShipEdit>);
+
+	/// Sent to a client whose [ShipEditRequest] was refused
+	#[derive(Event, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+	pub struct ShipEditRejected {
+		pub edit: ShipEdit,
+		pub reason: ShipEditRejection,
+	}
+}
+
+mod systems {
+	use strum::IntoEnumIterator;
+
+	use crate::{
+		blocks::manual_builder::RelativePixel,
+		players::{
+			fabricator_block::FabricationRequest,
+			inventory::Inventory,
+			thruster_block::{Thruster, ThrusterBlockBlueprint},
+		},
+		prelude::*,
+	};
+
+	use super::*;
+
+	impl ShipEditorPlugin {
+		pub(super) fn handle_build_mode_inputs(
+			inputs: Res<ActionState<ShipEditorInput>>,
+			build_mode: Option<ResMut<BuildMode>>,
+			mut commands: Commands,
+			mut fabrications: EventWriter<FabricationRequest>,
+		) {
+			if inputs.just_pressed(ShipEditorInput::ToggleBuildMode) {
+				match build_mode {
+					Some(_) => {
+						info!("Leaving build mode");
+						commands.remove_resource::<BuildMode>();
+					}
+					None => {
+						info!("Entering build mode");
+						commands.init_resource::<BuildMode>();
+					}
+				}
+				return;
+			}
+
+			let Some(mut build_mode) = build_mode else {
+				return;
+			};
+			if inputs.just_pressed(ShipEditorInput::CycleBlock) {
+				build_mode.block = next(build_mode.block);
+				info!("Placing {:?} blocks", build_mode.block);
+			}
+			if inputs.just_pressed(ShipEditorInput::RotateBlock) {
+				build_mode.facing = next(build_mode.facing);
+				info!("Placing blocks facing {:?}", build_mode.facing);
+			}
+			if inputs.just_pressed(ShipEditorInput::FabricateBlock) {
+				debug!("Requesting a {:?} block to be fabricated", build_mode.block);
+				fabrications.send(FabricationRequest {
+					block: build_mode.block,
+				});
+			}
+		}
+
+		pub(super) fn exit_build_mode(mut commands: Commands) {
+			commands.remove_resource::<BuildMode>();
+		}
+
+		/// Turns clicks on the local player's blocks into [ShipEditRequest]s
+		pub(super) fn handle_block_clicks(
+			mut clicks: EventReader<Pointer<Click>>,
+			blocks: Query<(&Transform, &Parent, Has<Thruster>)>,
+			players: Query<(&NetworkId, &GlobalTransform), With<PlayerBlueprintComponent>>,
+			build_mode: Res<BuildMode>,
+			local_id: ClientID,
+			mut edits: EventWriter<ShipEditRequest>,
+		) {
+			for click in clicks.read() {
+				let Ok((transform, parent, is_thruster)) = blocks.get(click.target) else {
+					continue;
+				};
+				let Ok((player, player_transform)) = players.get(parent.get()) else {
+					continue;
+				};
+				if local_id.get() != Some(player.get_network_id()) {
+					continue;
+				}
+
+				let clicked = if is_thruster {
+					BlockBlueprint::<ThrusterBlockBlueprint>::location_of(transform)
+				} else {
+					RelativePixel::from_world_offset(transform.translation)
+				};
+				let edit = match click.event.button {
+					PointerButton::Primary => {
+						let Some(normal) = click.event.hit.normal else {
+							continue;
+						};
+						// the hit normal is global, but pixels are relative to the player
+						let (_, rotation, _) = player_transform.to_scale_rotation_translation();
+						let offset = snap_to_axis(rotation.inverse().mul_vec3(normal));
+						ShipEdit::Place {
+							location: RelativePixel(clicked.0 + offset),
+							block: build_mode.block,
+							facing: build_mode.facing,
+						}
+					}
+					PointerButton::Secondary => ShipEdit::Remove { location: clicked },
+					PointerButton::Middle => continue,
+				};
+				debug!("Requesting {:?}", edit);
+				edits.send(ShipEditRequest(edit));
+			}
+		}
+
+		/// Validates and applies edits, see [PlayerBlueprintComponent::try_edit].
+		/// Placed blocks are taken out of the player's [Inventory], removed ones are put back
+		pub(super) fn receive_ship_edits(
+			mut players: Query<(&NetworkId, &mut PlayerBlueprintComponent, &mut Inventory)>,
+			mut requests: EventReader<FromClient<ShipEditRequest>>,
+			mut rejections: EventWriter<ToClients<ShipEditRejected>>,
+			config: Res<NetcodeConfig>,
+			mut journal: ResMut<OfflineJournal>,
+		) {
+			for FromClient {
+				client_id,
+				event: ShipEditRequest(edit),
+			} in requests.read()
+			{
+				let Some((_, mut blueprint, mut inventory)) = players
+					.iter_mut()
+					.find(|(id, _, _)| id.get_network_id() == *client_id)
+				else {
+					trace!("Dropping ship edit from client {} without a player", client_id);
+					continue;
+				};
+
+				// only mutably deref when the edit is valid, so the blueprint isn't re-expanded needlessly
+				let mut edited = blueprint.clone();
+				let result = match *edit {
+					ShipEdit::Place { block, .. }
+						if block.is_fabricated() && inventory.blocks(block) == 0 =>
+					{
+						Err(ShipEditRejection::NotInInventory)
+					}
+					ShipEdit::Place { block, .. } => edited.try_edit(*edit).and_then(|()| {
+						if block.is_fabricated() {
+							inventory
+								.try_remove_block(block)
+								.map_err(|_| ShipEditRejection::NotInInventory)?;
+						}
+						Ok(())
+					}),
+					ShipEdit::Remove { location } => {
+						let removed = blueprint.block_at(location);
+						edited.try_edit(*edit).map(|()| {
+							if let Some(block) = removed.filter(EditorBlock::is_fabricated) {
+								// lost if it doesn't fit anymore
+								inventory.try_add_block(block).ok();
+							}
+						})
+					}
+				};
+				match result {
+					Ok(()) => {
+						*blueprint = edited;
+						if config.is_offline() && *client_id == SERVER_ID {
+							journal.0.push(*edit);
+						}
+					}
+					Err(reason) => {
+						debug!("Rejecting {:?} from client {}: {}", edit, client_id, reason);
+						rejections.send(ToClients {
+							mode: SendMode::Direct(*client_id),
+							event: ShipEditRejected {
+								edit: *edit,
+								reason,
+							},
+						});
+					}
+				}
+			}
+		}
+
+		/// Once the local player's ship has been replicated from the server after going online,
+		/// requests every edit that was made offline
+		pub(super) fn replay_offline_journal(
+			mut journal: ResMut<OfflineJournal>,
+			players: Query<&NetworkId, With<PlayerBlueprintComponent>>,
+			local_id: ClientID,
+			mut edits: EventWriter<ShipEditRequest>,
+		) {
+			if journal.0.is_empty()
+				|| !players
+					.iter()
+					.any(|player| local_id.get() == Some(player.get_network_id()))
+			{
+				return;
+			}
+			info!("Replaying {} ship edits made offline", journal.0.len());
+			edits.send_batch(journal.0.drain(..).map(ShipEditRequest));
+		}
+
+		pub(super) fn receive_ship_edit_rejections(mut rejections: EventReader<ShipEditRejected>) {
+			for ShipEditRejected { edit, reason } in rejections.read() {
+				warn!("Couldn't edit ship ({:?}): {}", edit, reason);
+			}
+		}
+	}
+
+	/// Which neighbouring pixel a face with the (player relative) `normal` points to,
+	/// by keeping only the largest component of the normal
+	fn snap_to_axis(normal: Vec3) -> IVec3 {
+		let abs = normal.abs();
+		if abs.x >= abs.y && abs.x >= abs.z {
+			IVec3::X * normal.x.signum() as i32
+		} else if abs.y >= abs.z {
+			IVec3::Y * normal.y.signum() as i32
+		} else {
+			IVec3::Z * normal.z.signum() as i32
+		}
+	}
+
+	fn next<T: IntoEnumIterator + PartialEq + Copy>(current: T) -> T {
+		let mut all = T::iter().cycle();
+		all.find(|item| *item == current);
+		all.next().unwrap_or(current)
+	}
+}
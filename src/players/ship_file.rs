@@ -0,0 +1,250 @@
+//! Saving and loading ships to and from versioned RON or JSON files,
+//! so that ship designs can be checked into a repository.
+//!
+//! A ship file wraps a [PlayerBlueprintComponent] with the version of the format it was written with:
+//! ```ron
+//! (
+//! 	version: 1,
+//! 	ship: (
+//! 		structure_children: [ ... ],
+//! 		thruster_children: [ ... ],
+//! 		mining_tool_children: [ ... ],
+//! 		fabricator_children: [ ... ],
+//! 		weapon_children: [ ... ],
+//! 		primary_camera: ( ... ),
+//! 	),
+//! )
+//! ```
+
+use std::path::Path;
+
+use crate::{players::player::PlayerBlueprintComponent, prelude::*};
+
+use super::ship_editor::ShipEditRejection;
+
+/// The version of the ship file format written by [save_ship].
+///
+/// Bump this whenever the serialized [PlayerBlueprintComponent] changes in a way that
+/// older files can't be read anymore, and add a migration from the previous version to [migrate].
+pub const SHIP_FILE_VERSION: u32 = 1;
+
+/// Chosen from the extension of a ship file, `.ron` or `.json`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShipFileFormat {
+	Ron,
+	Json,
+}
+
+impl ShipFileFormat {
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ShipFileError> {
+		let path = path.as_ref();
+		match path.extension().and_then(|extension| extension.to_str()) {
+			Some("ron") => Ok(Self::Ron),
+			Some("json") => Ok(Self::Json),
+			_ => Err(ShipFileError::UnknownFormat(path.to_owned())),
+		}
+	}
+
+	fn deserialize<T: DeserializeOwned>(self, contents: &str) -> Result<T, ShipFileError> {
+		match self {
+			Self::Ron => Ok(ron::from_str(contents)?),
+			Self::Json => Ok(serde_json::from_str(contents)?),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum ShipFileError {
+	Io(std::io::Error),
+	UnknownFormat(std::path::PathBuf),
+	Ron(ron::error::SpannedError),
+	RonSerialize(ron::Error),
+	Json(serde_json::Error),
+	/// The file was written by a newer (or unknown) version of the game, see [migrate]
+	UnsupportedVersion(u32),
+	/// The file parsed, but the ship itself breaks the rules of the ship editor
+	InvalidShip(ShipEditRejection),
+}
+
+impl std::fmt::Display for ShipFileError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io(err) => write!(f, "Couldn't access ship file: {}", err),
+			Self::UnknownFormat(path) => write!(
+				f,
+				"Couldn't tell the format of {:?}, use a .ron or .json extension",
+				path
+			),
+			Self::Ron(err) => write!(f, "Invalid RON: {}", err),
+			Self::RonSerialize(err) => write!(f, "Couldn't write RON: {}", err),
+			Self::Json(err) => write!(f, "Invalid JSON: {}", err),
+			Self::UnsupportedVersion(version) => write!(
+				f,
+				"Ship file version {} is not supported, the latest version is {}",
+				version, SHIP_FILE_VERSION
+			),
+			Self::InvalidShip(reason) => write!(f, "Invalid ship: {}", reason),
+		}
+	}
+}
+
+impl std::error::Error for ShipFileError {}
+
+impl From<std::io::Error> for ShipFileError {
+	fn from(err: std::io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+impl From<ron::error::SpannedError> for ShipFileError {
+	fn from(err: ron::error::SpannedError) -> Self {
+		Self::Ron(err)
+	}
+}
+
+impl From<ron::Error> for ShipFileError {
+	fn from(err: ron::Error) -> Self {
+		Self::RonSerialize(err)
+	}
+}
+
+impl From<serde_json::Error> for ShipFileError {
+	fn from(err: serde_json::Error) -> Self {
+		Self::Json(err)
+	}
+}
+
+impl From<ShipEditRejection> for ShipFileError {
+	fn from(reason: ShipEditRejection) -> Self {
+		Self::InvalidShip(reason)
+	}
+}
+
+/// Only the version, read first to decide how to read the rest of the file
+#[derive(Deserialize)]
+struct VersionHeader {
+	version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ShipFile {
+	version: u32,
+	ship: PlayerBlueprintComponent,
+}
+
+/// Reads a ship from the `contents` of a ship file, migrating it from older versions
+/// and checking it with [PlayerBlueprintComponent::validate]
+pub fn parse_ship(
+	contents: &str,
+	format: ShipFileFormat,
+) -> Result<PlayerBlueprintComponent, ShipFileError> {
+	let VersionHeader { version } = format.deserialize(contents)?;
+	let ship = migrate(version, contents, format)?;
+	ship.validate()?;
+	Ok(ship)
+}
+
+/// Turns a file of any supported `version` into the current [PlayerBlueprintComponent]
+fn migrate(
+	version: u32,
+	contents: &str,
+	format: ShipFileFormat,
+) -> Result<PlayerBlueprintComponent, ShipFileError> {
+	match version {
+		SHIP_FILE_VERSION => Ok(format.deserialize::<ShipFile>(contents)?.ship),
+		// when the format changes, keep the previous `ShipFile` around as e.g. `v1::ShipFile`, and:
+		// 1 => Ok(format.deserialize::<v1::ShipFile>(contents)?.into_current()),
+		version => Err(ShipFileError::UnsupportedVersion(version)),
+	}
+}
+
+pub fn ship_to_string(
+	ship: &PlayerBlueprintComponent,
+	format: ShipFileFormat,
+) -> Result<String, ShipFileError> {
+	let file = ShipFile {
+		version: SHIP_FILE_VERSION,
+		ship: ship.clone(),
+	};
+	match format {
+		ShipFileFormat::Ron => Ok(ron::ser::to_string_pretty(
+			&file,
+			ron::ser::PrettyConfig::default(),
+		)?),
+		ShipFileFormat::Json => Ok(serde_json::to_string_pretty(&file)?),
+	}
+}
+
+/// Reads a ship file, in the format given by its extension
+pub fn load_ship(path: impl AsRef<Path>) -> Result<PlayerBlueprintComponent, ShipFileError> {
+	let format = ShipFileFormat::from_path(&path)?;
+	let contents = std::fs::read_to_string(path)?;
+	parse_ship(&contents, format)
+}
+
+/// Writes a ship file, in the format given by its extension
+pub fn save_ship(
+	ship: &PlayerBlueprintComponent,
+	path: impl AsRef<Path>,
+) -> Result<(), ShipFileError> {
+	let format = ShipFileFormat::from_path(&path)?;
+	std::fs::write(path, ship_to_string(ship, format)?)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::players::player::PlayerBlueprintBundle;
+
+	fn default_ship() -> PlayerBlueprintComponent {
+		(*PlayerBlueprintBundle::new(ClientId::from_raw(1), Transform::default())).clone()
+	}
+
+	#[test]
+	fn round_trips() {
+		let ship = default_ship();
+		for format in [ShipFileFormat::Ron, ShipFileFormat::Json] {
+			let contents = ship_to_string(&ship, format).unwrap();
+			assert_eq!(parse_ship(&contents, format).unwrap(), ship);
+		}
+	}
+
+	#[test]
+	fn rejects_unsupported_versions() {
+		let contents = ship_to_string(&default_ship(), ShipFileFormat::Json).unwrap();
+		for version in [0, 999] {
+			let contents = contents.replacen(
+				&format!("\"version\": {}", SHIP_FILE_VERSION),
+				&format!("\"version\": {}", version),
+				1,
+			);
+			assert!(matches!(
+				parse_ship(&contents, ShipFileFormat::Json),
+				Err(ShipFileError::UnsupportedVersion(v)) if v == version
+			));
+		}
+	}
+
+	#[test]
+	fn current_version_migrates_to_itself() {
+		let ship = default_ship();
+		for format in [ShipFileFormat::Ron, ShipFileFormat::Json] {
+			let contents = ship_to_string(&ship, format).unwrap();
+			assert_eq!(migrate(SHIP_FILE_VERSION, &contents, format).unwrap(), ship);
+		}
+	}
+
+	#[test]
+	fn format_from_extension() {
+		assert_eq!(
+			ShipFileFormat::from_path("ships/default.ron").unwrap(),
+			ShipFileFormat::Ron
+		);
+		assert_eq!(
+			ShipFileFormat::from_path("default.json").unwrap(),
+			ShipFileFormat::Json
+		);
+		assert!(ShipFileFormat::from_path("default.toml").is_err());
+	}
+}
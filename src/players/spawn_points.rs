@@ -14,10 +14,8 @@ impl Plugin for SpawnPointsPlugin {
 			.register_type::<blueprint::SpawnPointBlueprintComponent>()
 			.add_systems(Startup, Self::load_default_materials)
 			.add_systems(PostProcessCollisions, Self::filter_non_occupied_collisions)
-			.add_systems(
-				Blueprints,
-				blueprint::SpawnPointBlueprintBundle::expand_system.in_set(BlueprintExpansion::SpawnPoints),
-			)
+			.register_blueprint::<blueprint::SpawnPointBlueprintComponent>()
+			.persist::<blueprint::SpawnPointBlueprintBundle>()
 			.add_systems(
 				WorldCreation,
 				Self::creation_spawn_points.in_set(WorldCreationSet::SpawnPoints),
@@ -47,6 +45,7 @@ mod api {
 
 	impl AvailableSpawnPoints<'_, '_> {
 		/// Returns a valid spawn location, handling side effects.
+		/// If `player_occupying` already occupies a spawn point, that one is returned again.
 		///
 		/// Maybe: Handle spawning a new spawn location in the future?
 		pub fn try_get_spawn_location(&mut self, player_occupying: ClientId) -> Option<Transform> {
@@ -57,6 +56,14 @@ mod api {
 				);
 			}
 
+			if let Some((_, transform)) = self
+				.spawn_points
+				.iter()
+				.find(|(sp, _)| sp.get_occupation() == Some(player_occupying))
+			{
+				return Some(transform.with_scale(Vec3::splat(1.0)));
+			}
+
 			let mut available_points = self
 				.spawn_points
 				.iter_mut()
@@ -70,6 +77,16 @@ mod api {
 
 			Some(transform.with_scale(Vec3::splat(1.0)))
 		}
+
+		/// Frees any spawn point occupied by `player`, so other players can spawn there.
+		/// The change is replicated to all clients.
+		pub fn release_spawn_location(&mut self, player: ClientId) {
+			for (mut spawn_point, _) in self.spawn_points.iter_mut() {
+				if spawn_point.get_occupation() == Some(player) {
+					spawn_point.clear_occupation();
+				}
+			}
+		}
 	}
 }
 
@@ -228,7 +245,7 @@ mod blueprint {
 		pub occupation: Option<u64>,
 	}
 
-	#[derive(Bundle, Deref)]
+	#[derive(Bundle, Deref, Serialize, Deserialize)]
 	pub struct SpawnPointBlueprintBundle {
 		/// synced
 		transform: Transform,
@@ -251,6 +268,17 @@ mod blueprint {
 		pub const DEFAULT_SIZE: f32 = 3.0;
 	}
 
+	impl PersistentBundle for SpawnPointBlueprintBundle {
+		const TABLE: &'static str = "spawn_point";
+
+		fn from_entity(entity: EntityRef) -> Option<Self> {
+			Some(Self {
+				transform: *entity.get::<Transform>()?,
+				blueprint: entity.get::<SpawnPointBlueprintComponent>()?.clone(),
+			})
+		}
+	}
+
 	// impl Default for SpawnPointBlueprint {
 	// 	fn default() -> Self {
 	// 		Self {
@@ -322,9 +350,9 @@ mod bundle {
 
 	impl Blueprint for SpawnPointBlueprintComponent {
 		type Bundle = SpawnPointBundle;
-		type StampSystemParam<'w, 's> = MMA<'w>;
+		type StampSystemParam = MMA<'static>;
 
-		fn stamp(&self, mma: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
 			let SpawnPointBlueprintComponent { .. } = self;
 
 			SpawnPointBundle {
@@ -346,8 +374,6 @@ mod bundle {
 
 	impl NetworkedBlueprintBundle for SpawnPointBlueprintBundle {
 		type NetworkedBlueprintComponent = SpawnPointBlueprintComponent;
-
-		type SpawnSystemParam = ();
 	}
 }
 
@@ -380,5 +406,9 @@ mod components {
 		pub(super) fn set_occupation(&mut self, id: ClientId) {
 			self.occupation = Some(id.raw());
 		}
+
+		pub(super) fn clear_occupation(&mut self) {
+			self.occupation = None;
+		}
 	}
 }
@@ -1,6 +1,6 @@
 use crate::prelude::*;
 
-pub use blueprint::ThrusterBlockBlueprint;
+pub use blueprint::{ThrusterBlockBlueprint, ThrusterTier};
 
 use super::PlayerMovementSet;
 
@@ -11,10 +11,8 @@ impl Plugin for ThrusterPlugin {
 	fn build(&self, app: &mut App) {
 		app
 			.register_type::<Thruster>()
-			.add_systems(
-				Blueprints,
-				Self::spawn_thruster_visuals.in_set(BlueprintExpansion::ThrusterBlocks),
-			)
+			.register_type::<visuals::ThrusterVisuals>()
+			.register_child_blueprint::<visuals::ThrusterVisuals>()
 			.add_systems(
 				GameLogic,
 				((
@@ -51,6 +49,11 @@ impl Thruster {
 	pub fn set_status(&mut self, status: f32) {
 		self.current_status = status.clamp(0., 1.);
 	}
+
+	/// How many Newtons the thruster pushes with when fully on
+	pub fn get_strength_factor(&self) -> f32 {
+		self.strength_factor
+	}
 }
 
 impl GetBlockId for Thruster {
@@ -65,24 +68,28 @@ mod bundle {
 
 	use crate::prelude::*;
 
-	use super::{blueprint::ThrusterBlockBlueprint, Thruster};
+	use super::{blueprint::ThrusterBlockBlueprint, visuals::ThrusterVisuals, Thruster};
 
 	/// Thruster that is spawned into the world
 	#[derive(Bundle)]
 	pub struct ThrusterBlockBundle {
 		pbr: PbrBundle,
 		collider: AsyncCollider,
+		density: ColliderDensity,
+		integrity: Integrity,
 		// body: RigidBody,
 		name: Name,
+		id: BlockId,
 		thruster: Thruster,
 		internal_force: InternalForce,
+		visuals: ThrusterVisuals,
 	}
 
 	impl Blueprint for BlockBlueprint<ThrusterBlockBlueprint> {
 		type Bundle = ThrusterBlockBundle;
-		type StampSystemParam<'w, 's> = MMA<'w>;
+		type StampSystemParam = MMA<'static>;
 
-		fn stamp(&self, mma: &mut Self::StampSystemParam<'_, '_>) -> Self::Bundle {
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
 			let BlockBlueprint {
 				transform,
 				mesh,
@@ -97,10 +104,14 @@ mod bundle {
 					..default()
 				},
 				collider: AsyncCollider(ComputedCollider::ConvexHull),
+				density: ColliderDensity(specific_marker.tier.density()),
+				integrity: Integrity::new(specific_marker.tier.integrity()),
 				// body: RigidBody::Dynamic,
-				name: Name::new("ThrusterBlock"),
+				name: Name::new(format!("ThrusterBlock {}", specific_marker.tier.name())),
+				id: specific_marker.get_block_id(),
 				thruster: specific_marker.clone().into(),
 				internal_force: InternalForce(Vec3::Z),
+				visuals: ThrusterVisuals,
 			}
 		}
 	}
@@ -170,98 +181,119 @@ mod systems {
 		}
 
 		/// Can change, typically stays constant
-		const ACCELERATION_ATTR: &str = "dynamic_accel";
+		pub(super) const ACCELERATION_ATTR: &str = "dynamic_accel";
 		/// Makes a visual difference in the colour and range of the particles.
 		/// Between 0 (for no lifetime) and 1 (for full lifetime)
-		const LIFETIME_ATTR: &str = "dynamic_lifetime";
+		pub(super) const LIFETIME_ATTR: &str = "dynamic_lifetime";
+	}
+}
 
-		pub(super) fn spawn_thruster_visuals(
-			added_thrusters: Query<Entity, (With<Thruster>, Added<FreshlyExpanded>)>,
-			mut commands: Commands,
-			mut effects: ResMut<Assets<EffectAsset>>,
-		) {
-			for thruster in added_thrusters.iter() {
-				trace!("Expanding thruster visual");
-				commands
-					.entity(thruster)
-					.despawn_descendants()
-					.with_children(|parent| {
-						// debug!("Spawning thruster visuals");
-
-						let mut color_gradient = Gradient::new();
-						color_gradient.add_key(0.0, Vec4::splat(1.0));
-						color_gradient.add_key(0.4, Vec4::new(1.0, 1.0, 0.0, 1.0));
-						color_gradient.add_key(0.7, Vec4::new(1.0, 0.0, 0.0, 1.0));
-						color_gradient.add_key(1.0, Vec4::new(0.2, 0., 0., 1.));
-
-						let mut size_gradient = Gradient::new();
-						size_gradient.add_key(0.0, Vec2::splat(0.1));
-						size_gradient.add_key(0.5, Vec2::splat(0.5));
-						size_gradient.add_key(1.0, Vec2::splat(0.08));
-
-						let writer = ExprWriter::new();
-
-						let age = (writer.lit(1.) - writer.prop(Self::LIFETIME_ATTR)).expr();
-						let init_age1 = SetAttributeModifier::new(Attribute::AGE, age);
-
-						let lifetime = writer.lit(1.).expr();
-						let init_lifetime1 = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
-
-						// Add constant downward acceleration to simulate gravity
-						// let accel1 = writer1.lit(Vec3::Y * -3.).expr();
-						// let update_accel1 = AccelModifier::new(accel1);
-
-						let init_pos1 = SetPositionCone3dModifier {
-							base_radius: writer.lit(PIXEL_SIZE * 0.1).expr(),
-							top_radius: writer.lit(PIXEL_SIZE * 0.7).expr(),
-							height: writer.lit(PIXEL_SIZE * 2.).expr(),
-							dimension: ShapeDimension::Volume,
-						};
-
-						let init_vel1 = SetVelocitySphereModifier {
-							center: writer.lit(Vec3::ZERO).expr(),
-							speed: writer.prop(Self::ACCELERATION_ATTR).expr(),
-						};
-
-						let effect = effects.add(
-							EffectAsset::new(
-								32768,
-								Spawner::rate(500.0.into()).with_starts_active(false),
-								writer.finish(),
-							)
-							.with_name("emit:rate")
-							.with_property(Self::ACCELERATION_ATTR, Value::from(PIXEL_SIZE * 10.))
-							.with_property(Self::LIFETIME_ATTR, Value::from(0.5))
-							// .with_property("my_accel", Vec3::new(0., -3., 0.).into())
-							.init(init_pos1)
-							// Make spawned particles move away from the emitter origin
-							.init(init_vel1)
-							.init(init_age1)
-							.init(init_lifetime1)
-							// .update(update_accel1)
-							.render(ColorOverLifetimeModifier {
-								gradient: color_gradient,
-							})
-							.render(SizeOverLifetimeModifier {
-								gradient: size_gradient,
-								screen_space_size: false,
-							})
-							.render(OrientModifier {
-								mode: OrientMode::ParallelCameraDepthPlane,
-							}),
-						);
+mod visuals {
+	use crate::prelude::*;
 
-						parent.spawn((
-							Name::new("Thruster Visuals"),
-							ParticleEffectBundle {
-								effect: ParticleEffect::new(effect),
-								transform: Transform::from_rotation(Quat::from_rotation_x(-TAU / 4.)),
-								..default()
-							},
-							EffectProperties::default(),
-						));
-					});
-			}
+	use super::ThrusterPlugin;
+
+	/// Placed on thrusters, expands into a particle emitter child.
+	///
+	/// Registered as a child blueprint, since thrusters are themselves
+	/// spawned by the player's blueprint
+	#[derive(Component, Debug, Default, Reflect)]
+	#[reflect(Component)]
+	pub(super) struct ThrusterVisuals;
+
+	impl Blueprint for ThrusterVisuals {
+		type Bundle = ();
+		type StampSystemParam = ResMut<'static, Assets<EffectAsset>>;
+
+		fn stamp(&self, _: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {}
+
+		fn expand(
+			&self,
+			thruster: Entity,
+			commands: &mut Commands,
+			effects: &mut SystemParamItem<'_, '_, Self::StampSystemParam>,
+		) {
+			trace!("Expanding thruster visual");
+			let mut color_gradient = Gradient::new();
+			color_gradient.add_key(0.0, Vec4::splat(1.0));
+			color_gradient.add_key(0.4, Vec4::new(1.0, 1.0, 0.0, 1.0));
+			color_gradient.add_key(0.7, Vec4::new(1.0, 0.0, 0.0, 1.0));
+			color_gradient.add_key(1.0, Vec4::new(0.2, 0., 0., 1.));
+
+			let mut size_gradient = Gradient::new();
+			size_gradient.add_key(0.0, Vec2::splat(0.1));
+			size_gradient.add_key(0.5, Vec2::splat(0.5));
+			size_gradient.add_key(1.0, Vec2::splat(0.08));
+
+			let writer = ExprWriter::new();
+
+			let age = (writer.lit(1.) - writer.prop(ThrusterPlugin::LIFETIME_ATTR)).expr();
+			let init_age1 = SetAttributeModifier::new(Attribute::AGE, age);
+
+			let lifetime = writer.lit(1.).expr();
+			let init_lifetime1 = SetAttributeModifier::new(Attribute::LIFETIME, lifetime);
+
+			// Add constant downward acceleration to simulate gravity
+			// let accel1 = writer1.lit(Vec3::Y * -3.).expr();
+			// let update_accel1 = AccelModifier::new(accel1);
+
+			let init_pos1 = SetPositionCone3dModifier {
+				base_radius: writer.lit(PIXEL_SIZE * 0.1).expr(),
+				top_radius: writer.lit(PIXEL_SIZE * 0.7).expr(),
+				height: writer.lit(PIXEL_SIZE * 2.).expr(),
+				dimension: ShapeDimension::Volume,
+			};
+
+			let init_vel1 = SetVelocitySphereModifier {
+				center: writer.lit(Vec3::ZERO).expr(),
+				speed: writer.prop(ThrusterPlugin::ACCELERATION_ATTR).expr(),
+			};
+
+			let effect = effects.add(
+				EffectAsset::new(
+					32768,
+					Spawner::rate(500.0.into()).with_starts_active(false),
+					writer.finish(),
+				)
+				.with_name("emit:rate")
+				.with_property(
+					ThrusterPlugin::ACCELERATION_ATTR,
+					Value::from(PIXEL_SIZE * 10.),
+				)
+				.with_property(ThrusterPlugin::LIFETIME_ATTR, Value::from(0.5))
+				// .with_property("my_accel", Vec3::new(0., -3., 0.).into())
+				.init(init_pos1)
+				// Make spawned particles move away from the emitter origin
+				.init(init_vel1)
+				.init(init_age1)
+				.init(init_lifetime1)
+				// .update(update_accel1)
+				.render(ColorOverLifetimeModifier {
+					gradient: color_gradient,
+				})
+				.render(SizeOverLifetimeModifier {
+					gradient: size_gradient,
+					screen_space_size: false,
+				})
+				.render(OrientModifier {
+					mode: OrientMode::ParallelCameraDepthPlane,
+				}),
+			);
+
+			commands
+				.entity(thruster)
+				.despawn_descendants()
+				.with_children(|parent| {
+					parent.spawn((
+						Name::new("Thruster Visuals"),
+						ParticleEffectBundle {
+							effect: ParticleEffect::new(effect),
+							transform: Transform::from_rotation(Quat::from_rotation_x(-TAU / 4.)),
+							..default()
+						},
+						EffectProperties::default(),
+					));
+				});
 		}
 	}
 }
@@ -274,17 +306,75 @@ mod blueprint {
 	/// Builder for [ThrusterBlockBundle].
 	///
 	/// Will spawn a particle emitter as a child
-	#[derive(Debug, Reflect, Serialize, Deserialize, Clone)]
+	#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
 	pub struct ThrusterBlockBlueprint {
-		id: BlockId,
+		pub(crate) id: BlockId,
 		strength: f32,
+		/// Defaulted, since older ship files don't have one
+		#[serde(default)]
+		pub(crate) tier: ThrusterTier,
+	}
+
+	/// Stronger thrusters are heavier or more expensive to fabricate
+	#[derive(
+		Debug, Default, Reflect, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, IntoStaticStr,
+	)]
+	pub enum ThrusterTier {
+		#[default]
+		Standard,
+		/// Much stronger, but also much heavier
+		Heavy,
+		/// Stronger without the weight, but expensive
+		Fusion,
+	}
+
+	impl ThrusterTier {
+		pub fn name(&self) -> &'static str {
+			self.into()
+		}
+
+		/// How many Newtons a thruster of this tier pushes with when fully on
+		pub fn strength(&self) -> f32 {
+			match self {
+				Self::Standard => 10.,
+				Self::Heavy => 25.,
+				Self::Fusion => 30.,
+			}
+		}
+
+		/// Relative to the default [ColliderDensity] of 1
+		pub fn density(&self) -> f32 {
+			match self {
+				Self::Standard => 1.,
+				Self::Heavy => 4.,
+				Self::Fusion => 1.5,
+			}
+		}
+
+		/// How much damage a thruster of this tier takes before it is destroyed, see [Integrity]
+		pub fn integrity(&self) -> f32 {
+			match self {
+				Self::Standard => 60.,
+				Self::Heavy => 150.,
+				Self::Fusion => 80.,
+			}
+		}
+
+		fn colour(&self) -> Color {
+			match self {
+				Self::Standard => Color::RED,
+				Self::Heavy => Color::MAROON,
+				Self::Fusion => Color::ORANGE_RED,
+			}
+		}
 	}
 
 	impl ThrusterBlockBlueprint {
-		pub fn new() -> Self {
+		pub fn new(tier: ThrusterTier) -> Self {
 			Self {
 				id: BlockId::random(),
-				strength: 10.,
+				strength: tier.strength(),
+				tier,
 			}
 		}
 	}
@@ -296,7 +386,7 @@ mod blueprint {
 	}
 
 	impl From<ThrusterBlockBlueprint> for Thruster {
-		fn from(ThrusterBlockBlueprint { id, strength }: ThrusterBlockBlueprint) -> Self {
+		fn from(ThrusterBlockBlueprint { id, strength, .. }: ThrusterBlockBlueprint) -> Self {
 			Thruster {
 				block_id: id,
 				strength_factor: strength,
@@ -309,6 +399,14 @@ mod blueprint {
 		pub fn new_thruster(
 			location: impl Into<manual_builder::RelativePixel>,
 			facing: impl Into<Quat>,
+		) -> Self {
+			Self::new_thruster_tier(ThrusterTier::Standard, location, facing)
+		}
+
+		pub fn new_thruster_tier(
+			tier: ThrusterTier,
+			location: impl Into<manual_builder::RelativePixel>,
+			facing: impl Into<Quat>,
 		) -> Self {
 			let rotation = facing.into();
 			BlockBlueprint {
@@ -321,9 +419,20 @@ mod blueprint {
 				mesh: OptimizableMesh::CustomRectangularPrism {
 					size: Vec3::splat(PIXEL_SIZE / 2.),
 				},
-				material: OptimizableMaterial::OpaqueColour(Color::RED),
-				specific_marker: ThrusterBlockBlueprint::new(),
+				material: OptimizableMaterial::OpaqueColour(tier.colour()),
+				specific_marker: ThrusterBlockBlueprint::new(tier),
 			}
 		}
+
+		pub fn location(&self) -> manual_builder::RelativePixel {
+			Self::location_of(&self.transform)
+		}
+
+		/// Thrusters sit at the back of their pixel, see [Self::new_thruster]
+		pub(crate) fn location_of(transform: &Transform) -> manual_builder::RelativePixel {
+			manual_builder::RelativePixel::from_world_offset(
+				transform.translation + transform.forward() * PIXEL_SIZE / 2.,
+			)
+		}
 	}
 }
@@ -0,0 +1,402 @@
+//! Weapons fire projectiles out of their front while
+//! [PlayerInput::Fire](crate::players::player_movement::PlayerInput::Fire) is held.
+//!
+//! Combat is server authoritative: only the server spawns and moves projectiles,
+//! which are replicated to clients like any other blueprint.
+//! Each step a projectile casts a ray along the distance it travels,
+//! and damages the [Integrity] of the first block or terrain item it hits.
+
+use crate::prelude::*;
+
+pub use api::*;
+pub use blueprint::{ProjectileBlueprint, WeaponBlockBlueprint};
+
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+	fn build(&self, app: &mut App) {
+		replicate_marked!(app, blueprint::ProjectileBlueprint);
+
+		app
+			.register_type::<Weapon>()
+			.register_type::<blueprint::ProjectileBlueprint>()
+			.register_blueprint::<blueprint::ProjectileBlueprint>()
+			.add_systems(
+				FixedUpdate,
+				(Self::fire_weapons, Self::move_projectiles)
+					.chain()
+					.in_set(GlobalSystemSet::ExecuteGameLogic)
+					.run_if(resource_exists::<NetcodeConfig>().and_then(NetcodeConfig::has_authority())),
+			);
+	}
+}
+
+mod api {
+	use crate::prelude::*;
+
+	/// Component for all weapons (on a player)
+	#[derive(Debug, Component, Reflect)]
+	pub struct Weapon {
+		pub(super) block_id: BlockId,
+		/// Finishes once the weapon can fire again
+		pub(super) cooldown: Timer,
+		pub(super) damage: f32,
+		pub(super) projectile_speed: f32,
+	}
+
+	impl Weapon {
+		pub const INTEGRITY: f32 = 90.;
+	}
+
+	impl GetBlockId for Weapon {
+		fn get_block_id(&self) -> BlockId {
+			self.block_id
+		}
+	}
+
+	/// Server side state of a projectile, see [crate::players::weapon_block::ProjectileBlueprint]
+	#[derive(Debug, Component)]
+	pub struct Projectile {
+		/// Its own blocks are never hit
+		pub(super) shooter: Entity,
+		pub(super) velocity: Vec3,
+		pub(super) damage: f32,
+		/// Despawned once this finishes, if it didn't hit anything
+		pub(super) lifetime: Timer,
+	}
+
+	impl Projectile {
+		pub const LIFETIME: Duration = Duration::from_secs(3);
+	}
+}
+
+mod bundle {
+	use crate::prelude::*;
+
+	use super::{
+		blueprint::{ProjectileBlueprint, WeaponBlockBlueprint},
+		Weapon,
+	};
+
+	/// Weapon that is spawned into the world
+	#[derive(Bundle)]
+	pub struct WeaponBlockBundle {
+		pbr: PbrBundle,
+		collider: AsyncCollider,
+		integrity: Integrity,
+		name: Name,
+		id: BlockId,
+		weapon: Weapon,
+	}
+
+	impl Blueprint for BlockBlueprint<WeaponBlockBlueprint> {
+		type Bundle = WeaponBlockBundle;
+		type StampSystemParam = MMA<'static>;
+
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
+			let BlockBlueprint {
+				transform,
+				mesh,
+				material,
+				specific_marker,
+			} = self;
+			Self::Bundle {
+				pbr: PbrBundle {
+					transform: *transform,
+					mesh: mesh.clone().into_mesh(mma),
+					material: material.clone().into_material(&mut mma.mats),
+					..default()
+				},
+				collider: AsyncCollider(ComputedCollider::ConvexHull),
+				integrity: Integrity::new(Weapon::INTEGRITY),
+				name: Name::new("WeaponBlock"),
+				id: specific_marker.get_block_id(),
+				weapon: specific_marker.clone().into(),
+			}
+		}
+	}
+
+	/// The visuals of a projectile, which has no collider of its own
+	#[derive(Bundle)]
+	pub struct ProjectileBundle {
+		pbr: PbrBundle,
+		name: Name,
+	}
+
+	impl Blueprint for ProjectileBlueprint {
+		type Bundle = ProjectileBundle;
+		type StampSystemParam = MMA<'static>;
+
+		fn stamp(&self, mma: &mut SystemParamItem<'_, '_, Self::StampSystemParam>) -> Self::Bundle {
+			Self::Bundle {
+				pbr: PbrBundle {
+					transform: self.transform,
+					mesh: OptimizableMesh::Sphere {
+						radius: PIXEL_SIZE / 8.,
+					}
+					.into_mesh(mma),
+					material: OptimizableMaterial::OpaqueColour(Color::ORANGE_RED)
+						.into_material(&mut mma.mats),
+					..default()
+				},
+				name: Name::new("Projectile"),
+			}
+		}
+	}
+}
+
+mod systems {
+	use crate::{players::player_movement::PlayerInput, prelude::*};
+
+	use super::{blueprint::ProjectileBlueprint, Projectile, Weapon, WeaponPlugin};
+
+	impl WeaponPlugin {
+		/// Fires every weapon that is ready on the ships of the players holding [PlayerInput::Fire]
+		pub(super) fn fire_weapons(
+			mut commands: Commands,
+			players: Query<(
+				Entity,
+				&ActionState<PlayerInput>,
+				&Children,
+				&LinearVelocity,
+			)>,
+			mut weapons: Query<(&mut Weapon, &GlobalTransform)>,
+			time: Res<Time>,
+		) {
+			for (mut weapon, _) in weapons.iter_mut() {
+				weapon.cooldown.tick(time.delta());
+			}
+
+			for (player, inputs, blocks, linvel) in players.iter() {
+				if !inputs.pressed(PlayerInput::Fire) {
+					continue;
+				}
+				let mut player_weapons = weapons.iter_many_mut(blocks.iter());
+				while let Some((mut weapon, weapon_transform)) = player_weapons.fetch_next() {
+					if !weapon.cooldown.finished() {
+						continue;
+					}
+					weapon.cooldown.reset();
+
+					let forward = weapon_transform.forward();
+					let mut transform = weapon_transform.compute_transform();
+					transform.translation += forward * PIXEL_SIZE / 2.;
+					commands.spawn((
+						ProjectileBlueprint { transform },
+						Projectile {
+							shooter: player,
+							// inherits the ship's velocity, so moving ships don't outrun their own shots
+							velocity: linvel.0 + forward * weapon.projectile_speed,
+							damage: weapon.damage,
+							lifetime: Timer::new(Projectile::LIFETIME, TimerMode::Once),
+						},
+						Replication,
+					));
+				}
+			}
+		}
+
+		/// Moves every projectile, damaging the first thing with [Integrity] it would pass through
+		pub(super) fn move_projectiles(
+			mut commands: Commands,
+			mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+			ships: Query<&Children>,
+			mut targets: Query<&mut Integrity>,
+			spatial_query: SpatialQuery,
+			time: Res<Time>,
+		) {
+			for (projectile, mut state, mut transform) in projectiles.iter_mut() {
+				if state.lifetime.tick(time.delta()).finished() {
+					commands.entity(projectile).despawn_recursive();
+					continue;
+				}
+
+				let step = state.velocity * time.delta_seconds();
+				let Some(direction) = step.try_normalize() else {
+					continue;
+				};
+				let not_own_blocks = SpatialQueryFilter::new().without_entities(
+					ships
+						.get(state.shooter)
+						.into_iter()
+						.flat_map(|blocks| blocks.iter().copied()),
+				);
+				let hit = spatial_query.cast_ray(
+					transform.translation,
+					direction,
+					step.length(),
+					true,
+					not_own_blocks,
+				);
+				let Some(hit) = hit else {
+					transform.translation += step;
+					continue;
+				};
+
+				if let Ok(mut integrity) = targets.get_mut(hit.entity) {
+					if integrity.damage(state.damage) {
+						trace!("Block {:?} was destroyed by a projectile", hit.entity);
+					}
+				}
+				commands.entity(projectile).despawn_recursive();
+			}
+		}
+	}
+}
+
+mod blueprint {
+	use crate::prelude::*;
+
+	use super::Weapon;
+
+	/// Builder for [WeaponBlockBundle]
+	#[derive(Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
+	pub struct WeaponBlockBlueprint {
+		pub(crate) id: BlockId,
+		/// Shots per second
+		fire_rate: f32,
+		/// Done to the [Integrity] of whatever is hit
+		damage: f32,
+		/// Relative to the ship that fired
+		projectile_speed: f32,
+	}
+
+	impl WeaponBlockBlueprint {
+		pub fn new() -> Self {
+			Self {
+				id: BlockId::random(),
+				fire_rate: 4.,
+				damage: 20.,
+				projectile_speed: 40.,
+			}
+		}
+	}
+
+	impl GetBlockId for WeaponBlockBlueprint {
+		fn get_block_id(&self) -> BlockId {
+			self.id
+		}
+	}
+
+	impl From<WeaponBlockBlueprint> for Weapon {
+		fn from(
+			WeaponBlockBlueprint {
+				id,
+				fire_rate,
+				damage,
+				projectile_speed,
+			}: WeaponBlockBlueprint,
+		) -> Self {
+			let mut cooldown = Timer::from_seconds(1. / fire_rate.max(0.01), TimerMode::Once);
+			// ready to fire as soon as it is placed
+			cooldown.tick(cooldown.duration());
+			Weapon {
+				block_id: id,
+				cooldown,
+				damage,
+				projectile_speed,
+			}
+		}
+	}
+
+	impl BlockBlueprint<WeaponBlockBlueprint> {
+		/// The weapon fires in the direction it is `facing`
+		pub fn new_weapon(
+			location: impl Into<manual_builder::RelativePixel>,
+			facing: impl Into<Quat>,
+		) -> Self {
+			BlockBlueprint {
+				transform: Transform::from_translation(location.into().into_world_offset())
+					.with_rotation(facing.into()),
+				mesh: OptimizableMesh::CustomRectangularPrism {
+					size: Vec3::new(PIXEL_SIZE / 3., PIXEL_SIZE / 3., PIXEL_SIZE),
+				},
+				material: OptimizableMaterial::OpaqueColour(Color::MAROON),
+				specific_marker: WeaponBlockBlueprint::new(),
+			}
+		}
+
+		pub fn location(&self) -> manual_builder::RelativePixel {
+			manual_builder::RelativePixel::from_world_offset(self.transform.translation)
+		}
+	}
+
+	/// A projectile in flight, replicated so that clients can see it.
+	///
+	/// Only the server moves it, see [super::Projectile]
+	#[derive(Component, Debug, Reflect, Serialize, Deserialize, Clone, PartialEq)]
+	#[reflect(Component)]
+	pub struct ProjectileBlueprint {
+		pub transform: Transform,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::prelude::*;
+
+	use super::{blueprint::WeaponBlockBlueprint, Projectile, Weapon, WeaponPlugin};
+
+	#[test]
+	fn fires_at_its_fire_rate() {
+		let mut weapon = Weapon::from(WeaponBlockBlueprint::new());
+		assert!(weapon.cooldown.finished());
+
+		weapon.cooldown.reset();
+		weapon.cooldown.tick(Duration::from_secs_f32(0.2));
+		assert!(!weapon.cooldown.finished());
+		weapon.cooldown.tick(Duration::from_secs_f32(0.1));
+		assert!(weapon.cooldown.finished());
+	}
+
+	#[test]
+	fn projectiles_damage_what_they_hit_but_not_their_shooter() {
+		let mut app = test_app();
+		app.add_plugins(PhysicsPlugins::default());
+
+		let block = |x: f32| {
+			(
+				TransformBundle::from_transform(Transform::from_xyz(x, 0., 0.)),
+				Collider::cuboid(1., 1., 1.),
+				Integrity::new(100.),
+			)
+		};
+		let mut own_block = None;
+		let shooter = app
+			.world
+			.spawn((SpatialBundle::default(), RigidBody::Static))
+			.with_children(|ship| own_block = Some(ship.spawn(block(2.)).id()))
+			.id();
+		let own_block = own_block.unwrap();
+		let target = app.world.spawn((block(4.), RigidBody::Static)).id();
+		let projectile = app
+			.world
+			.spawn((
+				TransformBundle::default(),
+				Projectile {
+					shooter,
+					velocity: Vec3::X * 10.,
+					damage: 20.,
+					lifetime: Timer::new(Projectile::LIFETIME, TimerMode::Once),
+				},
+			))
+			.id();
+		// fills the spatial query pipeline with the colliders
+		app.update();
+		app.update();
+
+		let mut time = Time::<()>::default();
+		time.advance_by(Duration::from_secs(1));
+		app.world.insert_resource(time);
+		app.world.run_system_once(WeaponPlugin::move_projectiles);
+
+		let mut damaged = Integrity::new(100.);
+		damaged.damage(20.);
+		assert_eq!(app.world.get::<Integrity>(target), Some(&damaged));
+		assert_eq!(
+			app.world.get::<Integrity>(own_block),
+			Some(&Integrity::new(100.))
+		);
+		assert!(app.world.get_entity(projectile).is_none());
+	}
+}
@@ -38,8 +38,10 @@ pub use strum::IntoEnumIterator as _;
 // pub use uuid::Uuid;
 pub use clap::Parser;
 pub use rand::random;
+pub use rand::rngs::StdRng;
 pub use rand::rngs::ThreadRng;
 pub use rand::Rng;
+pub use rand::SeedableRng;
 pub use serde::de::DeserializeOwned;
 pub use serde::{Deserialize, Serialize};
 pub use strum::IntoStaticStr;
@@ -53,6 +55,9 @@ pub use crate::global::blueprints::*;
 pub use crate::global::*;
 
 pub use crate::netcode::*;
+pub use crate::persistence::{
+	PersistAppExt, PersistentBundle, RestoreWorld, SaveWorld, WorldStore,
+};
 
 pub use crate::states::*;
 
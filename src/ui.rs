@@ -8,12 +8,15 @@ impl PluginGroup for UiPlugins {
 		PluginGroupBuilder::start::<Self>()
 			.add(self::start_screen::StartScreen)
 			.add(self::ui_cameras::UiCamerasPlugin)
+			.add(self::join_rejected::JoinRejectedNotice)
 			.build()
 	}
 }
 
 mod ui_cameras;
 
+mod join_rejected;
+
 mod manual_ui {
 	use crate::prelude::*;
 
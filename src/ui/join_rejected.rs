@@ -0,0 +1,51 @@
+//! Tells the player why the server wouldn't let them join, see [JoinRejected]
+
+use crate::prelude::*;
+
+/// Plugin
+pub struct JoinRejectedNotice;
+
+impl Plugin for JoinRejectedNotice {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Update,
+			Self::show_rejection.run_if(resource_added::<JoinRejected>()),
+		);
+	}
+}
+
+/// Marks the text showing why joining was rejected
+#[derive(Component)]
+struct RejectionText;
+
+impl JoinRejectedNotice {
+	const CAM: UiCameras = UiCameras::Center;
+
+	fn show_rejection(
+		mut commands: Commands,
+		rejected: Res<JoinRejected>,
+		existing: Query<Entity, With<RejectionText>>,
+		ass: Res<AssetServer>,
+	) {
+		for text in existing.iter() {
+			commands.entity(text).despawn_recursive();
+		}
+
+		let style = TextStyle {
+			font: ass.load(GlobalFont::Default),
+			font_size: 30.,
+			color: Color::ORANGE_RED,
+		};
+		commands
+			.spawn((
+				Text2dBundle {
+					text: Text::from_section(format!("Couldn't join: {}", **rejected), style)
+						.with_alignment(TextAlignment::Center),
+					..default()
+				},
+				RejectionText,
+				Name::new("Join Rejected Text"),
+			))
+			.render_layer(GlobalRenderLayers::Ui(Self::CAM));
+	}
+}
@@ -112,6 +112,7 @@ impl HostGameButtons {
 enum ClientGameButtons {
 	// PublicGame,
 	MachineLocalGame,
+	OfflineMachineLocalGame,
 }
 
 impl ClientGameButtons {
@@ -119,6 +120,7 @@ impl ClientGameButtons {
 		match self {
 			// ClientGameButtons::HostPublicGame => "Host Public Game",
 			ClientGameButtons::MachineLocalGame => "Join Machine-Local Game",
+			ClientGameButtons::OfflineMachineLocalGame => "Play Offline, Sync With Machine-Local Game",
 		}
 	}
 }
@@ -358,6 +360,9 @@ impl StartScreen {
 					global_state.set(GlobalGameStates::InGame);
 					commands.insert_resource(match btn {
 						ClientGameButtons::MachineLocalGame => NetcodeConfig::new_client_machine_local(),
+						ClientGameButtons::OfflineMachineLocalGame => {
+							NetcodeConfig::new_offline_machine_local()
+						}
 					});
 				}
 			} else {
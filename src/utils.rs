@@ -129,16 +129,6 @@ mod polar_tests {
 	}
 }
 
-pub fn vec3_polar_random(rng: &mut ThreadRng) -> Vec3 {
-	let phi = rng.gen_range(0. ..TAU);
-	let z: f32 = rng.gen_range(-1. ..1.);
-	let theta = z.acos();
-
-	let ret = vec3_polar(theta, phi);
-
-	ret.normalize()
-}
-
 pub trait GetNetworkId {
 	fn get_network_id(&self) -> ClientId;
 }